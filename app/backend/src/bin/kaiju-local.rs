@@ -0,0 +1,240 @@
+//! Local development server.
+//!
+//! Serves the same route table as the Lambda function over plain HTTP, so the frontend
+//! can talk to the Rust backend without deploying it. Every request is converted to the
+//! `ApiGatewayProxyRequest` API Gateway would send and passed to `function_handler`.
+//!
+//! ```text
+//! KAIJU_LOCAL_ADDR=127.0.0.1:3000 cargo run --bin kaiju-local
+//! ```
+
+use std::convert::Infallible;
+use std::fmt::Display;
+use std::net::SocketAddr;
+
+use aws_lambda_events::encodings::Body as LambdaBody;
+use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
+use aws_lambda_events::query_map::QueryMap;
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Bytes, Incoming};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use lambda_runtime::{Context, Error, LambdaEvent};
+use tokio::net::TcpListener;
+use tracing::{error, info};
+
+use kaiju_academy_backend::{common, function_handler};
+
+/// Address used when `KAIJU_LOCAL_ADDR` is not set, matching the port the frontend proxy expects
+const DEFAULT_ADDR: &str = "127.0.0.1:3000";
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    // Load and validate configuration, refusing to start with insecure production settings
+    let config = common::config::init()?;
+    common::keys::init()?;
+    common::password::init()?;
+
+    // Initialize tracing
+    common::logger::init_tracing(config);
+
+    let addr: SocketAddr = std::env::var("KAIJU_LOCAL_ADDR")
+        .unwrap_or_else(|_| DEFAULT_ADDR.to_string())
+        .parse()?;
+
+    // Connect up front so a misconfigured database shows up immediately
+    if let Err(e) = common::db::init_db().await {
+        error!("Database not reachable yet, handlers will retry: {}", e);
+    }
+
+    let listener = TcpListener::bind(addr).await?;
+    info!("Kaiju Academy local server listening on http://{}", addr);
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+
+        tokio::spawn(async move {
+            let service = service_fn(move |request| serve(request, peer));
+
+            if let Err(e) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                error!("Connection error from {}: {}", peer, e);
+            }
+        });
+    }
+}
+
+/// Run one HTTP request through the Lambda handler
+async fn serve(request: Request<Incoming>, peer: SocketAddr) -> Result<Response<Full<Bytes>>, Infallible> {
+    let event = match to_proxy_request(request, peer).await {
+        Ok(event) => event,
+        Err(message) => return Ok(plain_response(StatusCode::BAD_REQUEST, message)),
+    };
+
+    let mut context = Context::default();
+    context.request_id = uuid::Uuid::new_v4().to_string();
+
+    match function_handler(LambdaEvent::new(event, context)).await {
+        Ok(response) => Ok(from_proxy_response(response)),
+        Err(e) => {
+            error!("Handler failed: {}", e);
+            Ok(plain_response(StatusCode::INTERNAL_SERVER_ERROR, "Internal server error".to_string()))
+        }
+    }
+}
+
+/// Build the event API Gateway would deliver for this request.
+/// Path parameters are filled in by the router once it has matched a route template.
+async fn to_proxy_request<B>(request: Request<B>, peer: SocketAddr) -> Result<ApiGatewayProxyRequest, String>
+where
+    B: hyper::body::Body,
+    B::Error: Display,
+{
+    let (parts, body) = request.into_parts();
+
+    let body = body
+        .collect()
+        .await
+        .map_err(|e| format!("Failed to read request body: {}", e))?
+        .to_bytes();
+    let body = if body.is_empty() {
+        None
+    } else {
+        Some(String::from_utf8(body.to_vec()).map_err(|_| "Request body must be UTF-8".to_string())?)
+    };
+
+    let query: QueryMap = parts.uri.query().unwrap_or_default().parse().unwrap_or_default();
+
+    let path = Some(parts.uri.path().to_string());
+    let mut event = ApiGatewayProxyRequest {
+        http_method: parts.method.clone(),
+        path: path.clone(),
+        headers: parts.headers,
+        query_string_parameters: query.clone(),
+        multi_value_query_string_parameters: query,
+        body,
+        ..Default::default()
+    };
+    event.request_context.http_method = parts.method;
+    event.request_context.path = path;
+    event.request_context.stage = Some("local".to_string());
+    event.request_context.request_id = Some(uuid::Uuid::new_v4().to_string());
+    event.request_context.identity.source_ip = Some(peer.ip().to_string());
+
+    Ok(event)
+}
+
+/// Turn the handler's API Gateway response back into an HTTP response
+fn from_proxy_response(response: ApiGatewayProxyResponse) -> Response<Full<Bytes>> {
+    let status = u16::try_from(response.status_code)
+        .ok()
+        .and_then(|code| StatusCode::from_u16(code).ok())
+        .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+
+    let body = match response.body {
+        Some(LambdaBody::Text(text)) => Bytes::from(text),
+        Some(LambdaBody::Binary(bytes)) => Bytes::from(bytes),
+        Some(LambdaBody::Empty) | None => Bytes::new(),
+    };
+
+    let mut http_response = Response::new(Full::new(body));
+    *http_response.status_mut() = status;
+
+    let headers = http_response.headers_mut();
+    headers.extend(response.multi_value_headers);
+    headers.extend(response.headers);
+
+    http_response
+}
+
+/// Response for requests that never reached a handler
+fn plain_response(status: StatusCode, message: String) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(message)));
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use http::{HeaderValue, Method};
+
+    use super::*;
+
+    fn peer() -> SocketAddr {
+        "192.0.2.7:51234".parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn requests_become_proxy_events() {
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/courses/abc/enroll?tag=rust&tag=wasm&limit=5")
+            .header("Authorization", "Bearer abc.def")
+            .body(Full::new(Bytes::from(r#"{"ok":true}"#)))
+            .unwrap();
+
+        let event = to_proxy_request(request, peer()).await.unwrap();
+
+        assert_eq!(event.http_method, Method::POST);
+        assert_eq!(event.path.as_deref(), Some("/courses/abc/enroll"));
+        assert_eq!(event.query_string_parameters.first("limit"), Some("5"));
+        assert_eq!(event.multi_value_query_string_parameters.all("tag"), Some(vec!["rust", "wasm"]));
+        assert_eq!(event.headers["Authorization"], "Bearer abc.def");
+        assert_eq!(event.body.as_deref(), Some(r#"{"ok":true}"#));
+        assert_eq!(event.request_context.http_method, Method::POST);
+        assert_eq!(event.request_context.identity.source_ip.as_deref(), Some("192.0.2.7"));
+        assert!(event.path_parameters.is_empty());
+    }
+
+    #[tokio::test]
+    async fn empty_bodies_are_absent() {
+        let request = Request::builder().uri("/courses").body(Full::new(Bytes::new())).unwrap();
+
+        let event = to_proxy_request(request, peer()).await.unwrap();
+
+        assert_eq!(event.http_method, Method::GET);
+        assert_eq!(event.body, None);
+        assert!(event.query_string_parameters.is_empty());
+    }
+
+    #[tokio::test]
+    async fn non_utf8_bodies_are_refused() {
+        let request = Request::builder().uri("/courses").body(Full::new(Bytes::from_static(b"\xff\xfe"))).unwrap();
+
+        assert_eq!(to_proxy_request(request, peer()).await.unwrap_err(), "Request body must be UTF-8");
+    }
+
+    #[tokio::test]
+    async fn proxy_responses_become_http_responses() {
+        let mut response = ApiGatewayProxyResponse {
+            status_code: 201,
+            body: Some(LambdaBody::Text(r#"{"id":"abc"}"#.to_string())),
+            ..Default::default()
+        };
+        response.headers.insert("Content-Type", HeaderValue::from_static("application/json"));
+        response.multi_value_headers.insert("X-Request-Id", HeaderValue::from_static("req-1"));
+
+        let response = from_proxy_response(response);
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()["Content-Type"], "application/json");
+        assert_eq!(response.headers()["X-Request-Id"], "req-1");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, Bytes::from(r#"{"id":"abc"}"#));
+    }
+
+    #[tokio::test]
+    async fn invalid_status_codes_become_server_errors() {
+        let response = from_proxy_response(ApiGatewayProxyResponse {
+            status_code: 42,
+            ..Default::default()
+        });
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(response.into_body().collect().await.unwrap().to_bytes().is_empty());
+    }
+}
@@ -0,0 +1,56 @@
+//! Schema migration runner.
+//!
+//! Connects with the same `SURREALDB_*` settings as the Lambda function.
+//!
+//! ```text
+//! cargo run --bin kaiju-migrate -- status   # list migrations and whether they are applied
+//! cargo run --bin kaiju-migrate -- up       # apply every pending migration
+//! ```
+
+use lambda_runtime::Error;
+
+use kaiju_academy_backend::{common, migrations};
+
+const USAGE: &str = "Usage: kaiju-migrate <up|status>";
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    // Load and validate configuration, refusing to start with insecure production settings
+    let config = common::config::init()?;
+
+    // Initialize tracing
+    common::logger::init_tracing(config);
+
+    let command = std::env::args().nth(1);
+    let db = common::db::get_db_client().await?;
+
+    match command.as_deref() {
+        Some("status") => {
+            for status in migrations::status(&db).await? {
+                let state = match status.applied_at {
+                    Some(at) => format!("applied {}", at.to_rfc3339()),
+                    None => "pending".to_string(),
+                };
+                println!("{:04} {:<32} {}", status.migration.version, status.migration.name, state);
+            }
+        }
+        Some("up") => {
+            let ran = migrations::up(&db).await?;
+            if ran.is_empty() {
+                println!("Database is up to date");
+            }
+            for migration in ran {
+                println!("Applied {:04} {}", migration.version, migration.name);
+            }
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    }
+
+    // Send the migration's database spans before the process exits
+    common::telemetry::flush().await;
+
+    Ok(())
+}
@@ -0,0 +1,102 @@
+//! Audit trail of privileged and destructive actions.
+//!
+//! Handlers call [`record`] after a change has been written. The action has already
+//! happened by then, so a failure to write the audit event is logged rather than returned.
+
+use aws_lambda_events::event::apigw::ApiGatewayProxyRequest;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use surrealdb::sql::Thing;
+use tracing::error;
+
+use crate::common::extract::{self, AuthContext};
+use crate::models::audit::{AuditAction, AuditEvent};
+use crate::repository::AuditRepository;
+
+/// Fields that change on every write and say nothing about what the action did
+const IGNORED_FIELDS: &[&str] = &["updated_at", "updated_by"];
+
+/// Append an event for `action` by the caller on `target`, with the fields that changed
+/// between `before` and `after`
+pub async fn record<T: Serialize>(
+    repo: &impl AuditRepository,
+    request: &ApiGatewayProxyRequest,
+    auth: &AuthContext,
+    action: AuditAction,
+    target: &Thing,
+    before: Option<&T>,
+    after: Option<&T>,
+) {
+    let (before, after) = diff(before.map(to_value), after.map(to_value));
+
+    let mut event = AuditEvent::new(auth.user.clone(), action, target.clone());
+    event.before = before;
+    event.after = after;
+    event.ip = extract::client_info(request).ip_address;
+    event.request_id = request.request_context.request_id.clone();
+
+    if let Err(err) = repo.record_audit_event(event).await {
+        error!("Failed to record audit event {:?} on {}: {}", action, target, err);
+    }
+}
+
+fn to_value<T: Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+/// Reduce two snapshots of a record to the fields that differ.
+///
+/// A missing snapshot, as for a creation or deletion, keeps the other one whole.
+pub fn diff(before: Option<Value>, after: Option<Value>) -> (Option<Value>, Option<Value>) {
+    match (before, after) {
+        (Some(Value::Object(before)), Some(Value::Object(after))) => {
+            let mut changed_before = Map::new();
+            let mut changed_after = Map::new();
+
+            let keys = before.keys().chain(after.keys().filter(|key| !before.contains_key(*key)));
+            for key in keys {
+                if IGNORED_FIELDS.contains(&key.as_str()) || before.get(key) == after.get(key) {
+                    continue;
+                }
+                changed_before.insert(key.clone(), before.get(key).cloned().unwrap_or(Value::Null));
+                changed_after.insert(key.clone(), after.get(key).cloned().unwrap_or(Value::Null));
+            }
+
+            (Some(Value::Object(changed_before)), Some(Value::Object(changed_after)))
+        }
+        (before, after) => (before.map(without_ignored), after.map(without_ignored)),
+    }
+}
+
+fn without_ignored(mut value: Value) -> Value {
+    if let Value::Object(fields) = &mut value {
+        fields.retain(|key, _| !IGNORED_FIELDS.contains(&key.as_str()));
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn updates_keep_only_changed_fields() {
+        let before = json!({"title": "Rust", "is_published": false, "tags": ["a"], "updated_at": 1});
+        let after = json!({"title": "Rust", "is_published": true, "tags": ["a", "b"], "updated_at": 2, "thumbnail": "x.png"});
+
+        let (before, after) = diff(Some(before), Some(after));
+
+        assert_eq!(before.unwrap(), json!({"is_published": false, "tags": ["a"], "thumbnail": null}));
+        assert_eq!(after.unwrap(), json!({"is_published": true, "tags": ["a", "b"], "thumbnail": "x.png"}));
+    }
+
+    #[test]
+    fn creations_and_deletions_keep_the_whole_record() {
+        let course = json!({"title": "Rust", "updated_at": 1});
+
+        assert_eq!(diff(None, Some(course.clone())), (None, Some(json!({"title": "Rust"}))));
+        assert_eq!(diff(Some(course), None), (Some(json!({"title": "Rust"})), None));
+    }
+}
@@ -1,7 +1,20 @@
-use crate::common::error::AppError;
-use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use crate::common::config::{Config, CONFIG};
+use crate::common::error::{AppError, ErrorCode};
+use crate::common::keys::KEYS;
+use crate::models::session::{ClientInfo, Session};
+use crate::models::token::{PersonalToken, RefreshToken, TokenPair};
+use crate::repository::{record_id, TokenRepository};
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{self, PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use jsonwebtoken::{decode, decode_header, encode, Header, Validation};
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use surrealdb::sql::Thing;
+use tracing::error;
 
 // JWT claims structure
 #[derive(Debug, Serialize, Deserialize)]
@@ -10,59 +23,385 @@ pub struct Claims {
     pub role: String,     // User role: "admin", "educator", "student", "moderator"
     pub exp: u64,         // Expiration time
     pub iat: u64,         // Issued at
+    pub jti: String,      // Token ID, checked against the revocation list
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mfa: Option<MfaStep>, // Set on MFA-pending tokens, which only the MFA endpoints accept
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>, // Session ID; the token is rejected once its session is revoked
 }
 
-/// Generate a JWT token for a user
-pub fn generate_token(user_id: &str, role: &str) -> Result<String, AppError> {
-    let secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "kaiju_academy_secret_key".to_string());
-    
+/// Second login step an MFA-pending token is waiting on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MfaStep {
+    /// Enter a code from the enrolled authenticator, or a recovery code
+    Verify,
+    /// Enroll an authenticator, because the user's role requires one
+    Enroll,
+}
+
+/// Generate a short-lived JWT access token for a user's session
+pub fn generate_token(user_id: &str, role: &str, session: &str) -> Result<String, AppError> {
     // Current timestamp
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .map_err(|e| AppError::Internal(format!("System time error: {}", e)))?
         .as_secs();
-    
-    // Token expiry (24 hours)
-    let expiry = now + Duration::from_secs(24 * 60 * 60).as_secs();
-    
+
+    // Token expiry
+    let expiry = now + access_token_lifetime().as_secs();
+
     // Create the claims
     let claims = Claims {
         sub: user_id.to_string(),
         role: role.to_string(),
         exp: expiry,
         iat: now,
+        jti: uuid::Uuid::new_v4().to_string(),
+        mfa: None,
+        sid: Some(session.to_string()),
     };
-    
-    // Create the token
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(secret.as_bytes()),
-    )
-    .map_err(|e| AppError::Internal(format!("Token generation error: {}", e)))?;
-    
+
+    sign_claims(&claims)
+}
+
+/// Generate a short-lived token proving the password step of a login, pending `step`.
+///
+/// [`validate_token`] refuses these, so they cannot be used as access tokens.
+pub fn generate_mfa_token(user_id: &str, role: &str, step: MfaStep) -> Result<String, AppError> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| AppError::Internal(format!("System time error: {}", e)))?
+        .as_secs();
+
+    let claims = Claims {
+        sub: user_id.to_string(),
+        role: role.to_string(),
+        exp: now + mfa_token_lifetime().as_secs(),
+        iat: now,
+        jti: uuid::Uuid::new_v4().to_string(),
+        mfa: Some(step),
+        sid: None,
+    };
+
+    sign_claims(&claims)
+}
+
+/// Sign claims with the active key
+fn sign_claims(claims: &Claims) -> Result<String, AppError> {
+    // Sign with the active key and name it in the header so verifiers can pick the right public key
+    let signing = KEYS.signing_key();
+    let mut header = Header::new(signing.algorithm);
+    header.kid = Some(signing.kid.clone());
+
+    let token = encode(&header, claims, &signing.key)
+        .map_err(|e| AppError::Internal(format!("Token generation error: {}", e)))?;
+
     Ok(token)
 }
 
-/// Validate a JWT token and return the claims
-pub fn validate_token(token: &str) -> Result<Claims, AppError> {
-    let secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "kaiju_academy_secret_key".to_string());
-    
+/// Check a JWT's signature and expiry and return the claims, without consulting the revocation list
+pub fn decode_token(token: &str) -> Result<Claims, AppError> {
+    // Find the key named by the token. The algorithm comes from our key, never from the token.
+    let kid = decode_header(token)
+        .map_err(|_| AppError::Authentication(ErrorCode::InvalidToken, "Invalid token".to_string()))?
+        .kid
+        .ok_or_else(|| AppError::Authentication(ErrorCode::InvalidToken, "Token has no key ID".to_string()))?;
+
+    let key = KEYS
+        .verification_key(&kid)
+        .ok_or_else(|| AppError::Authentication(ErrorCode::InvalidToken, "Token signed with an unknown key".to_string()))?;
+
     // Decode and validate the token
-    let token_data = decode::<Claims>(
+    let token_data = decode::<Claims>(token, &key.key, &Validation::new(key.algorithm))
+        .map_err(|e| match e.kind() {
+            jsonwebtoken::errors::ErrorKind::ExpiredSignature => {
+                AppError::Authentication(ErrorCode::TokenExpired, "Token has expired".to_string())
+            }
+            jsonwebtoken::errors::ErrorKind::InvalidToken => {
+                AppError::Authentication(ErrorCode::InvalidToken, "Invalid token".to_string())
+            }
+            _ => AppError::Authentication(ErrorCode::InvalidToken, format!("Token validation error: {}", e)),
+        })?;
+
+    Ok(token_data.claims)
+}
+
+/// Validate a JWT token and return the claims, rejecting tokens that have been revoked
+/// and MFA-pending tokens
+pub async fn validate_token(repo: &impl TokenRepository, token: &str) -> Result<Claims, AppError> {
+    let claims = decode_token(token)?;
+
+    if claims.mfa.is_some() {
+        return Err(AppError::Authentication(
+            ErrorCode::MfaRequired,
+            "Multi-factor authentication has not been completed".to_string(),
+        ));
+    }
+
+    check_revocation(repo, claims).await
+}
+
+/// Validate an MFA-pending token for `step` and return the claims
+pub async fn validate_mfa_token(repo: &impl TokenRepository, token: &str, step: MfaStep) -> Result<Claims, AppError> {
+    let claims = decode_token(token)
+        .map_err(|_| AppError::Authentication(
+            ErrorCode::InvalidMfaToken,
+            "Invalid or expired MFA token. Log in again.".to_string(),
+        ))?;
+
+    if claims.mfa != Some(step) {
+        return Err(AppError::Authentication(ErrorCode::InvalidMfaToken, "Invalid MFA token".to_string()));
+    }
+
+    check_revocation(repo, claims).await
+}
+
+/// Reject claims whose token, session, or every token of whose user, has been revoked
+async fn check_revocation(repo: &impl TokenRepository, claims: Claims) -> Result<Claims, AppError> {
+    let user = record_id("user", &claims.sub)
+        .map_err(|_| AppError::Authentication(ErrorCode::InvalidToken, "Invalid token subject".to_string()))?;
+
+    if repo.is_access_token_revoked(&claims.jti, &user, claims.iat).await? {
+        return Err(AppError::Authentication(ErrorCode::TokenRevoked, "Token has been revoked".to_string()));
+    }
+
+    if let Some(sid) = &claims.sid {
+        let id = session_id(sid);
+        let active = repo
+            .find_session(&id)
+            .await?
+            .is_some_and(|session| session.user == user && session.revoked_at.is_none());
+        if !active {
+            return Err(AppError::Authentication(ErrorCode::SessionEnded, "This session has been signed out".to_string()));
+        }
+
+        if let Err(err) = repo.touch_session(&id, Utc::now()).await {
+            error!("Failed to record session activity: {}", err);
+        }
+    }
+
+    Ok(claims)
+}
+
+/// Record ID of the session for a refresh token family
+pub fn session_id(family: &str) -> Thing {
+    Thing::from(("session", family))
+}
+
+/// When a refresh token issued now expires
+fn refresh_token_expiry() -> DateTime<Utc> {
+    Utc::now() + ChronoDuration::days(CONFIG.refresh_token_expiry_days as i64)
+}
+
+/// Open a session for a user signing in from `client` and issue its first tokens
+pub async fn open_session(
+    repo: &impl TokenRepository,
+    user: &Thing,
+    role: &str,
+    client: &ClientInfo,
+) -> Result<TokenPair, AppError> {
+    let family = uuid::Uuid::new_v4().to_string();
+    repo.create_session(Session::new(&family, user.clone(), client, refresh_token_expiry()))
+        .await?;
+
+    issue_tokens(repo, user, role, &family).await
+}
+
+/// Record a refresh of the session with refresh token family `family` and issue its next tokens.
+///
+/// Families issued before sessions were recorded get a session record on their first refresh.
+pub async fn continue_session(
+    repo: &impl TokenRepository,
+    user: &Thing,
+    role: &str,
+    family: &str,
+    client: &ClientInfo,
+) -> Result<TokenPair, AppError> {
+    let id = session_id(family);
+    match repo.find_session(&id).await? {
+        Some(_) => repo.refresh_session(&id, client, Utc::now(), refresh_token_expiry()).await?,
+        None => {
+            repo.create_session(Session::new(family, user.clone(), client, refresh_token_expiry()))
+                .await?;
+        }
+    }
+
+    issue_tokens(repo, user, role, family).await
+}
+
+/// Issue an access token and a refresh token in the session with refresh token family `family`
+pub async fn issue_tokens(
+    repo: &impl TokenRepository,
+    user: &Thing,
+    role: &str,
+    family: &str,
+) -> Result<TokenPair, AppError> {
+    let token = generate_token(&user.to_string(), role, family)?;
+
+    let refresh_token = generate_opaque_token();
+
+    repo.create_refresh_token(RefreshToken::new(
+        hash_opaque_token(&refresh_token),
+        user.clone(),
+        family.to_string(),
+        refresh_token_expiry(),
+    ))
+    .await?;
+
+    Ok(TokenPair {
         token,
-        &DecodingKey::from_secret(secret.as_bytes()),
-        &Validation::new(Algorithm::HS256),
-    )
-    .map_err(|e| match e.kind() {
-        jsonwebtoken::errors::ErrorKind::ExpiredSignature => {
-            AppError::Authentication("Token has expired".to_string())
+        refresh_token,
+        expires_in: access_token_lifetime().as_secs(),
+    })
+}
+
+/// Prefix marking personal access tokens, so the auth path can tell them from JWTs
+pub const PERSONAL_TOKEN_PREFIX: &str = "kpat_";
+
+/// Generate a new personal access token
+pub fn generate_personal_token() -> String {
+    format!("{}{}", PERSONAL_TOKEN_PREFIX, generate_opaque_token())
+}
+
+/// Is this bearer token a personal access token rather than a JWT?
+pub fn is_personal_token(token: &str) -> bool {
+    token.starts_with(PERSONAL_TOKEN_PREFIX)
+}
+
+/// Validate a personal access token and return its record, noting that it was used
+pub async fn validate_personal_token(repo: &impl TokenRepository, token: &str) -> Result<PersonalToken, AppError> {
+    let stored = repo
+        .find_personal_token(&hash_opaque_token(token))
+        .await?
+        .ok_or_else(|| AppError::Authentication(ErrorCode::InvalidToken, "Invalid token".to_string()))?;
+
+    let now = Utc::now();
+    if stored.revoked_at.is_some() {
+        return Err(AppError::Authentication(ErrorCode::TokenRevoked, "Token has been revoked".to_string()));
+    }
+    if stored.expires_at <= now {
+        return Err(AppError::Authentication(ErrorCode::TokenExpired, "Token has expired".to_string()));
+    }
+
+    if let Some(id) = &stored.id
+        && let Err(err) = repo.touch_personal_token(id, now).await
+    {
+        error!("Failed to record personal access token use: {}", err);
+    }
+
+    Ok(stored)
+}
+
+/// Outcome of checking a password against a stored hash
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordMatch {
+    /// Wrong password, or a stored hash that cannot be used
+    Invalid,
+    /// Right password, hashed with the current parameters
+    Valid,
+    /// Right password, but the hash predates the current parameters and should be replaced
+    Outdated,
+}
+
+/// Argon2id cost parameters from the `argon2_*` settings
+pub fn argon2_params(config: &Config) -> Result<Params, argon2::Error> {
+    Params::new(config.argon2_memory_kib, config.argon2_iterations, config.argon2_parallelism, None)
+}
+
+/// Hash a password with Argon2id and the configured costs for storage
+pub fn hash_password(password: &str) -> Result<String, AppError> {
+    let params = argon2_params(&CONFIG)
+        .map_err(|e| AppError::Internal(format!("Invalid Argon2 parameters: {}", e)))?;
+    let salt = SaltString::generate(&mut OsRng);
+
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| AppError::Internal(format!("Failed to hash password: {}", e)))
+}
+
+/// Check a password against a stored hash.
+///
+/// The hash is verified with the algorithm and costs recorded in it, so hashes made under
+/// older settings keep working. A malformed hash is logged and treated as a mismatch.
+pub fn verify_password(password: &str, stored: &str) -> PasswordMatch {
+    let hash = match PasswordHash::new(stored) {
+        Ok(hash) => hash,
+        Err(err) => {
+            error!("Stored password hash is malformed: {}", err);
+            return PasswordMatch::Invalid;
         }
-        jsonwebtoken::errors::ErrorKind::InvalidToken => {
-            AppError::Authentication("Invalid token".to_string())
+    };
+
+    match Argon2::default().verify_password(password.as_bytes(), &hash) {
+        Ok(()) if is_current(&hash) => PasswordMatch::Valid,
+        Ok(()) => PasswordMatch::Outdated,
+        Err(password_hash::Error::Password) => PasswordMatch::Invalid,
+        Err(err) => {
+            error!("Failed to verify password: {}", err);
+            PasswordMatch::Invalid
         }
-        _ => AppError::Authentication(format!("Token validation error: {}", e)),
-    })?;
-    
-    Ok(token_data.claims)
-} 
\ No newline at end of file
+    }
+}
+
+/// Was the hash made with Argon2id and the configured costs?
+fn is_current(hash: &PasswordHash) -> bool {
+    let (Ok(current), Ok(used)) = (argon2_params(&CONFIG), Params::try_from(hash)) else {
+        return false;
+    };
+
+    hash.algorithm == Algorithm::Argon2id.ident()
+        && hash.version == Some(Version::V0x13.into())
+        && used.m_cost() == current.m_cost()
+        && used.t_cost() == current.t_cost()
+        && used.p_cost() == current.p_cost()
+}
+
+/// Generate a random opaque token, such as a refresh or password reset token
+pub fn generate_opaque_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Hash an opaque token for storage and lookup
+pub fn hash_opaque_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// How long access tokens stay valid
+fn access_token_lifetime() -> Duration {
+    Duration::from_secs(CONFIG.access_token_expiry_minutes * 60)
+}
+
+/// How long MFA-pending tokens stay valid
+pub fn mfa_token_lifetime() -> Duration {
+    Duration::from_secs(CONFIG.mfa_token_expiry_minutes * 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn malformed_hashes_do_not_match() {
+        assert_eq!(verify_password("password", "not a hash"), PasswordMatch::Invalid);
+        assert_eq!(verify_password("password", ""), PasswordMatch::Invalid);
+    }
+
+    #[test]
+    fn hashes_with_old_costs_are_outdated() {
+        let salt = SaltString::generate(&mut OsRng);
+        let cheap = Argon2::new(Algorithm::Argon2id, Version::V0x13, Params::new(8, 1, 1, None).unwrap())
+            .hash_password(b"correct horse", &salt)
+            .unwrap()
+            .to_string();
+
+        assert_eq!(verify_password("correct horse", &cheap), PasswordMatch::Outdated);
+        assert_eq!(verify_password("wrong horse", &cheap), PasswordMatch::Invalid);
+
+        let current = hash_password("correct horse").unwrap();
+        assert_eq!(verify_password("correct horse", &current), PasswordMatch::Valid);
+    }
+}
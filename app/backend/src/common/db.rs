@@ -1,40 +1,92 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use std::future::Future;
 use std::sync::Arc;
-use surrealdb::{engine::remote::ws::Client, Surreal};
-use tracing::{info, error};
+use std::time::{Duration, Instant};
+use surrealdb::{engine::remote::ws::Client, Connection, Surreal};
+use tokio::sync::RwLock;
+use tracing::{info, error, warn};
 
-/// Get a SurrealDB client instance
+use crate::common::config::CONFIG;
+
+/// A connection kept alive across Lambda invocations, with the time it was last known healthy
+struct CachedClient<C: Connection> {
+    client: Arc<Surreal<C>>,
+    last_checked: Instant,
+}
+
+/// A connection opened on first use and reopened when a health check fails
+struct ConnectionCache<C: Connection> {
+    slot: RwLock<Option<CachedClient<C>>>,
+}
+
+impl<C: Connection> ConnectionCache<C> {
+    const fn new() -> Self {
+        Self {
+            slot: RwLock::const_new(None),
+        }
+    }
+
+    /// Get the cached connection, health checking it at most every `check_interval` and
+    /// opening a new one with `connect` if there is none or the check fails
+    async fn get<F, Fut>(&self, check_interval: Duration, health_timeout: Duration, connect: F) -> Result<Arc<Surreal<C>>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Arc<Surreal<C>>>>,
+    {
+        // Fast path: the cached connection was checked recently
+        if let Some(cached) = self.slot.read().await.as_ref()
+            && cached.last_checked.elapsed() < check_interval
+        {
+            return Ok(cached.client.clone());
+        }
+
+        let mut guard = self.slot.write().await;
+
+        // Another task may have refreshed the connection while we waited for the lock
+        if let Some(cached) = guard.as_mut() {
+            if cached.last_checked.elapsed() < check_interval {
+                return Ok(cached.client.clone());
+            }
+
+            match tokio::time::timeout(health_timeout, cached.client.health()).await {
+                Ok(Ok(())) => {
+                    cached.last_checked = Instant::now();
+                    return Ok(cached.client.clone());
+                }
+                Ok(Err(err)) => warn!("SurrealDB health check failed, reconnecting: {}", err),
+                Err(_) => warn!("SurrealDB health check timed out, reconnecting"),
+            }
+
+            *guard = None;
+        }
+
+        let client = connect().await?;
+        *guard = Some(CachedClient {
+            client: client.clone(),
+            last_checked: Instant::now(),
+        });
+
+        Ok(client)
+    }
+}
+
+/// Connection shared by every invocation in this container
+static DB: ConnectionCache<Client> = ConnectionCache::new();
+
+/// Get a SurrealDB client instance.
+///
+/// The connection is opened once per container and reused by later invocations.
+/// It is health checked at most every `db_health_check_interval_secs` and
+/// transparently reopened if the check fails.
 pub async fn get_db_client() -> Result<Arc<Surreal<Client>>> {
-    // Get connection details from environment variables
-    let db_host = std::env::var("SURREALDB_HOST").unwrap_or_else(|_| "localhost".to_string());
-    let db_port = std::env::var("SURREALDB_PORT").unwrap_or_else(|_| "8000".to_string());
-    let db_user = std::env::var("SURREALDB_USER").unwrap_or_else(|_| "root".to_string());
-    let db_pass = std::env::var("SURREALDB_PASS").unwrap_or_else(|_| "root".to_string());
-    let db_namespace = std::env::var("SURREALDB_NS").unwrap_or_else(|_| "code_learning_platform".to_string());
-    let db_database = std::env::var("SURREALDB_DB").unwrap_or_else(|_| "code_learning_platform".to_string());
-    
-    let connection_string = format!("ws://{}:{}", db_host, db_port);
-    
-    info!("Connecting to SurrealDB at {}", connection_string);
-    
-    // Create a connection to the SurrealDB server
-    let db = Surreal::new::<surrealdb::engine::remote::ws::Ws>(&connection_string).await?;
-    
-    // Sign in as a namespace, database, or root user
-    db.signin(surrealdb::opt::auth::Root {
-        username: &db_user,
-        password: &db_pass,
-    }).await?;
-    
-    // Select a specific namespace / database
-    db.use_ns(&db_namespace).use_db(&db_database).await?;
-    
-    info!("Successfully connected to SurrealDB namespace {} and database {}", db_namespace, db_database);
-    
-    Ok(Arc::new(db))
+    let check_interval = Duration::from_secs(CONFIG.db_health_check_interval_secs);
+
+    DB.get(check_interval, connect_timeout(), connect_with_retry).await
 }
 
-/// Initialize the database connection
+/// Initialize the database connection.
+///
+/// Called once during the Lambda init phase so the first request does not pay for the handshake.
 pub async fn init_db() -> Result<Arc<Surreal<Client>>> {
     match get_db_client().await {
         Ok(client) => {
@@ -46,4 +98,126 @@ pub async fn init_db() -> Result<Arc<Surreal<Client>>> {
             Err(err)
         }
     }
-} 
\ No newline at end of file
+}
+
+/// Connect to SurrealDB, retrying with exponential backoff
+async fn connect_with_retry() -> Result<Arc<Surreal<Client>>> {
+    let max_attempts = CONFIG.db_connect_max_retries + 1;
+    let base = Duration::from_millis(CONFIG.db_retry_backoff_ms);
+    let mut attempt = 1;
+
+    loop {
+        match tokio::time::timeout(connect_timeout(), connect()).await {
+            Ok(Ok(client)) => return Ok(Arc::new(client)),
+            Ok(Err(err)) if attempt >= max_attempts => return Err(err),
+            Err(_) if attempt >= max_attempts => {
+                return Err(anyhow!("Timed out connecting to SurrealDB after {} attempts", attempt));
+            }
+            Ok(Err(err)) => warn!("SurrealDB connection attempt {} failed: {}", attempt, err),
+            Err(_) => warn!("SurrealDB connection attempt {} timed out", attempt),
+        }
+
+        tokio::time::sleep(retry_backoff(base, attempt)).await;
+        attempt += 1;
+    }
+}
+
+/// Delay after failed attempt number `attempt` (starting at 1): `base`, doubling each time
+fn retry_backoff(base: Duration, attempt: u32) -> Duration {
+    base.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+}
+
+/// Open a new connection, sign in and select the namespace and database
+async fn connect() -> Result<Surreal<Client>> {
+    let connection_string = format!("{}:{}", CONFIG.db_host, CONFIG.db_port);
+
+    info!("Connecting to SurrealDB at ws://{}", connection_string);
+
+    // Create a connection to the SurrealDB server
+    let db = Surreal::new::<surrealdb::engine::remote::ws::Ws>(&connection_string).await?;
+
+    // Sign in as a namespace, database, or root user
+    db.signin(surrealdb::opt::auth::Root {
+        username: &CONFIG.db_user,
+        password: &CONFIG.db_pass,
+    }).await?;
+
+    // Select a specific namespace / database
+    db.use_ns(&CONFIG.db_namespace).use_db(&CONFIG.db_database).await?;
+
+    info!("Successfully connected to SurrealDB namespace {} and database {}", CONFIG.db_namespace, CONFIG.db_database);
+
+    Ok(db)
+}
+
+/// Timeout applied to connecting and to health checks
+fn connect_timeout() -> Duration {
+    Duration::from_secs(CONFIG.db_connect_timeout_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use surrealdb::engine::local::{Db, Mem};
+
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_after_each_attempt() {
+        let base = Duration::from_millis(100);
+
+        assert_eq!(retry_backoff(base, 1), Duration::from_millis(100));
+        assert_eq!(retry_backoff(base, 2), Duration::from_millis(200));
+        assert_eq!(retry_backoff(base, 3), Duration::from_millis(400));
+        assert_eq!(retry_backoff(Duration::ZERO, 5), Duration::ZERO);
+    }
+
+    #[test]
+    fn backoff_saturates_instead_of_overflowing() {
+        assert_eq!(retry_backoff(Duration::MAX, 2), Duration::MAX);
+        assert!(retry_backoff(Duration::from_millis(100), 64) >= retry_backoff(Duration::from_millis(100), 32));
+    }
+
+    #[tokio::test]
+    async fn failed_health_checks_reopen_the_connection() {
+        let cache = ConnectionCache::<Db>::new();
+        let connects = AtomicUsize::new(0);
+        let healthy = || async {
+            connects.fetch_add(1, Ordering::SeqCst);
+            Ok(Arc::new(Surreal::new::<Mem>(()).await?))
+        };
+
+        // A client that never connected fails its health check
+        let broken = cache
+            .get(Duration::ZERO, Duration::from_secs(1), || async { Ok(Arc::new(Surreal::<Db>::init())) })
+            .await
+            .unwrap();
+        assert!(broken.health().await.is_err());
+
+        let reopened = cache.get(Duration::ZERO, Duration::from_secs(1), healthy).await.unwrap();
+        assert_eq!(connects.load(Ordering::SeqCst), 1);
+        assert!(reopened.health().await.is_ok());
+
+        // A healthy connection is kept
+        let kept = cache.get(Duration::ZERO, Duration::from_secs(1), healthy).await.unwrap();
+        assert_eq!(connects.load(Ordering::SeqCst), 1);
+        assert!(Arc::ptr_eq(&reopened, &kept));
+    }
+
+    #[tokio::test]
+    async fn recently_checked_connections_skip_the_health_check() {
+        let cache = ConnectionCache::<Db>::new();
+        let interval = Duration::from_secs(60);
+
+        let broken = cache
+            .get(interval, Duration::from_secs(1), || async { Ok(Arc::new(Surreal::<Db>::init())) })
+            .await
+            .unwrap();
+        let cached = cache
+            .get(interval, Duration::from_secs(1), || async { Err(anyhow!("should not reconnect")) })
+            .await
+            .unwrap();
+
+        assert!(Arc::ptr_eq(&broken, &cached));
+    }
+}
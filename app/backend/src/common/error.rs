@@ -1,69 +1,289 @@
 use aws_lambda_events::event::apigw::ApiGatewayProxyResponse;
-use http::HeaderMap;
-use aws_lambda_events::encodings::Body;
+use serde::Serialize;
 use serde_json::json;
 use thiserror::Error;
+use tracing::error;
+
+use crate::common::response::json_response;
+
+/// Stable identifier of a failure, returned as `error.code` so clients can localize the
+/// message and react to it without parsing text. Codes are never renamed once released.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    // Malformed requests
+    InvalidBody,
+    MissingBody,
+    InvalidPathParameter,
+    InvalidId,
+    ValidationFailed,
+
+    // Field-level validation, used in `details`
+    Required,
+    InvalidFormat,
+    TooShort,
+    TooLong,
+    OutOfRange,
+    PasswordBreached,
+    PasswordContainsEmail,
+
+    // Authentication
+    AuthenticationRequired,
+    InvalidCredentials,
+    InvalidToken,
+    TokenExpired,
+    TokenRevoked,
+    RefreshTokenReused,
+    SessionEnded,
+    MfaRequired,
+    InvalidMfaToken,
+    InvalidMfaCode,
+    SignInFailed,
+    SignInExpired,
+
+    // Authorization
+    Forbidden,
+    InsufficientScope,
+    EmailNotVerified,
+    PasswordResetRequired,
+    MfaRequiredForRole,
+
+    // Accounts
+    EmailTaken,
+    IdentityAlreadyLinked,
+    InvalidVerificationCode,
+    InvalidResetToken,
+    MfaAlreadyEnrolled,
+    MfaNotEnrolled,
+    MfaNotPending,
+
+    // Missing resources
+    RouteNotFound,
+    MethodNotAllowed,
+    UserNotFound,
+    CourseNotFound,
+    SessionNotFound,
+    TokenNotFound,
+    TestCasesNotFound,
+    ProviderNotFound,
+
+    // Rate limits
+    TooManyLoginAttempts,
+    TooManyIncorrectCodes,
+    CodeRecentlySent,
+    RateLimited,
+
+    // Server-side failures
+    InternalError,
+    ExternalServiceError,
+}
+
+/// A problem with one field of the request, returned in `error.details`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// A required field that is missing or empty
+    pub fn required(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(field, ErrorCode::Required, message)
+    }
+}
+
+/// Shown to clients in place of server-side failures, whose details stay in the logs
+const INTERNAL_ERROR_MESSAGE: &str = "Something went wrong on our side. Try again later.";
 
 // Mapping error types for the application
 #[derive(Error, Debug)]
 pub enum AppError {
-    #[error("Authentication error: {0}")]
-    Authentication(String),
-    
-    #[error("Authorization error: {0}")]
-    Authorization(String),
-    
-    #[error("Resource not found: {0}")]
-    NotFound(String),
-    
-    #[error("Validation error: {0}")]
-    Validation(String),
-    
+    #[error("Authentication error: {1}")]
+    Authentication(ErrorCode, String),
+
+    #[error("Authorization error: {1}")]
+    Authorization(ErrorCode, String),
+
+    #[error("Resource not found: {1}")]
+    NotFound(ErrorCode, String),
+
+    #[error("Method not allowed: {0}")]
+    MethodNotAllowed(String),
+
+    #[error("Validation error: {1}")]
+    Validation(ErrorCode, String),
+
+    #[error("Validation error: {}", join_messages(.0))]
+    InvalidFields(Vec<FieldError>),
+
     #[error("Database error: {0}")]
-    Database(#[from] surrealdb::Error),
-    
+    Database(Box<surrealdb::Error>),
+
     #[error("Internal server error: {0}")]
     Internal(String),
-    
+
     #[error("External service error: {0}")]
     ExternalService(String),
-    
-    #[error("Rate limit exceeded: {0}")]
-    RateLimit(String),
+
+    #[error("Rate limit exceeded: {1}")]
+    RateLimit(ErrorCode, String),
+}
+
+impl AppError {
+    /// A single invalid field
+    pub fn invalid_field(field: impl Into<String>, code: ErrorCode, message: impl Into<String>) -> Self {
+        AppError::InvalidFields(vec![FieldError::new(field, code, message)])
+    }
+
+    /// The code returned to clients
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AppError::Authentication(code, _)
+            | AppError::Authorization(code, _)
+            | AppError::NotFound(code, _)
+            | AppError::Validation(code, _)
+            | AppError::RateLimit(code, _) => *code,
+            AppError::MethodNotAllowed(_) => ErrorCode::MethodNotAllowed,
+            AppError::InvalidFields(_) => ErrorCode::ValidationFailed,
+            AppError::Database(_) | AppError::Internal(_) => ErrorCode::InternalError,
+            AppError::ExternalService(_) => ErrorCode::ExternalServiceError,
+        }
+    }
+
+    /// The message returned to clients, without the internals of server-side failures
+    pub fn public_message(&self) -> String {
+        match self {
+            AppError::Authentication(_, message)
+            | AppError::Authorization(_, message)
+            | AppError::NotFound(_, message)
+            | AppError::Validation(_, message)
+            | AppError::RateLimit(_, message)
+            | AppError::MethodNotAllowed(message)
+            | AppError::ExternalService(message) => message.clone(),
+            AppError::InvalidFields(fields) => join_messages(fields),
+            AppError::Database(_) | AppError::Internal(_) => INTERNAL_ERROR_MESSAGE.to_string(),
+        }
+    }
+}
+
+fn join_messages(fields: &[FieldError]) -> String {
+    fields.iter().map(|field| field.message.as_str()).collect::<Vec<_>>().join("; ")
+}
+
+// Boxed so that `Result<_, AppError>` stays small
+impl From<surrealdb::Error> for AppError {
+    fn from(error: surrealdb::Error) -> Self {
+        AppError::Database(Box::new(error))
+    }
 }
 
 // Convert an application error to an API Gateway response
 impl From<AppError> for ApiGatewayProxyResponse {
     fn from(error: AppError) -> Self {
         let (status_code, error_type) = match &error {
-            AppError::Authentication(_) => (401, "Authentication Error"),
-            AppError::Authorization(_) => (403, "Authorization Error"),
-            AppError::NotFound(_) => (404, "Not Found"),
-            AppError::Validation(_) => (400, "Validation Error"),
-            AppError::Database(_) => (500, "Database Error"),
-            AppError::Internal(_) => (500, "Internal Server Error"),
+            AppError::Authentication(..) => (401, "Authentication Error"),
+            AppError::Authorization(..) => (403, "Authorization Error"),
+            AppError::NotFound(..) => (404, "Not Found"),
+            AppError::MethodNotAllowed(_) => (405, "Method Not Allowed"),
+            AppError::Validation(..) | AppError::InvalidFields(_) => (400, "Validation Error"),
+            AppError::Database(_) | AppError::Internal(_) => (500, "Internal Server Error"),
             AppError::ExternalService(_) => (502, "External Service Error"),
-            AppError::RateLimit(_) => (429, "Rate Limit Exceeded"),
+            AppError::RateLimit(..) => (429, "Rate Limit Exceeded"),
         };
-        
+
+        // The client only sees a generic message, so keep the real one in the logs
+        if status_code == 500 {
+            error!("{}", error);
+        }
+
+        let details = match &error {
+            AppError::InvalidFields(fields) => fields.clone(),
+            _ => Vec::new(),
+        };
+
         let body = json!({
             "error": {
+                "code": error.code(),
                 "type": error_type,
-                "message": error.to_string()
+                "message": error.public_message(),
+                "details": details
             }
         });
-        
-        let body_str = body.to_string();
-        
-        let mut headers = HeaderMap::new();
-        headers.insert("Content-Type", "application/json".parse().unwrap());
-        
-        ApiGatewayProxyResponse {
-            status_code,
-            headers,
-            multi_value_headers: HeaderMap::new(),
-            body: Some(Body::from(body_str)),
-            is_base64_encoded: false,
-        }
+
+        json_response(status_code, body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    fn body(error: AppError) -> (i64, Value) {
+        let response = ApiGatewayProxyResponse::from(error);
+        let body = match response.body {
+            Some(aws_lambda_events::encodings::Body::Text(text)) => serde_json::from_str(&text).unwrap(),
+            other => panic!("unexpected body: {:?}", other),
+        };
+        (response.status_code, body)
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn errors_carry_a_code_and_a_plain_message() {
+        let (status, body) = body(AppError::NotFound(ErrorCode::CourseNotFound, "Course with ID abc not found".to_string()));
+
+        assert_eq!(status, 404);
+        assert_eq!(body["error"]["code"], "COURSE_NOT_FOUND");
+        assert_eq!(body["error"]["message"], "Course with ID abc not found");
+        assert_eq!(body["error"]["details"], json!([]));
+    }
+
+    #[test]
+    fn field_errors_are_listed_in_details() {
+        let (status, body) = body(AppError::InvalidFields(vec![
+            FieldError::required("title", "Title is required"),
+            FieldError::new("email", ErrorCode::InvalidFormat, "Invalid email format"),
+        ]));
+
+        assert_eq!(status, 400);
+        assert_eq!(body["error"]["code"], "VALIDATION_FAILED");
+        assert_eq!(body["error"]["message"], "Title is required; Invalid email format");
+        assert_eq!(
+            body["error"]["details"],
+            json!([
+                { "field": "title", "code": "REQUIRED", "message": "Title is required" },
+                { "field": "email", "code": "INVALID_FORMAT", "message": "Invalid email format" },
+            ])
+        );
+    }
+
+    #[test]
+    fn server_errors_hide_their_cause() {
+        let (status, body) = body(AppError::Internal("Failed to write /tmp/outbox/1.json: denied".to_string()));
+
+        assert_eq!(status, 500);
+        assert_eq!(body["error"]["code"], "INTERNAL_ERROR");
+        assert_eq!(body["error"]["message"], INTERNAL_ERROR_MESSAGE);
+        assert!(!body.to_string().contains("/tmp/outbox"));
+    }
+
+    #[test]
+    fn database_errors_hide_their_cause() {
+        let cause = surrealdb::error::Api::Query("Parse error: unexpected token `SELEC`".to_string());
+        let (status, body) = body(AppError::from(surrealdb::Error::from(cause)));
+
+        assert_eq!(status, 500);
+        assert_eq!(body["error"]["code"], "INTERNAL_ERROR");
+        assert!(!body.to_string().contains("SELEC"));
+    }
+}
@@ -0,0 +1,294 @@
+use aws_lambda_events::event::apigw::ApiGatewayProxyRequest;
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use surrealdb::sql::Thing;
+
+use crate::common::auth::{self, Claims};
+use crate::common::error::{AppError, ErrorCode};
+use crate::models::session::ClientInfo;
+use crate::models::token::{PersonalToken, TokenScope};
+use crate::models::user::UserRole;
+use crate::repository::{TokenRepository, UserRepository};
+
+/// Key under which the authenticated caller is stored in the request's authorizer context
+const AUTHORIZER_KEY: &str = "kaiju_auth";
+
+/// Metadata about the token that authenticated a request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenMetadata {
+    pub id: String,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// Refresh token family of the session the access token belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+}
+
+/// Authenticated caller.
+///
+/// The router authenticates the `Authorization` header once per request, including the
+/// revocation check, and attaches the result to the request's authorizer context.
+/// Handlers read it back with [`AuthContext::from_request`] or [`AuthContext::optional`].
+///
+/// The bearer token is either a JWT from a login or a personal access token. Personal
+/// access tokens carry `scopes`, and the router only accepts them on routes that name one
+/// of those scopes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthContext {
+    pub user: Thing,
+    pub role: UserRole,
+    pub token: TokenMetadata,
+    /// Scopes of a personal access token; `None` for a login session, which may do anything
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<Vec<TokenScope>>,
+}
+
+impl AuthContext {
+    /// Get the authenticated caller, failing if the request carried no valid bearer token
+    pub fn from_request(request: &ApiGatewayProxyRequest) -> Result<Self, AppError> {
+        Self::optional(request)?
+            .ok_or_else(|| AppError::Authentication(
+                ErrorCode::AuthenticationRequired,
+                "Missing authorization header".to_string(),
+            ))
+    }
+
+    /// Get the caller for public endpoints, or `Ok(None)` for anonymous requests
+    pub fn optional(request: &ApiGatewayProxyRequest) -> Result<Option<Self>, AppError> {
+        request
+            .request_context
+            .authorizer
+            .fields
+            .get(AUTHORIZER_KEY)
+            .map(|value| {
+                serde_json::from_value(value.clone())
+                    .map_err(|_| AppError::Internal("Invalid authorizer context".to_string()))
+            })
+            .transpose()
+    }
+
+    /// Validate the request's bearer token, returning `Ok(None)` when no `Authorization` header is sent
+    pub async fn authenticate(
+        repo: &(impl TokenRepository + UserRepository),
+        request: &ApiGatewayProxyRequest,
+    ) -> Result<Option<Self>, AppError> {
+        let Some(token) = bearer_token(request)? else {
+            return Ok(None);
+        };
+
+        if auth::is_personal_token(token) {
+            let stored = auth::validate_personal_token(repo, token).await?;
+
+            // The role is read at use time, so a demoted owner's tokens lose their old powers
+            let owner = repo
+                .find_user(&stored.user)
+                .await?
+                .ok_or_else(|| AppError::Authentication(ErrorCode::InvalidToken, "Token owner no longer exists".to_string()))?;
+
+            return Self::from_personal_token(stored, owner.role).map(Some);
+        }
+
+        let claims = auth::validate_token(repo, token).await?;
+        Self::from_claims(claims).map(Some)
+    }
+
+    /// Store the caller in the request's authorizer context for the handler
+    pub fn attach(&self, request: &mut ApiGatewayProxyRequest) -> Result<(), AppError> {
+        let value = serde_json::to_value(self)
+            .map_err(|e| AppError::Internal(format!("Failed to serialize authorizer context: {}", e)))?;
+
+        request
+            .request_context
+            .authorizer
+            .fields
+            .insert(AUTHORIZER_KEY.to_string(), value);
+
+        Ok(())
+    }
+
+    /// Drop any caller already in the request's authorizer context, so only the router can set it
+    pub fn detach(request: &mut ApiGatewayProxyRequest) {
+        request
+            .request_context
+            .authorizer
+            .fields
+            .remove(AUTHORIZER_KEY);
+    }
+
+    /// Build the context from already validated claims
+    pub fn from_claims(claims: Claims) -> Result<Self, AppError> {
+        let role = UserRole::from_str(&claims.role)
+            .map_err(|_| AppError::Authentication(ErrorCode::InvalidToken, "Invalid token role".to_string()))?;
+
+        Ok(Self {
+            user: parse_user_id(&claims.sub)?,
+            role,
+            token: TokenMetadata {
+                id: claims.jti,
+                issued_at: timestamp(claims.iat)?,
+                expires_at: timestamp(claims.exp)?,
+                session: claims.sid,
+            },
+            scopes: None,
+        })
+    }
+
+    /// Build the context from an already validated personal access token
+    pub fn from_personal_token(token: PersonalToken, role: UserRole) -> Result<Self, AppError> {
+        let id = token
+            .id
+            .ok_or_else(|| AppError::Internal("Personal access token has no ID".to_string()))?;
+
+        Ok(Self {
+            user: token.user,
+            role,
+            token: TokenMetadata {
+                id: id.to_string(),
+                issued_at: token.created_at,
+                expires_at: token.expires_at,
+                session: None,
+            },
+            scopes: Some(token.scopes),
+        })
+    }
+
+    /// Fail unless the caller may use a route needing `scope`.
+    ///
+    /// Login sessions may use every route. Personal access tokens need the route's scope,
+    /// and cannot use routes without one, such as the token management endpoints.
+    pub fn require_scope(&self, scope: Option<TokenScope>) -> Result<(), AppError> {
+        let Some(scopes) = &self.scopes else {
+            return Ok(());
+        };
+
+        match scope {
+            Some(scope) if scopes.contains(&scope) => Ok(()),
+            Some(scope) => Err(AppError::Authorization(
+                ErrorCode::InsufficientScope,
+                format!("This token is missing the {} scope", scope),
+            )),
+            None => Err(AppError::Authorization(
+                ErrorCode::InsufficientScope,
+                "Personal access tokens cannot be used for this endpoint".to_string(),
+            )),
+        }
+    }
+
+    /// Is the caller the given user record?
+    pub fn is_user(&self, user: &Thing) -> bool {
+        self.user == *user
+    }
+}
+
+/// Longest user agent kept on a session record
+const MAX_USER_AGENT_LENGTH: usize = 512;
+
+/// Where a request came from: its source IP and `User-Agent` header
+pub fn client_info(request: &ApiGatewayProxyRequest) -> ClientInfo {
+    let user_agent = request
+        .headers
+        .get("User-Agent")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.chars().take(MAX_USER_AGENT_LENGTH).collect());
+
+    ClientInfo {
+        user_agent,
+        ip_address: request.request_context.identity.source_ip.clone(),
+    }
+}
+
+/// Read the bearer token from the `Authorization` header, if one was sent
+fn bearer_token(request: &ApiGatewayProxyRequest) -> Result<Option<&str>, AppError> {
+    let Some(header) = request.headers.get("Authorization") else {
+        return Ok(None);
+    };
+
+    let header = header
+        .to_str()
+        .map_err(|_| AppError::Authentication(ErrorCode::InvalidToken, "Invalid authorization header".to_string()))?;
+
+    let parts: Vec<&str> = header.split_whitespace().collect();
+    if parts.len() != 2 || !parts[0].eq_ignore_ascii_case("bearer") {
+        return Err(AppError::Authentication(ErrorCode::InvalidToken, "Invalid authorization format".to_string()));
+    }
+
+    Ok(Some(parts[1]))
+}
+
+/// Parse the token subject into a user record ID, accepting both `user:abc` and bare `abc`
+fn parse_user_id(sub: &str) -> Result<Thing, AppError> {
+    if sub.is_empty() {
+        return Err(AppError::Authentication(ErrorCode::InvalidToken, "Invalid token subject".to_string()));
+    }
+
+    match Thing::from_str(sub) {
+        Ok(thing) if thing.tb == "user" => Ok(thing),
+        Ok(_) => Err(AppError::Authentication(ErrorCode::InvalidToken, "Invalid token subject".to_string())),
+        Err(_) => Ok(Thing::from(("user", sub))),
+    }
+}
+
+/// Convert a JWT timestamp claim to a `DateTime`
+fn timestamp(secs: u64) -> Result<DateTime<Utc>, AppError> {
+    i64::try_from(secs)
+        .ok()
+        .and_then(|secs| Utc.timestamp_opt(secs, 0).single())
+        .ok_or_else(|| AppError::Authentication(ErrorCode::InvalidToken, "Invalid token timestamp".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+
+    use super::*;
+
+    fn with_authorization(value: HeaderValue) -> ApiGatewayProxyRequest {
+        let mut request = ApiGatewayProxyRequest::default();
+        request.headers.insert("Authorization", value);
+        request
+    }
+
+    fn assert_invalid_token(result: Result<Option<&str>, AppError>) {
+        assert!(matches!(result, Err(AppError::Authentication(ErrorCode::InvalidToken, _))), "{:?}", result);
+    }
+
+    #[test]
+    fn a_missing_header_is_anonymous() {
+        let request = ApiGatewayProxyRequest::default();
+
+        assert!(matches!(bearer_token(&request), Ok(None)));
+        assert!(matches!(
+            AuthContext::from_request(&request),
+            Err(AppError::Authentication(ErrorCode::AuthenticationRequired, _))
+        ));
+    }
+
+    #[test]
+    fn bearer_tokens_are_read_from_the_header() {
+        let request = with_authorization(HeaderValue::from_static("bearer abc.def"));
+
+        assert_eq!(bearer_token(&request).unwrap(), Some("abc.def"));
+    }
+
+    #[test]
+    fn non_utf8_headers_are_rejected() {
+        let request = with_authorization(HeaderValue::from_bytes(b"Bearer \xff\xfe").unwrap());
+
+        assert_invalid_token(bearer_token(&request));
+    }
+
+    #[test]
+    fn other_schemes_are_rejected() {
+        let request = with_authorization(HeaderValue::from_static("Basic YWRhOnNlY3JldA=="));
+
+        assert_invalid_token(bearer_token(&request));
+    }
+
+    #[test]
+    fn empty_headers_are_rejected() {
+        assert_invalid_token(bearer_token(&with_authorization(HeaderValue::from_static(""))));
+        assert_invalid_token(bearer_token(&with_authorization(HeaderValue::from_static("Bearer"))));
+        assert_invalid_token(bearer_token(&with_authorization(HeaderValue::from_static("Bearer   "))));
+    }
+}
@@ -0,0 +1,299 @@
+//! JWT signing and verification keys.
+//!
+//! Access tokens are signed with the PEM private key in `jwt_signing_key` (RSA for RS256,
+//! Ed25519 for EdDSA) and carry its `jwt_key_id` as the `kid` header. Retired public keys
+//! listed in `jwt_previous_keys` (a JWK set) stay valid for verification, so keys can be
+//! rotated without logging everyone out. Every public key is published at
+//! `GET /.well-known/jwks.json`.
+//!
+//! Without a signing key, development builds fall back to HS256 with `jwt_secret`; that key
+//! is never published and production refuses to start without a signing key.
+
+use std::collections::HashMap;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+    OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey};
+use once_cell::sync::{Lazy, OnceCell};
+use ring::rsa::PublicKeyComponents;
+use ring::signature::{Ed25519KeyPair, KeyPair, RsaKeyPair};
+
+use crate::common::config::{Config, ConfigError, CONFIG};
+
+/// The key new tokens are signed with
+pub struct SigningKey {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    pub key: EncodingKey,
+}
+
+/// A key accepted when verifying tokens
+pub struct VerificationKey {
+    pub algorithm: Algorithm,
+    pub key: DecodingKey,
+}
+
+/// The active signing key plus every key tokens may still be verified with
+pub struct KeyRing {
+    signing: SigningKey,
+    verification: HashMap<String, VerificationKey>,
+    jwks: JwkSet,
+}
+
+impl KeyRing {
+    /// Build the key ring from the JWT settings in `config`
+    pub fn from_config(config: &Config) -> Result<Self, ConfigError> {
+        let mut verification = HashMap::new();
+        let mut jwks = JwkSet { keys: Vec::new() };
+
+        let signing = if config.jwt_signing_key.is_empty() {
+            // Development fallback: a shared secret that is never published
+            verification.insert(
+                config.jwt_key_id.clone(),
+                VerificationKey {
+                    algorithm: Algorithm::HS256,
+                    key: DecodingKey::from_secret(config.jwt_secret.as_bytes()),
+                },
+            );
+
+            SigningKey {
+                kid: config.jwt_key_id.clone(),
+                algorithm: Algorithm::HS256,
+                key: EncodingKey::from_secret(config.jwt_secret.as_bytes()),
+            }
+        } else {
+            let (signing, jwk) = parse_signing_key(&config.jwt_key_id, &config.jwt_signing_key)?;
+            insert_public_key(&mut verification, &mut jwks, jwk)?;
+            signing
+        };
+
+        if !config.jwt_previous_keys.is_empty() {
+            let previous: JwkSet = serde_json::from_str(&config.jwt_previous_keys)
+                .map_err(|e| invalid_key(format!("jwt_previous_keys is not a JWK set: {}", e)))?;
+
+            for jwk in previous.keys {
+                insert_public_key(&mut verification, &mut jwks, jwk)?;
+            }
+        }
+
+        Ok(Self { signing, verification, jwks })
+    }
+
+    /// The key new tokens are signed with
+    pub fn signing_key(&self) -> &SigningKey {
+        &self.signing
+    }
+
+    /// Look up the key for a token's `kid` header
+    pub fn verification_key(&self, kid: &str) -> Option<&VerificationKey> {
+        self.verification.get(kid)
+    }
+
+    /// Public keys for the JWKS endpoint
+    pub fn jwks(&self) -> &JwkSet {
+        &self.jwks
+    }
+}
+
+/// Parse a PEM private key, returning the signing key and its public JWK
+fn parse_signing_key(kid: &str, pem_key: &str) -> Result<(SigningKey, Jwk), ConfigError> {
+    if kid.is_empty() {
+        return Err(invalid_key("jwt_key_id must be set when jwt_signing_key is".to_string()));
+    }
+
+    let parsed = pem::parse(pem_key)
+        .map_err(|e| invalid_key(format!("jwt_signing_key is not valid PEM: {}", e)))?;
+
+    let rsa = match parsed.tag() {
+        "RSA PRIVATE KEY" => RsaKeyPair::from_der(parsed.contents()).ok(),
+        "PRIVATE KEY" => RsaKeyPair::from_pkcs8(parsed.contents()).ok(),
+        _ => None,
+    };
+
+    let (algorithm, key, parameters) = if let Some(pair) = rsa {
+        let components: PublicKeyComponents<Vec<u8>> = pair.public().into();
+        let key = EncodingKey::from_rsa_pem(pem_key.as_bytes())
+            .map_err(|e| invalid_key(format!("jwt_signing_key: {}", e)))?;
+
+        let parameters = AlgorithmParameters::RSA(RSAKeyParameters {
+            key_type: RSAKeyType::RSA,
+            n: URL_SAFE_NO_PAD.encode(&components.n),
+            e: URL_SAFE_NO_PAD.encode(&components.e),
+        });
+
+        (Algorithm::RS256, key, parameters)
+    } else if parsed.tag() == "PRIVATE KEY" {
+        let pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(parsed.contents()).map_err(|_| {
+            invalid_key("jwt_signing_key must be an RSA or Ed25519 private key".to_string())
+        })?;
+
+        let parameters = AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+            key_type: OctetKeyPairType::OctetKeyPair,
+            curve: EllipticCurve::Ed25519,
+            x: URL_SAFE_NO_PAD.encode(pair.public_key().as_ref()),
+        });
+
+        (Algorithm::EdDSA, EncodingKey::from_ed_der(parsed.contents()), parameters)
+    } else {
+        return Err(invalid_key(format!("unsupported PEM block {:?} in jwt_signing_key", parsed.tag())));
+    };
+
+    let jwk = Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(key_algorithm(algorithm)),
+            key_id: Some(kid.to_string()),
+            ..Default::default()
+        },
+        algorithm: parameters,
+    };
+
+    let signing = SigningKey { kid: kid.to_string(), algorithm, key };
+
+    Ok((signing, jwk))
+}
+
+/// Register a public JWK for verification and publication
+fn insert_public_key(
+    verification: &mut HashMap<String, VerificationKey>,
+    jwks: &mut JwkSet,
+    jwk: Jwk,
+) -> Result<(), ConfigError> {
+    let kid = jwk
+        .common
+        .key_id
+        .clone()
+        .ok_or_else(|| invalid_key("every verification key needs a kid".to_string()))?;
+
+    let algorithm = match (&jwk.algorithm, jwk.common.key_algorithm) {
+        (AlgorithmParameters::RSA(_), None | Some(KeyAlgorithm::RS256)) => Algorithm::RS256,
+        (AlgorithmParameters::OctetKeyPair(params), None | Some(KeyAlgorithm::EdDSA))
+            if params.curve == EllipticCurve::Ed25519 =>
+        {
+            Algorithm::EdDSA
+        }
+        _ => {
+            return Err(invalid_key(format!("key {} must be an RS256 or Ed25519 public key", kid)));
+        }
+    };
+
+    if verification.contains_key(&kid) {
+        return Err(invalid_key(format!("duplicate kid {}", kid)));
+    }
+
+    let key = DecodingKey::from_jwk(&jwk).map_err(|e| invalid_key(format!("key {}: {}", kid, e)))?;
+
+    let mut published = jwk;
+    published.common.public_key_use = Some(PublicKeyUse::Signature);
+    published.common.key_algorithm = Some(key_algorithm(algorithm));
+    jwks.keys.push(published);
+
+    verification.insert(kid, VerificationKey { algorithm, key });
+
+    Ok(())
+}
+
+/// The JWK `alg` value for a supported signing algorithm
+fn key_algorithm(algorithm: Algorithm) -> KeyAlgorithm {
+    match algorithm {
+        Algorithm::RS256 => KeyAlgorithm::RS256,
+        Algorithm::EdDSA => KeyAlgorithm::EdDSA,
+        _ => KeyAlgorithm::HS256,
+    }
+}
+
+fn invalid_key(message: String) -> ConfigError {
+    ConfigError::InvalidKey(message)
+}
+
+/// Key ring built once per process
+static LOADED: OnceCell<KeyRing> = OnceCell::new();
+
+/// Parse the configured keys.
+///
+/// Entry points call this at startup, after `config::init`, so a malformed key stops the
+/// process instead of failing the first login.
+pub fn init() -> Result<&'static KeyRing, ConfigError> {
+    LOADED.get_or_try_init(|| KeyRing::from_config(&CONFIG))
+}
+
+/// Global key ring, lazily loaded
+pub static KEYS: Lazy<&'static KeyRing> =
+    Lazy::new(|| init().unwrap_or_else(|err| panic!("Invalid JWT keys: {}", err)));
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::{decode, encode, Header, Validation};
+    use ring::rand::SystemRandom;
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct TestClaims {
+        sub: String,
+        exp: u64,
+    }
+
+    fn ed25519_pem() -> String {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        pem::encode(&pem::Pem::new("PRIVATE KEY", pkcs8.as_ref()))
+    }
+
+    fn sign(keys: &KeyRing) -> String {
+        let signing = keys.signing_key();
+        let mut header = Header::new(signing.algorithm);
+        header.kid = Some(signing.kid.clone());
+
+        let claims = TestClaims { sub: "user:ada".to_string(), exp: u64::MAX / 2 };
+        encode(&header, &claims, &signing.key).unwrap()
+    }
+
+    fn verify(keys: &KeyRing, token: &str) -> bool {
+        let kid = jsonwebtoken::decode_header(token).unwrap().kid.unwrap();
+        let Some(key) = keys.verification_key(&kid) else {
+            return false;
+        };
+        decode::<TestClaims>(token, &key.key, &Validation::new(key.algorithm)).is_ok()
+    }
+
+    #[test]
+    fn development_fallback_is_not_published() {
+        let keys = KeyRing::from_config(&Config::default()).unwrap();
+
+        assert_eq!(keys.signing_key().algorithm, Algorithm::HS256);
+        assert!(keys.jwks().keys.is_empty());
+        assert!(verify(&keys, &sign(&keys)));
+    }
+
+    #[test]
+    fn rotated_keys_still_verify() {
+        let old = KeyRing::from_config(&Config {
+            jwt_signing_key: ed25519_pem(),
+            jwt_key_id: "2024-01".to_string(),
+            ..Config::default()
+        })
+        .unwrap();
+        let old_token = sign(&old);
+        assert_eq!(old.signing_key().algorithm, Algorithm::EdDSA);
+
+        let new = KeyRing::from_config(&Config {
+            jwt_signing_key: ed25519_pem(),
+            jwt_key_id: "2024-02".to_string(),
+            jwt_previous_keys: serde_json::to_string(old.jwks()).unwrap(),
+            ..Config::default()
+        })
+        .unwrap();
+
+        assert_eq!(new.jwks().keys.len(), 2);
+        assert!(verify(&new, &old_token));
+        assert!(verify(&new, &sign(&new)));
+
+        // Dropping the old key from the set invalidates its tokens
+        assert!(!verify(&old, &sign(&new)));
+    }
+}
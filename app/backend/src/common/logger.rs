@@ -1,25 +1,152 @@
-use tracing::{Level, Subscriber};
-use tracing_subscriber::FmtSubscriber;
-
-/// Initialize tracing with the appropriate log level based on the environment
-pub fn init_tracing() {
-    // Determine log level based on environment
-    let log_level = match std::env::var("LOG_LEVEL").unwrap_or_else(|_| "info".into()).to_lowercase().as_str() {
-        "trace" => Level::TRACE,
-        "debug" => Level::DEBUG,
-        "info" => Level::INFO,
-        "warn" => Level::WARN,
-        "error" => Level::ERROR,
-        _ => Level::INFO,
-    };
-    
-    // Create a subscriber with the determined log level
-    let subscriber = FmtSubscriber::builder()
-        .with_max_level(log_level)
-        .finish();
-    
-    // Set the subscriber as the default
-    if let Err(err) = tracing::subscriber::set_global_default(subscriber) {
-        eprintln!("Failed to set tracing subscriber: {}", err);
+//! Log output and request correlation.
+//!
+//! Every invocation runs inside a `request` span carrying the Lambda request ID, the API
+//! Gateway request ID, the matched route and the caller's user ID. Logs are written as JSON
+//! lines that include those fields, so a CloudWatch Logs Insights query on the ID returned
+//! in the `X-Request-Id` header finds every line of a request.
+
+use std::sync::Once;
+
+use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
+use http::HeaderValue;
+use lambda_runtime::Context;
+use tracing::field::Empty;
+use tracing::{info, Level, Span};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::{fmt, Layer, Registry};
+
+use crate::common::config::Config;
+use crate::common::telemetry::{self, TraceContext};
+
+/// Response header carrying the ID a request was logged under
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+static INIT: Once = Once::new();
+
+/// Initialize tracing with the appropriate log level based on the environment.
+///
+/// Logs are JSON unless `LOG_FORMAT=text`, which is easier to read in a terminal. Spans are
+/// also exported over OTLP when `config` names a collector, see [`telemetry`].
+/// Only the first call installs the subscriber; later calls do nothing.
+pub fn init_tracing(config: &Config) {
+    INIT.call_once(|| {
+        // Determine log level based on environment
+        let log_level = match std::env::var("LOG_LEVEL").unwrap_or_else(|_| "info".into()).to_lowercase().as_str() {
+            "trace" => Level::TRACE,
+            "debug" => Level::DEBUG,
+            "info" => Level::INFO,
+            "warn" => Level::WARN,
+            "error" => Level::ERROR,
+            _ => Level::INFO,
+        };
+
+        let output: Box<dyn Layer<Registry> + Send + Sync> = match std::env::var("LOG_FORMAT").as_deref() {
+            Ok("text") => fmt::layer().boxed(),
+            _ => fmt::layer()
+                .json()
+                .flatten_event(true)
+                .with_current_span(false)
+                .with_span_list(true)
+                .boxed(),
+        };
+
+        let subscriber = tracing_subscriber::registry()
+            .with(output)
+            .with(telemetry::layer(config))
+            .with(LevelFilter::from_level(log_level));
+
+        if let Err(err) = tracing::subscriber::set_global_default(subscriber) {
+            eprintln!("Failed to set tracing subscriber: {}", err);
+        }
+
+        // Configuration is loaded before there is a subscriber to log to
+        info!("Configuration loaded for {} environment", config.environment);
+    });
+}
+
+/// The ID a request is logged under: API Gateway's request ID, which clients can also
+/// find in the API Gateway access logs, or the Lambda request ID when invoked directly
+pub fn correlation_id(request: &ApiGatewayProxyRequest, context: &Context) -> String {
+    request
+        .request_context
+        .request_id
+        .clone()
+        .filter(|id| !id.is_empty())
+        .unwrap_or_else(|| context.request_id.clone())
+}
+
+/// Span wrapping one invocation, continuing the caller's trace if it sent one.
+/// `route` and `user_id` are filled in by the router once known, and the status code by
+/// [`record_status`].
+pub fn request_span(request: &ApiGatewayProxyRequest, context: &Context) -> Span {
+    let span = tracing::info_span!(
+        "request",
+        request_id = %correlation_id(request, context),
+        lambda_request_id = %context.request_id,
+        method = %request.http_method,
+        route = Empty,
+        user_id = Empty,
+        otel.kind = "server",
+        http.response.status_code = Empty,
+        otel.status_code = Empty,
+    );
+
+    if let Some(parent) = TraceContext::from_request(request, context) {
+        telemetry::continue_trace(&span, parent);
+    }
+
+    span
+}
+
+/// Record the response status on a request span, marking server errors as failures
+pub fn record_status(span: &Span, status_code: i64) {
+    span.record("http.response.status_code", status_code);
+    if status_code >= 500 {
+        telemetry::record_error(span);
+    }
+}
+
+/// Record the matched route template on the current request span
+pub fn record_route(template: &str) {
+    Span::current().record("route", template);
+}
+
+/// Record the authenticated caller on the current request span
+pub fn record_user(user_id: &str) {
+    Span::current().record("user_id", user_id);
+}
+
+/// Return the request ID to the client so it can be quoted in bug reports
+pub fn set_request_id_header(response: &mut ApiGatewayProxyResponse, request_id: &str) {
+    if let Ok(value) = HeaderValue::from_str(request_id) {
+        response.headers.insert(REQUEST_ID_HEADER, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(request_id: &str) -> Context {
+        let mut context = Context::default();
+        context.request_id = request_id.to_string();
+        context
+    }
+
+    #[test]
+    fn correlation_id_prefers_the_api_gateway_request_id() {
+        let mut request = ApiGatewayProxyRequest::default();
+        assert_eq!(correlation_id(&request, &context("lambda-id")), "lambda-id");
+
+        request.request_context.request_id = Some("apigw-id".to_string());
+        assert_eq!(correlation_id(&request, &context("lambda-id")), "apigw-id");
+    }
+
+    #[test]
+    fn request_id_header_is_set() {
+        let mut response = ApiGatewayProxyResponse::default();
+        set_request_id_header(&mut response, "apigw-id");
+        assert_eq!(response.headers.get(REQUEST_ID_HEADER).unwrap(), "apigw-id");
     }
-} 
\ No newline at end of file
+}
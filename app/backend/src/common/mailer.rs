@@ -0,0 +1,147 @@
+//! Outgoing email.
+//!
+//! Handlers send messages through the [`Mailer`] trait. `mail_backend` selects the
+//! implementation: `outbox` writes each message to a file in `mail_outbox_dir` so local
+//! development needs no mail server, and `sqs` queues each message as JSON on
+//! `mail_queue_url` for the delivery worker.
+
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::Serialize;
+use tokio::sync::OnceCell;
+use tracing::{info, Instrument};
+
+use crate::common::config::{MailBackend, CONFIG};
+use crate::common::error::AppError;
+use crate::common::telemetry;
+
+/// A plain-text email
+#[derive(Debug, Clone, Serialize)]
+pub struct Email {
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+impl Email {
+    /// Create a message from the configured sender address
+    pub fn new(to: &str, subject: &str, body: String) -> Self {
+        Self {
+            from: CONFIG.mail_from.clone(),
+            to: to.to_string(),
+            subject: subject.to_string(),
+            body,
+        }
+    }
+}
+
+/// Delivers outgoing email
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    /// Send a message, returning once it has been handed off for delivery
+    async fn send(&self, email: &Email) -> Result<(), AppError>;
+}
+
+/// Writes each message to a `.eml` file in a directory
+#[derive(Debug, Clone)]
+pub struct OutboxMailer {
+    dir: PathBuf,
+}
+
+impl OutboxMailer {
+    /// Create a mailer writing to `dir`, which is created on first use
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl Mailer for OutboxMailer {
+    async fn send(&self, email: &Email) -> Result<(), AppError> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to create mail outbox: {}", e)))?;
+
+        let now = Utc::now();
+        let path = self
+            .dir
+            .join(format!("{}-{}.eml", now.timestamp_millis(), uuid::Uuid::new_v4()));
+
+        let message = format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{}\r\n",
+            email.from,
+            email.to,
+            email.subject,
+            now.to_rfc2822(),
+            email.body
+        );
+
+        tokio::fs::write(&path, message)
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to write {}: {}", path.display(), e)))?;
+
+        info!("Wrote email \"{}\" to {}", email.subject, path.display());
+        Ok(())
+    }
+}
+
+/// Queues each message on SQS for a separate delivery worker
+#[derive(Debug, Clone)]
+pub struct SqsMailer {
+    client: aws_sdk_sqs::Client,
+    queue_url: String,
+}
+
+impl SqsMailer {
+    /// Create a mailer sending to `queue_url`
+    pub fn new(client: aws_sdk_sqs::Client, queue_url: String) -> Self {
+        Self { client, queue_url }
+    }
+}
+
+#[async_trait]
+impl Mailer for SqsMailer {
+    async fn send(&self, email: &Email) -> Result<(), AppError> {
+        let body = serde_json::to_string(email)
+            .map_err(|e| AppError::Internal(format!("Failed to serialize email: {}", e)))?;
+
+        self.client
+            .send_message()
+            .queue_url(&self.queue_url)
+            .message_body(body)
+            .send()
+            .instrument(telemetry::aws_span("SQS", "SendMessage"))
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Failed to queue email: {}", e)))?;
+
+        info!("Queued email \"{}\"", email.subject);
+        Ok(())
+    }
+}
+
+/// SQS client, built once per container
+static SQS_CLIENT: OnceCell<aws_sdk_sqs::Client> = OnceCell::const_new();
+
+/// Build the mailer selected by `mail_backend`
+pub async fn connect() -> Result<Box<dyn Mailer>, AppError> {
+    match CONFIG.mail_backend {
+        MailBackend::Outbox => Ok(Box::new(OutboxMailer::new(&CONFIG.mail_outbox_dir))),
+        MailBackend::Sqs => {
+            if CONFIG.mail_queue_url.is_empty() {
+                return Err(AppError::Internal("MAIL_QUEUE_URL must be set when MAIL_BACKEND=sqs".to_string()));
+            }
+
+            let client = SQS_CLIENT
+                .get_or_init(|| async {
+                    let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+                    aws_sdk_sqs::Client::new(&config)
+                })
+                .await;
+
+            Ok(Box::new(SqsMailer::new(client.clone(), CONFIG.mail_queue_url.clone())))
+        }
+    }
+}
@@ -0,0 +1,320 @@
+//! CloudWatch metrics in Embedded Metric Format.
+//!
+//! Metrics are written as EMF records: JSON log lines that CloudWatch Logs turns into metrics
+//! without an agent or `PutMetricData` calls. The router runs each request inside [`scope`],
+//! which collects the time spent in database queries and decides where records go. Lambda
+//! writes them to standard output with [`StdoutSink`]; tests collect them with [`MemorySink`].
+
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::common::config::CONFIG;
+use crate::models::execution::{ExecutionStatus, Language};
+
+/// Route dimension for requests that matched no route, so unknown paths do not each
+/// create a metric of their own
+pub const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Unit of a metric value, as named by CloudWatch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Unit {
+    Milliseconds,
+    Count,
+}
+
+/// A single value in a record
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metric {
+    pub name: &'static str,
+    pub unit: Unit,
+    pub value: f64,
+}
+
+/// Metrics sharing a set of dimensions, written as one EMF record
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricRecord {
+    /// Dimension values, such as `("Route", "/courses")`
+    pub dimensions: Vec<(&'static str, String)>,
+    /// Combinations of dimension names each metric is published under
+    pub dimension_sets: Vec<Vec<&'static str>>,
+    pub metrics: Vec<Metric>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl MetricRecord {
+    /// Value of the metric called `name`, if the record has one
+    pub fn value(&self, name: &str) -> Option<f64> {
+        self.metrics.iter().find(|metric| metric.name == name).map(|metric| metric.value)
+    }
+
+    /// Value of the dimension called `name`, if the record has one
+    pub fn dimension(&self, name: &str) -> Option<&str> {
+        self.dimensions
+            .iter()
+            .find(|(dimension, _)| *dimension == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Render the record as an EMF JSON object under `namespace`
+    pub fn to_emf(&self, namespace: &str) -> Value {
+        let definitions: Vec<Value> = self
+            .metrics
+            .iter()
+            .map(|metric| json!({ "Name": metric.name, "Unit": metric.unit }))
+            .collect();
+
+        let mut record = Map::new();
+        record.insert(
+            "_aws".to_string(),
+            json!({
+                "Timestamp": self.timestamp.timestamp_millis(),
+                "CloudWatchMetrics": [{
+                    "Namespace": namespace,
+                    "Dimensions": self.dimension_sets,
+                    "Metrics": definitions,
+                }],
+            }),
+        );
+        for (name, value) in &self.dimensions {
+            record.insert(name.to_string(), Value::from(value.clone()));
+        }
+        for metric in &self.metrics {
+            record.insert(metric.name.to_string(), Value::from(metric.value));
+        }
+
+        Value::Object(record)
+    }
+}
+
+/// Destination for metric records
+pub trait MetricSink: Send + Sync {
+    fn emit(&self, record: MetricRecord);
+}
+
+/// Writes records to standard output, where the Lambda runtime passes them to CloudWatch Logs
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StdoutSink;
+
+impl MetricSink for StdoutSink {
+    fn emit(&self, record: MetricRecord) {
+        println!("{}", record.to_emf(&CONFIG.metrics_namespace));
+    }
+}
+
+/// Keeps records in memory so tests can assert on them
+#[derive(Debug, Default)]
+pub struct MemorySink {
+    records: Mutex<Vec<MetricRecord>>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every record emitted so far
+    pub fn records(&self) -> Vec<MetricRecord> {
+        self.records.lock().map(|records| records.clone()).unwrap_or_default()
+    }
+}
+
+impl MetricSink for MemorySink {
+    fn emit(&self, record: MetricRecord) {
+        if let Ok(mut records) = self.records.lock() {
+            records.push(record);
+        }
+    }
+}
+
+/// State of the request being served
+struct RequestScope {
+    sink: Arc<dyn MetricSink>,
+    db_time_micros: AtomicU64,
+    db_queries: AtomicU64,
+}
+
+tokio::task_local! {
+    static CURRENT: RequestScope;
+}
+
+/// Run `future` as one request: its records go to `sink`, and database queries it makes
+/// count towards its `DbTime`
+pub async fn scope<F: Future>(sink: Arc<dyn MetricSink>, future: F) -> F::Output {
+    let scope = RequestScope {
+        sink,
+        db_time_micros: AtomicU64::new(0),
+        db_queries: AtomicU64::new(0),
+    };
+
+    CURRENT.scope(scope, future).await
+}
+
+/// Send a record to the current request's sink, or to standard output outside a request
+pub fn emit(record: MetricRecord) {
+    match CURRENT.try_with(|scope| scope.sink.clone()) {
+        Ok(sink) => sink.emit(record),
+        Err(_) => StdoutSink.emit(record),
+    }
+}
+
+/// Add a finished database query to the current request
+pub fn record_db_query(elapsed: Duration) {
+    let micros = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+    let _ = CURRENT.try_with(|scope| {
+        scope.db_time_micros.fetch_add(micros, Ordering::Relaxed);
+        scope.db_queries.fetch_add(1, Ordering::Relaxed);
+    });
+}
+
+/// Record a served request: its latency, the database time within it, and its status code
+pub fn record_request(route: &str, method: &str, status_code: i64, latency: Duration) {
+    let (db_time, db_queries) = CURRENT
+        .try_with(|scope| {
+            let micros = scope.db_time_micros.load(Ordering::Relaxed);
+            (Duration::from_micros(micros), scope.db_queries.load(Ordering::Relaxed))
+        })
+        .unwrap_or_default();
+
+    emit(MetricRecord {
+        dimensions: vec![
+            ("Route", route.to_string()),
+            ("Method", method.to_string()),
+            ("StatusCode", status_code.to_string()),
+        ],
+        dimension_sets: vec![vec!["Route", "Method"], vec!["Route", "Method", "StatusCode"]],
+        metrics: vec![
+            Metric { name: "Requests", unit: Unit::Count, value: 1.0 },
+            Metric { name: "Latency", unit: Unit::Milliseconds, value: millis(latency) },
+            Metric { name: "DbTime", unit: Unit::Milliseconds, value: millis(db_time) },
+            Metric { name: "DbQueries", unit: Unit::Count, value: db_queries as f64 },
+        ],
+        timestamp: Utc::now(),
+    });
+}
+
+/// Record the outcome of running a submission
+pub fn record_execution(language: &Language, status: &ExecutionStatus, elapsed: Duration) {
+    emit(MetricRecord {
+        dimensions: vec![("Language", label(language)), ("ExecutionStatus", label(status))],
+        dimension_sets: vec![vec!["Language"], vec!["Language", "ExecutionStatus"]],
+        metrics: vec![
+            Metric { name: "Executions", unit: Unit::Count, value: 1.0 },
+            Metric { name: "ExecutionTime", unit: Unit::Milliseconds, value: millis(elapsed) },
+        ],
+        timestamp: Utc::now(),
+    });
+}
+
+/// Dimension value for an enum, matching its name in the API
+fn label<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(Value::String(name)) => name,
+        _ => "unknown".to_string(),
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
+    use http::Method;
+    use lambda_runtime::{Context, LambdaEvent};
+
+    use super::*;
+    use crate::common::router::{AuthRequirement, Router};
+
+    fn event(method: Method, path: &str) -> LambdaEvent<ApiGatewayProxyRequest> {
+        let request = ApiGatewayProxyRequest {
+            http_method: method,
+            path: Some(path.to_string()),
+            ..Default::default()
+        };
+        LambdaEvent::new(request, Context::default())
+    }
+
+    #[tokio::test]
+    async fn requests_are_recorded_per_route_and_status() {
+        let sink = Arc::new(MemorySink::new());
+        let router = Router::new()
+            .route(Method::GET, "/courses/{id}", AuthRequirement::Public, |_| async {
+                record_db_query(Duration::from_millis(3));
+                record_db_query(Duration::from_millis(4));
+                Ok(ApiGatewayProxyResponse { status_code: 200, ..Default::default() })
+            })
+            .metrics(sink.clone());
+
+        router.dispatch(event(Method::GET, "/courses/abc")).await.unwrap();
+        router.dispatch(event(Method::DELETE, "/courses/abc")).await.unwrap();
+        router.dispatch(event(Method::GET, "/nowhere")).await.unwrap();
+
+        let records = sink.records();
+        assert_eq!(records.len(), 3);
+
+        assert_eq!(records[0].dimension("Route"), Some("/courses/{id}"));
+        assert_eq!(records[0].dimension("Method"), Some("GET"));
+        assert_eq!(records[0].dimension("StatusCode"), Some("200"));
+        assert_eq!(records[0].value("Requests"), Some(1.0));
+        assert_eq!(records[0].value("DbQueries"), Some(2.0));
+        assert!(records[0].value("DbTime").unwrap() >= 7.0);
+        assert!(records[0].value("Latency").is_some());
+
+        // The route is known even when the method is not allowed
+        assert_eq!(records[1].dimension("Route"), Some("/courses/{id}"));
+        assert_eq!(records[1].dimension("StatusCode"), Some("405"));
+        assert_eq!(records[1].value("DbQueries"), Some(0.0));
+
+        assert_eq!(records[2].dimension("Route"), Some(UNMATCHED_ROUTE));
+        assert_eq!(records[2].dimension("StatusCode"), Some("404"));
+    }
+
+    #[tokio::test]
+    async fn executions_are_recorded_by_language_and_status() {
+        let sink = Arc::new(MemorySink::new());
+        scope(sink.clone(), async {
+            record_execution(&Language::JavaScript, &ExecutionStatus::MemoryLimitExceeded, Duration::from_millis(12));
+        })
+        .await;
+
+        let records = sink.records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].dimension("Language"), Some("javascript"));
+        assert_eq!(records[0].dimension("ExecutionStatus"), Some("MEMORY_LIMIT_EXCEEDED"));
+        assert_eq!(records[0].value("Executions"), Some(1.0));
+        assert_eq!(records[0].value("ExecutionTime"), Some(12.0));
+    }
+
+    #[test]
+    fn records_render_as_emf() {
+        let record = MetricRecord {
+            dimensions: vec![("Route", "/courses".to_string())],
+            dimension_sets: vec![vec!["Route"]],
+            metrics: vec![Metric { name: "Latency", unit: Unit::Milliseconds, value: 5.0 }],
+            timestamp: DateTime::from_timestamp_millis(1_700_000_000_000).unwrap(),
+        };
+
+        assert_eq!(
+            record.to_emf("KaijuAcademy"),
+            json!({
+                "_aws": {
+                    "Timestamp": 1_700_000_000_000i64,
+                    "CloudWatchMetrics": [{
+                        "Namespace": "KaijuAcademy",
+                        "Dimensions": [["Route"]],
+                        "Metrics": [{ "Name": "Latency", "Unit": "Milliseconds" }],
+                    }],
+                },
+                "Route": "/courses",
+                "Latency": 5.0,
+            })
+        );
+    }
+}
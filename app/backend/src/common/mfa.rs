@@ -0,0 +1,105 @@
+//! Multi-factor authentication.
+//!
+//! Users may enroll a TOTP authenticator (see [`totp`](crate::common::totp)). Once it is
+//! confirmed, a correct password no longer logs them in: login answers with a short-lived
+//! MFA-pending token instead, which `POST /auth/mfa/verify` exchanges for real tokens along
+//! with a code from the app or one of the recovery codes issued at enrollment.
+//!
+//! Roles listed in `mfa_required_roles` cannot skip this. Their logins without an
+//! authenticator get a pending token that is only good for enrolling one.
+
+use aws_lambda_events::event::apigw::ApiGatewayProxyRequest;
+use chrono::{DateTime, Utc};
+use surrealdb::sql::Thing;
+
+use crate::common::auth::{self, Claims, MfaStep};
+use crate::common::config::CONFIG;
+use crate::common::error::{AppError, ErrorCode, FieldError};
+use crate::common::extract::AuthContext;
+use crate::common::totp;
+use crate::models::mfa::TotpEnrollment;
+use crate::models::user::{User, UserRole};
+use crate::repository::{record_id, MfaRepository, TokenRepository};
+
+/// Must users with this role use MFA?
+pub fn required_for(role: &UserRole) -> bool {
+    CONFIG.mfa_required_roles.contains(role)
+}
+
+/// The second step a user's login must complete, or `None` if the password is enough
+pub async fn pending_step(repo: &impl MfaRepository, user: &User) -> Result<Option<MfaStep>, AppError> {
+    let Some(id) = &user.id else {
+        return Err(AppError::Internal("User record has no ID".to_string()));
+    };
+
+    let enrolled = repo.find_totp(id).await?.is_some_and(|totp| totp.is_confirmed());
+
+    Ok(if enrolled {
+        Some(MfaStep::Verify)
+    } else if required_for(&user.role) {
+        Some(MfaStep::Enroll)
+    } else {
+        None
+    })
+}
+
+/// Who is managing an authenticator
+#[derive(Debug)]
+pub struct EnrollmentCaller {
+    pub user: Thing,
+    /// The enrollment-pending token, when the caller is finishing a login rather than signed in
+    pub pending: Option<Claims>,
+}
+
+/// Identify the caller of an enrollment endpoint, from either their access token or an
+/// enrollment-pending token in the body
+pub async fn enrollment_caller(
+    repo: &impl TokenRepository,
+    request: &ApiGatewayProxyRequest,
+    mfa_token: Option<&str>,
+) -> Result<EnrollmentCaller, AppError> {
+    if let Some(token) = mfa_token {
+        let claims = auth::validate_mfa_token(repo, token, MfaStep::Enroll).await?;
+        let user = record_id("user", &claims.sub)
+            .map_err(|_| AppError::Authentication(ErrorCode::InvalidToken, "Invalid token subject".to_string()))?;
+        return Ok(EnrollmentCaller { user, pending: Some(claims) });
+    }
+
+    let auth = AuthContext::from_request(request)?;
+    Ok(EnrollmentCaller { user: auth.user, pending: None })
+}
+
+/// Check a code from the user's authenticator, or else one of their recovery codes, and
+/// use it up so it cannot be presented again
+pub async fn check_second_factor(
+    repo: &impl MfaRepository,
+    enrollment: &TotpEnrollment,
+    code: Option<&str>,
+    recovery_code: Option<&str>,
+    now: DateTime<Utc>,
+) -> Result<(), AppError> {
+    let accepted = match (code, recovery_code) {
+        (Some(code), _) if !code.trim().is_empty() => {
+            match totp::verify(&enrollment.secret, code, now.timestamp() as u64, enrollment.last_used_step) {
+                Some(step) => repo.record_totp_step(&enrollment.user, step).await?,
+                None => false,
+            }
+        }
+        (_, Some(recovery_code)) if !recovery_code.trim().is_empty() => {
+            repo.consume_recovery_code(&enrollment.user, &totp::hash_recovery_code(recovery_code))
+                .await?
+        }
+        _ => {
+            return Err(AppError::InvalidFields(vec![
+                FieldError::required("code", "A code or recovery code is required"),
+                FieldError::required("recovery_code", "A code or recovery code is required"),
+            ]));
+        }
+    };
+
+    if accepted {
+        Ok(())
+    } else {
+        Err(AppError::Authentication(ErrorCode::InvalidMfaCode, "Invalid code".to_string()))
+    }
+}
@@ -1,5 +1,18 @@
 pub mod logger;
 pub mod error;
 pub mod db;
+pub mod audit;
 pub mod auth;
-pub mod config; 
\ No newline at end of file
+pub mod config;
+pub mod keys;
+pub mod mailer;
+pub mod metrics;
+pub mod mfa;
+pub mod password;
+pub mod policy;
+pub mod response;
+pub mod router;
+pub mod telemetry;
+pub mod throttle;
+pub mod totp;
+pub mod extract; 
\ No newline at end of file
@@ -0,0 +1,174 @@
+//! Password policy.
+//!
+//! New passwords, at registration and reset, must be `password_min_length` to
+//! `password_max_length` characters, must not appear in the breached password list named
+//! by `password_breached_list` (a text file with one password per line), and must not
+//! contain the account's email address or its local part.
+//!
+//! Entry points call [`init`] at startup so a missing list or bad `argon2_*` costs stop the
+//! process instead of failing the first registration.
+
+use std::collections::HashSet;
+
+use once_cell::sync::{Lazy, OnceCell};
+
+use crate::common::auth;
+use crate::common::config::{Config, ConfigError, CONFIG};
+use crate::common::error::{AppError, ErrorCode};
+
+/// Local parts shorter than this are too common to reject passwords over
+const MIN_SIMILAR_LEN: usize = 3;
+
+/// Rules every new password must pass
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    min_length: usize,
+    max_length: usize,
+    breached: HashSet<String>,
+}
+
+impl PasswordPolicy {
+    /// Build the policy from the password settings in `config`, reading the breached list
+    pub fn from_config(config: &Config) -> Result<Self, ConfigError> {
+        auth::argon2_params(config)
+            .map_err(|e| ConfigError::InvalidPasswordPolicy(format!("invalid Argon2 costs: {}", e)))?;
+
+        if config.password_min_length == 0 || config.password_min_length > config.password_max_length {
+            return Err(ConfigError::InvalidPasswordPolicy(format!(
+                "password length limits {}..{} are empty",
+                config.password_min_length, config.password_max_length
+            )));
+        }
+
+        let breached = if config.password_breached_list.is_empty() {
+            HashSet::new()
+        } else {
+            let list = std::fs::read_to_string(&config.password_breached_list).map_err(|e| {
+                ConfigError::InvalidPasswordPolicy(format!(
+                    "failed to read {}: {}",
+                    config.password_breached_list, e
+                ))
+            })?;
+
+            list.lines()
+                .map(str::trim_end)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+
+        Ok(Self {
+            min_length: config.password_min_length,
+            max_length: config.password_max_length,
+            breached,
+        })
+    }
+
+    /// Check a new password for the account with the given email
+    pub fn check(&self, password: &str, email: &str) -> Result<(), AppError> {
+        let length = password.chars().count();
+        if length < self.min_length {
+            return Err(AppError::invalid_field(
+                "password",
+                ErrorCode::TooShort,
+                format!("Password must be at least {} characters", self.min_length),
+            ));
+        }
+        if length > self.max_length {
+            return Err(AppError::invalid_field(
+                "password",
+                ErrorCode::TooLong,
+                format!("Password must be at most {} characters", self.max_length),
+            ));
+        }
+
+        if self.breached.contains(password) {
+            return Err(AppError::invalid_field(
+                "password",
+                ErrorCode::PasswordBreached,
+                "This password has appeared in a data breach. Choose a different one.",
+            ));
+        }
+
+        if resembles_email(password, email) {
+            return Err(AppError::invalid_field(
+                "password",
+                ErrorCode::PasswordContainsEmail,
+                "Password must not contain your email address",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Does the password contain the email address or its local part?
+fn resembles_email(password: &str, email: &str) -> bool {
+    let password = password.to_lowercase();
+    let email = email.trim().to_lowercase();
+    let local = email.split('@').next().unwrap_or_default();
+
+    (!email.is_empty() && password.contains(&email))
+        || (local.chars().count() >= MIN_SIMILAR_LEN && password.contains(local))
+}
+
+/// Policy loaded once per process
+static LOADED: OnceCell<PasswordPolicy> = OnceCell::new();
+
+/// Load the password policy
+pub fn init() -> Result<&'static PasswordPolicy, ConfigError> {
+    LOADED.get_or_try_init(|| PasswordPolicy::from_config(&CONFIG))
+}
+
+/// Global password policy, lazily loaded
+pub static POLICY: Lazy<&'static PasswordPolicy> =
+    Lazy::new(|| init().unwrap_or_else(|err| panic!("Invalid password policy: {}", err)));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn length_limits_count_characters() {
+        let policy = PasswordPolicy::from_config(&Config::default()).unwrap();
+
+        assert!(policy.check("short", "ada@example.com").is_err());
+        assert!(policy.check("ŝŝŝŝŝŝŝŝ", "ada@example.com").is_ok());
+        assert!(policy.check(&"x".repeat(129), "ada@example.com").is_err());
+    }
+
+    #[test]
+    fn breached_passwords_are_refused() {
+        let path = std::env::temp_dir().join(format!("breached-{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "password1\ncorrect horse battery staple\n\n").unwrap();
+
+        let policy = PasswordPolicy::from_config(&Config {
+            password_breached_list: path.display().to_string(),
+            ..Config::default()
+        })
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(policy.check("correct horse battery staple", "ada@example.com").is_err());
+        assert!(policy.check("correct horse battery stapler", "ada@example.com").is_ok());
+    }
+
+    #[test]
+    fn passwords_resembling_the_email_are_refused() {
+        let policy = PasswordPolicy::from_config(&Config::default()).unwrap();
+
+        assert!(policy.check("Ada@Example.com", "ada@example.com").is_err());
+        assert!(policy.check("lovelace1815", "lovelace@example.com").is_err());
+        assert!(policy.check("adamant horse", "ad@example.com").is_ok());
+    }
+
+    #[test]
+    fn missing_breached_list_fails_to_load() {
+        let config = Config {
+            password_breached_list: "/nonexistent/breached.txt".to_string(),
+            ..Config::default()
+        };
+
+        assert!(PasswordPolicy::from_config(&config).is_err());
+    }
+}
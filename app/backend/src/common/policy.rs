@@ -0,0 +1,236 @@
+//! Role-based access control.
+//!
+//! Each role grants a fixed set of permissions. Permissions on resources that have an
+//! owner come in two scopes: `.own` covers resources the caller owns, such as courses they
+//! teach, and `.any` covers every resource of that kind. Handlers and the router ask
+//! [`authorize`] whether the caller may take an action, passing the owner of the resource
+//! it concerns; no other code compares roles.
+
+use std::fmt;
+
+use surrealdb::sql::Thing;
+
+use crate::common::error::{AppError, ErrorCode};
+use crate::common::extract::AuthContext;
+use crate::models::user::UserRole;
+
+/// A capability granted to roles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    CourseCreate,
+    CourseReadDraftOwn,
+    CourseReadDraftAny,
+    CourseUpdateOwn,
+    CourseUpdateAny,
+    CourseDeleteOwn,
+    CourseDeleteAny,
+    CodeExecute,
+    SecurityEventRead,
+    LockoutManage,
+    AuditEventRead,
+}
+
+impl Permission {
+    /// Dotted name, such as `course.update.own`
+    pub fn name(self) -> &'static str {
+        match self {
+            Permission::CourseCreate => "course.create",
+            Permission::CourseReadDraftOwn => "course.read_draft.own",
+            Permission::CourseReadDraftAny => "course.read_draft.any",
+            Permission::CourseUpdateOwn => "course.update.own",
+            Permission::CourseUpdateAny => "course.update.any",
+            Permission::CourseDeleteOwn => "course.delete.own",
+            Permission::CourseDeleteAny => "course.delete.any",
+            Permission::CodeExecute => "code.execute",
+            Permission::SecurityEventRead => "security_event.read",
+            Permission::LockoutManage => "lockout.manage",
+            Permission::AuditEventRead => "audit_event.read",
+        }
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Permissions granted to each role
+pub fn permissions(role: &UserRole) -> &'static [Permission] {
+    use Permission::*;
+
+    match role {
+        UserRole::Student => &[CodeExecute],
+        UserRole::Educator => &[CourseCreate, CourseReadDraftOwn, CourseUpdateOwn, CourseDeleteOwn, CodeExecute],
+        // Moderators review and correct course content, but do not publish courses of their own
+        UserRole::Moderator => &[CourseReadDraftAny, CourseUpdateAny, CodeExecute],
+        UserRole::Admin => &[
+            CourseCreate,
+            CourseReadDraftAny,
+            CourseUpdateAny,
+            CourseDeleteAny,
+            CodeExecute,
+            SecurityEventRead,
+            LockoutManage,
+            AuditEventRead,
+        ],
+    }
+}
+
+/// Something a caller may try to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    CreateCourse,
+    /// See a course that is not published yet
+    ReadDraftCourse,
+    UpdateCourse,
+    DeleteCourse,
+    ExecuteCode,
+    ReadSecurityEvents,
+    ManageLockouts,
+    ReadAuditEvents,
+}
+
+impl Action {
+    /// The permission allowing the action on any resource, and the one allowing it on the caller's own
+    fn permissions(self) -> (Permission, Option<Permission>) {
+        match self {
+            Action::CreateCourse => (Permission::CourseCreate, None),
+            Action::ReadDraftCourse => (Permission::CourseReadDraftAny, Some(Permission::CourseReadDraftOwn)),
+            Action::UpdateCourse => (Permission::CourseUpdateAny, Some(Permission::CourseUpdateOwn)),
+            Action::DeleteCourse => (Permission::CourseDeleteAny, Some(Permission::CourseDeleteOwn)),
+            Action::ExecuteCode => (Permission::CodeExecute, None),
+            Action::ReadSecurityEvents => (Permission::SecurityEventRead, None),
+            Action::ManageLockouts => (Permission::LockoutManage, None),
+            Action::ReadAuditEvents => (Permission::AuditEventRead, None),
+        }
+    }
+
+    /// Message returned when the action is denied
+    fn denial(self) -> &'static str {
+        match self {
+            Action::CreateCourse => "Only educators and admins can create courses",
+            Action::ReadDraftCourse => "You do not have permission to access this unpublished course",
+            Action::UpdateCourse => "You do not have permission to update this course",
+            Action::DeleteCourse => "You do not have permission to delete this course",
+            Action::ExecuteCode => "You do not have permission to run code",
+            Action::ReadSecurityEvents | Action::ManageLockouts | Action::ReadAuditEvents => {
+                "You do not have permission to access this resource"
+            }
+        }
+    }
+}
+
+/// May `role` take `action` on a resource, given whether the caller owns it?
+pub fn is_allowed(role: &UserRole, action: Action, owns_resource: bool) -> bool {
+    let granted = permissions(role);
+    let (any, own) = action.permissions();
+
+    granted.contains(&any) || (owns_resource && own.is_some_and(|own| granted.contains(&own)))
+}
+
+/// Decide whether the caller may take `action` on a resource owned by `owner`.
+///
+/// Pass `None` as the owner for actions that do not concern an existing resource, such as
+/// creating a course; `.own` permissions never apply to those.
+pub fn authorize(auth: &AuthContext, action: Action, owner: Option<&Thing>) -> Result<(), AppError> {
+    let owns_resource = owner.is_some_and(|owner| auth.is_user(owner));
+
+    if is_allowed(&auth.role, action, owns_resource) {
+        Ok(())
+    } else {
+        Err(AppError::Authorization(ErrorCode::Forbidden, action.denial().to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::common::extract::TokenMetadata;
+
+    use UserRole::{Admin, Educator, Moderator, Student};
+
+    const ROLES: [UserRole; 4] = [Student, Educator, Moderator, Admin];
+
+    /// The full permission matrix: for each action, whether each role in [`ROLES`] may take
+    /// it on a resource it owns, and on someone else's
+    const MATRIX: &[(Action, [(bool, bool); 4])] = &[
+        //                            (own, others')   Student         Educator       Moderator      Admin
+        (Action::CreateCourse,       [(false, false), (true, true),   (false, false), (true, true)]),
+        (Action::ReadDraftCourse,    [(false, false), (true, false),  (true, true),   (true, true)]),
+        (Action::UpdateCourse,       [(false, false), (true, false),  (true, true),   (true, true)]),
+        (Action::DeleteCourse,       [(false, false), (true, false),  (false, false), (true, true)]),
+        (Action::ExecuteCode,        [(true, true),   (true, true),   (true, true),   (true, true)]),
+        (Action::ReadSecurityEvents, [(false, false), (false, false), (false, false), (true, true)]),
+        (Action::ManageLockouts,     [(false, false), (false, false), (false, false), (true, true)]),
+        (Action::ReadAuditEvents,    [(false, false), (false, false), (false, false), (true, true)]),
+    ];
+
+    fn caller(role: UserRole) -> AuthContext {
+        AuthContext {
+            user: Thing::from(("user", "caller")),
+            role,
+            token: TokenMetadata {
+                id: "jti".to_string(),
+                issued_at: Utc::now(),
+                expires_at: Utc::now(),
+                session: None,
+            },
+            scopes: None,
+        }
+    }
+
+    #[test]
+    fn permission_matrix() {
+        let own = Thing::from(("user", "caller"));
+        let others = Thing::from(("user", "someone_else"));
+
+        for (action, expected) in MATRIX {
+            for (role, (on_own, on_others)) in ROLES.iter().zip(expected) {
+                let auth = caller(role.clone());
+                assert_eq!(authorize(&auth, *action, Some(&own)).is_ok(), *on_own, "{:?} {:?} on own", role, action);
+                assert_eq!(authorize(&auth, *action, Some(&others)).is_ok(), *on_others, "{:?} {:?} on others'", role, action);
+            }
+        }
+    }
+
+    #[test]
+    fn own_permissions_need_an_owner() {
+        // Without a resource there is nothing to own, so only `.any` permissions count
+        assert!(authorize(&caller(Educator), Action::UpdateCourse, None).is_err());
+        assert!(authorize(&caller(Moderator), Action::UpdateCourse, None).is_ok());
+        assert!(authorize(&caller(Educator), Action::CreateCourse, None).is_ok());
+    }
+
+    #[test]
+    fn matrix_covers_every_permission() {
+        let mut named: Vec<&str> = ROLES.iter().flat_map(permissions).map(|permission| permission.name()).collect();
+        named.sort();
+        named.dedup();
+
+        assert_eq!(
+            named,
+            [
+                "audit_event.read",
+                "code.execute",
+                "course.create",
+                "course.delete.any",
+                "course.delete.own",
+                "course.read_draft.any",
+                "course.read_draft.own",
+                "course.update.any",
+                "course.update.own",
+                "lockout.manage",
+                "security_event.read",
+            ]
+        );
+    }
+
+    #[test]
+    fn denials_explain_the_action() {
+        let err = authorize(&caller(Student), Action::DeleteCourse, None).unwrap_err();
+        assert!(matches!(err, AppError::Authorization(ErrorCode::Forbidden, message) if message.contains("delete this course")));
+    }
+}
@@ -0,0 +1,188 @@
+//! Building responses, and the headers the router adds to every one of them.
+//!
+//! Handlers return [`json_response`]s. The router answers `OPTIONS` requests itself with
+//! [`options`], then passes every response through [`apply_headers`], which adds CORS headers
+//! for origins in `CORS_ALLOWED_ORIGINS` and the security and caching headers, so the API
+//! behaves the same behind any API Gateway configuration.
+
+use aws_lambda_events::encodings::Body;
+use aws_lambda_events::event::apigw::ApiGatewayProxyResponse;
+use http::header::{self, HeaderValue};
+use http::HeaderMap;
+use serde_json::Value;
+
+use crate::common::config::{OriginList, CONFIG};
+
+/// Request headers browsers may send cross-origin
+const ALLOWED_HEADERS: &str = "Authorization, Content-Type, traceparent, X-Amzn-Trace-Id";
+
+/// Response headers cross-origin scripts may read
+const EXPOSED_HEADERS: &str = "Allow, Retry-After, X-Request-Id";
+
+/// How long browsers may cache a preflight response, in seconds
+const PREFLIGHT_MAX_AGE: &str = "600";
+
+/// Browsers must use HTTPS for two years, including on subdomains
+const STRICT_TRANSPORT_SECURITY: &str = "max-age=63072000; includeSubDomains";
+
+/// Responses carry tokens and personal data, so nothing is cached unless a handler says so
+const DEFAULT_CACHE_CONTROL: &str = "no-store";
+
+/// A response with `body` serialized as JSON
+pub fn json_response(status_code: i64, body: Value) -> ApiGatewayProxyResponse {
+    let mut headers = HeaderMap::new();
+    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+    ApiGatewayProxyResponse {
+        status_code,
+        headers,
+        multi_value_headers: HeaderMap::new(),
+        body: Some(Body::from(body.to_string())),
+        is_base64_encoded: false,
+    }
+}
+
+/// Answer an `OPTIONS` request, including a CORS preflight, for a path that supports `allow`
+pub fn options(allow: &str) -> ApiGatewayProxyResponse {
+    let mut headers = HeaderMap::new();
+    if let Ok(allow) = HeaderValue::from_str(allow) {
+        headers.insert(header::ALLOW, allow.clone());
+        headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, allow);
+    }
+    headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, HeaderValue::from_static(ALLOWED_HEADERS));
+    headers.insert(header::ACCESS_CONTROL_MAX_AGE, HeaderValue::from_static(PREFLIGHT_MAX_AGE));
+
+    ApiGatewayProxyResponse {
+        status_code: 204,
+        headers,
+        multi_value_headers: HeaderMap::new(),
+        body: None,
+        is_base64_encoded: false,
+    }
+}
+
+/// Add CORS, security and caching headers to a response for a request from `origin`
+pub fn apply_headers(origin: Option<&HeaderValue>, response: &mut ApiGatewayProxyResponse) {
+    apply_headers_for(&CONFIG.cors_allowed_origins, origin, response);
+}
+
+fn apply_headers_for(allowed: &OriginList, origin: Option<&HeaderValue>, response: &mut ApiGatewayProxyResponse) {
+    let headers = &mut response.headers;
+
+    // Without an allowed origin the browser refuses the response, preflights included
+    let allowed_origin = origin.filter(|origin| origin.to_str().is_ok_and(|origin| allowed.allows(origin)));
+    match allowed_origin {
+        Some(origin) => {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone());
+            headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, HeaderValue::from_static(EXPOSED_HEADERS));
+        }
+        None => {
+            headers.remove(header::ACCESS_CONTROL_ALLOW_METHODS);
+            headers.remove(header::ACCESS_CONTROL_ALLOW_HEADERS);
+            headers.remove(header::ACCESS_CONTROL_MAX_AGE);
+        }
+    }
+    // The CORS headers depend on the origin, so shared caches must key on it
+    headers.insert(header::VARY, HeaderValue::from_static("Origin"));
+
+    headers.insert(header::STRICT_TRANSPORT_SECURITY, HeaderValue::from_static(STRICT_TRANSPORT_SECURITY));
+    headers.insert(header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+    if !headers.contains_key(header::CACHE_CONTROL) {
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static(DEFAULT_CACHE_CONTROL));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use aws_lambda_events::event::apigw::ApiGatewayProxyRequest;
+    use http::Method;
+    use lambda_runtime::{Context, LambdaEvent};
+
+    use super::*;
+    use crate::common::router::{AuthRequirement, Router};
+
+    fn origins(list: &str) -> OriginList {
+        list.parse().unwrap()
+    }
+
+    #[test]
+    fn json_responses_carry_the_body() {
+        let response = json_response(201, serde_json::json!({ "ok": true }));
+
+        assert_eq!(response.status_code, 201);
+        assert_eq!(response.headers[header::CONTENT_TYPE], "application/json");
+        assert_eq!(response.body, Some(Body::from(r#"{"ok":true}"#)));
+    }
+
+    #[test]
+    fn allowed_origins_get_cors_headers() {
+        let mut response = json_response(200, Value::Null);
+        let origin = HeaderValue::from_static("https://academy.example.com");
+        apply_headers_for(&origins("https://academy.example.com, http://localhost:5173"), Some(&origin), &mut response);
+
+        assert_eq!(response.headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://academy.example.com");
+        assert!(response.headers[header::ACCESS_CONTROL_EXPOSE_HEADERS].to_str().unwrap().contains("X-Request-Id"));
+        assert_eq!(response.headers[header::VARY], "Origin");
+    }
+
+    #[test]
+    fn other_origins_get_no_cors_headers() {
+        let mut response = options("GET, OPTIONS");
+        let origin = HeaderValue::from_static("https://evil.example.com");
+        apply_headers_for(&origins("https://academy.example.com"), Some(&origin), &mut response);
+
+        assert_eq!(response.status_code, 204);
+        assert!(!response.headers.contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+        assert!(!response.headers.contains_key(header::ACCESS_CONTROL_ALLOW_METHODS));
+        assert_eq!(response.headers[header::ALLOW], "GET, OPTIONS");
+    }
+
+    #[test]
+    fn preflights_from_allowed_origins_list_methods_and_headers() {
+        let mut response = options("DELETE, GET, OPTIONS, PUT");
+        let origin = HeaderValue::from_static("http://localhost:5173");
+        apply_headers_for(&origins("*"), Some(&origin), &mut response);
+
+        assert_eq!(response.headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "http://localhost:5173");
+        assert_eq!(response.headers[header::ACCESS_CONTROL_ALLOW_METHODS], "DELETE, GET, OPTIONS, PUT");
+        assert!(response.headers[header::ACCESS_CONTROL_ALLOW_HEADERS].to_str().unwrap().contains("Authorization"));
+    }
+
+    #[test]
+    fn security_headers_are_always_set() {
+        let mut response = json_response(200, Value::Null);
+        apply_headers_for(&OriginList::default(), None, &mut response);
+
+        assert!(response.headers.contains_key(header::STRICT_TRANSPORT_SECURITY));
+        assert_eq!(response.headers[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert_eq!(response.headers[header::CACHE_CONTROL], "no-store");
+
+        // Handlers may opt in to caching
+        let mut response = json_response(200, Value::Null);
+        response.headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("public, max-age=300"));
+        apply_headers_for(&OriginList::default(), None, &mut response);
+        assert_eq!(response.headers[header::CACHE_CONTROL], "public, max-age=300");
+    }
+
+    #[tokio::test]
+    async fn the_router_answers_preflights_without_authentication() {
+        let router = Router::new()
+            .route(Method::GET, "/courses/{id}", AuthRequirement::Authenticated, |_| async {
+                Ok(json_response(200, Value::Null))
+            })
+            .route(Method::PUT, "/courses/{id}", AuthRequirement::Authenticated, |_| async {
+                Ok(json_response(200, Value::Null))
+            });
+        let request = ApiGatewayProxyRequest {
+            http_method: Method::OPTIONS,
+            path: Some("/courses/abc".to_string()),
+            ..Default::default()
+        };
+
+        let response = router.dispatch(LambdaEvent::new(request, Context::default())).await.unwrap();
+
+        assert_eq!(response.status_code, 204);
+        assert_eq!(response.headers[header::ALLOW], "GET, OPTIONS, PUT");
+        assert_eq!(response.headers[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+    }
+}
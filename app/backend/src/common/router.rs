@@ -0,0 +1,460 @@
+use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
+use http::{HeaderValue, Method};
+use lambda_runtime::{Error, LambdaEvent};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::info;
+
+use crate::common::error::{AppError, ErrorCode};
+use crate::common::extract::AuthContext;
+use crate::common::logger;
+use crate::common::metrics::{self, MetricSink, StdoutSink};
+use crate::common::response;
+use crate::common::policy::{self, Action};
+use crate::models::token::TokenScope;
+use crate::repository;
+
+/// Future returned by a boxed route handler
+pub type HandlerFuture = Pin<Box<dyn Future<Output = Result<ApiGatewayProxyResponse, Error>> + Send>>;
+
+type BoxedHandler = Box<dyn Fn(LambdaEvent<ApiGatewayProxyRequest>) -> HandlerFuture + Send + Sync>;
+
+/// Authentication requirement attached to a route
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthRequirement {
+    /// No token needed
+    Public,
+    /// Any valid token
+    Authenticated,
+    /// A valid token whose role may take the action, checked with [`policy::authorize`]
+    Permitted(Action),
+}
+
+/// A single segment of a route template
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Param(String),
+}
+
+/// A registered route
+struct Route {
+    method: Method,
+    template: String,
+    segments: Vec<Segment>,
+    auth: AuthRequirement,
+    /// Scope a personal access token needs; routes without one refuse them
+    scope: Option<TokenScope>,
+    handler: BoxedHandler,
+}
+
+impl Route {
+    /// Match a request path against this route, returning the extracted parameters
+    fn matches(&self, path_segments: &[String]) -> Option<HashMap<String, String>> {
+        if self.segments.len() != path_segments.len() {
+            return None;
+        }
+
+        let mut params = HashMap::new();
+        for (segment, value) in self.segments.iter().zip(path_segments) {
+            match segment {
+                Segment::Literal(literal) if literal == value => {}
+                Segment::Literal(_) => return None,
+                Segment::Param(name) => {
+                    if value.is_empty() {
+                        return None;
+                    }
+                    params.insert(name.clone(), value.clone());
+                }
+            }
+        }
+
+        Some(params)
+    }
+
+    /// Number of literal segments, used to prefer `/courses/mine` over `/courses/{id}`
+    fn specificity(&self) -> usize {
+        self.segments
+            .iter()
+            .filter(|segment| matches!(segment, Segment::Literal(_)))
+            .count()
+    }
+}
+
+/// Declarative router mapping method + path templates to Lambda handlers.
+///
+/// Templates use `{name}` placeholders, e.g. `/courses/{id}/sections/{section_id}`.
+/// Matched parameters are written into `path_parameters` before the handler runs,
+/// so handlers behave the same behind a catch-all proxy route or when run locally.
+/// Every request is recorded in the router's metric sink, see [`metrics::record_request`].
+pub struct Router {
+    routes: Vec<Route>,
+    sink: Arc<dyn MetricSink>,
+}
+
+impl Default for Router {
+    fn default() -> Self {
+        Self {
+            routes: Vec::new(),
+            sink: Arc::new(StdoutSink),
+        }
+    }
+}
+
+impl Router {
+    /// Create an empty router
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send metrics to `sink` instead of standard output
+    pub fn metrics(mut self, sink: Arc<dyn MetricSink>) -> Self {
+        self.sink = sink;
+        self
+    }
+
+    /// Register a handler for a method and path template
+    pub fn route<F, Fut>(mut self, method: Method, template: &str, auth: AuthRequirement, handler: F) -> Self
+    where
+        F: Fn(LambdaEvent<ApiGatewayProxyRequest>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<ApiGatewayProxyResponse, Error>> + Send + 'static,
+    {
+        let segments = split_path(template)
+            .into_iter()
+            .map(|segment| match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                Some(name) => Segment::Param(name.to_string()),
+                None => Segment::Literal(segment),
+            })
+            .collect();
+
+        self.routes.push(Route {
+            method,
+            template: template.to_string(),
+            segments,
+            auth,
+            scope: None,
+            handler: Box::new(move |event| Box::pin(handler(event))),
+        });
+
+        self
+    }
+
+    /// Let personal access tokens with `scope` call the route registered last
+    pub fn scoped(mut self, scope: TokenScope) -> Self {
+        if let Some(route) = self.routes.last_mut() {
+            route.scope = Some(scope);
+        }
+
+        self
+    }
+
+    /// Dispatch a request to the matching route, add the common response headers and record
+    /// its metrics
+    pub async fn dispatch(&self, event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
+        let started = Instant::now();
+        let method = event.payload.http_method.clone();
+        let origin = event.payload.headers.get("Origin").cloned();
+
+        metrics::scope(self.sink.clone(), async {
+            let (template, mut result) = self.route_request(event).await;
+            if let Ok(response) = &mut result {
+                response::apply_headers(origin.as_ref(), response);
+            }
+
+            let status_code = result.as_ref().map_or(500, |response| response.status_code);
+            metrics::record_request(template.unwrap_or(metrics::UNMATCHED_ROUTE), method.as_str(), status_code, started.elapsed());
+            result
+        })
+        .await
+    }
+
+    /// Run the matching route's handler, returning the route template alongside the response
+    async fn route_request(
+        &self,
+        mut event: LambdaEvent<ApiGatewayProxyRequest>,
+    ) -> (Option<&str>, Result<ApiGatewayProxyResponse, Error>) {
+        let method = event.payload.http_method.clone();
+        let path = event.payload.path.clone().unwrap_or_default();
+        let path_segments: Vec<String> = split_path(&path).iter().map(|s| percent_decode(s)).collect();
+
+        // Collect every route whose template matches the path, regardless of method
+        let mut candidates: Vec<(&Route, HashMap<String, String>)> = self
+            .routes
+            .iter()
+            .filter_map(|route| route.matches(&path_segments).map(|params| (route, params)))
+            .collect();
+
+        if candidates.is_empty() {
+            info!("No handler found for route: {} {}", method, path);
+            return (None, Ok(AppError::NotFound(
+                ErrorCode::RouteNotFound,
+                format!("No handler found for route: {} {}", method, path),
+            )
+            .into()));
+        }
+
+        candidates.sort_by_key(|(route, _)| std::cmp::Reverse(route.specificity()));
+
+        let mut allowed: Vec<&str> = candidates.iter().map(|(route, _)| route.method.as_str()).collect();
+        allowed.push(Method::OPTIONS.as_str());
+        allowed.sort_unstable();
+        allowed.dedup();
+        let allow = allowed.join(", ");
+        let most_specific = candidates.first().map(|(route, _)| route.template.as_str());
+
+        // Answer OPTIONS, including CORS preflights, without authentication
+        if method == Method::OPTIONS && !candidates.iter().any(|(route, _)| route.method == Method::OPTIONS) {
+            return (most_specific, Ok(response::options(&allow)));
+        }
+
+        let Some((route, params)) = candidates.iter().find(|(route, _)| route.method == method) else {
+            info!("Method {} not allowed for {}, allowed: {}", method, path, allow);
+
+            let mut response: ApiGatewayProxyResponse =
+                AppError::MethodNotAllowed(format!("{} is not supported for {}", method, path)).into();
+            if let Ok(value) = HeaderValue::from_str(&allow) {
+                response.headers.insert("Allow", value);
+            }
+            return (most_specific, Ok(response));
+        };
+
+        logger::record_route(&route.template);
+
+        // Authenticate the caller and enforce the route's requirement before running the handler
+        if let Err(err) = authenticate(&mut event.payload, route.auth, route.scope).await {
+            return (Some(&route.template), Ok(err.into()));
+        }
+
+        info!("Routing {} {} to {}", method, path, route.template);
+
+        event.payload.path_parameters.extend(params.clone());
+        if event.payload.resource.is_none() {
+            event.payload.resource = Some(route.template.clone());
+        }
+
+        (Some(&route.template), (route.handler)(event).await)
+    }
+}
+
+/// Validate the bearer token, attach the caller to the request and check the route's requirement.
+///
+/// Public routes treat an invalid, expired or revoked token, or a personal access token
+/// without the route's scope, as an anonymous request, so a stale token never locks a
+/// client out of endpoints such as login or refresh.
+async fn authenticate(
+    request: &mut ApiGatewayProxyRequest,
+    requirement: AuthRequirement,
+    scope: Option<TokenScope>,
+) -> Result<(), AppError> {
+    // A caller attached upstream is never trusted; only a token validated here counts
+    AuthContext::detach(request);
+
+    if request.headers.contains_key("Authorization") {
+        let result = match repository::connect().await {
+            Ok(repo) => AuthContext::authenticate(&repo, request).await,
+            Err(err) => Err(err),
+        }
+        .and_then(|auth| match auth {
+            Some(auth) => auth.require_scope(scope).map(|()| Some(auth)),
+            None => Ok(None),
+        });
+
+        match result {
+            Ok(Some(auth)) => {
+                logger::record_user(&auth.user.to_string());
+                auth.attach(request)?
+            }
+            Ok(None) => {}
+            Err(err) if requirement == AuthRequirement::Public => {
+                info!("Treating request as anonymous: {}", err);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    match requirement {
+        AuthRequirement::Public => Ok(()),
+        AuthRequirement::Authenticated => AuthContext::from_request(request).map(|_| ()),
+        AuthRequirement::Permitted(action) => policy::authorize(&AuthContext::from_request(request)?, action, None),
+    }
+}
+
+/// Extract a typed path parameter populated by the router
+pub fn path_param<T>(request: &ApiGatewayProxyRequest, name: &str) -> Result<T, AppError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let value = request
+        .path_parameters
+        .get(name)
+        .ok_or_else(|| AppError::Validation(ErrorCode::InvalidPathParameter, format!("Missing path parameter: {}", name)))?;
+
+    value
+        .parse::<T>()
+        .map_err(|err| AppError::Validation(ErrorCode::InvalidPathParameter, format!("Invalid path parameter {}: {}", name, err)))
+}
+
+/// Split a path into its non-empty segments, ignoring leading and trailing slashes
+fn split_path(path: &str) -> Vec<String> {
+    path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Decode `%XX` escapes in a path segment, leaving malformed escapes untouched
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && i + 2 < bytes.len()
+            && let (Some(high), Some(low)) = (hex_digit(bytes[i + 1]), hex_digit(bytes[i + 2]))
+        {
+            decoded.push(high << 4 | low);
+            i += 3;
+            continue;
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8(decoded).unwrap_or_else(|_| segment.to_string())
+}
+
+/// Value of a single hex digit; unlike `u8::from_str_radix`, signs are not accepted
+fn hex_digit(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|digit| digit as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use aws_lambda_events::encodings::Body;
+    use chrono::Utc;
+    use lambda_runtime::Context;
+    use serde_json::{json, Value};
+    use surrealdb::sql::Thing;
+
+    use super::*;
+    use crate::common::extract::TokenMetadata;
+    use crate::common::metrics::MemorySink;
+    use crate::models::user::UserRole;
+
+    /// Handler answering with the route it was registered for and the parameters it received
+    fn echo(name: &'static str) -> impl Fn(LambdaEvent<ApiGatewayProxyRequest>) -> HandlerFuture + Send + Sync {
+        move |event| {
+            Box::pin(async move {
+                let body = json!({ "route": name, "params": event.payload.path_parameters });
+                Ok(response::json_response(200, body))
+            })
+        }
+    }
+
+    fn router() -> Router {
+        Router::new()
+            .metrics(Arc::new(MemorySink::new()))
+            .route(Method::GET, "/courses/{id}", AuthRequirement::Public, echo("get"))
+            .route(Method::PUT, "/courses/{id}", AuthRequirement::Public, echo("update"))
+            .route(Method::GET, "/courses/mine", AuthRequirement::Public, echo("mine"))
+            .route(Method::GET, "/courses/{id}/sections/{section_id}", AuthRequirement::Public, echo("section"))
+    }
+
+    async fn send(method: Method, path: &str) -> ApiGatewayProxyResponse {
+        let request = ApiGatewayProxyRequest {
+            http_method: method,
+            path: Some(path.to_string()),
+            ..Default::default()
+        };
+
+        router().dispatch(LambdaEvent::new(request, Context::default())).await.unwrap()
+    }
+
+    fn body(response: &ApiGatewayProxyResponse) -> Value {
+        match &response.body {
+            Some(Body::Text(text)) => serde_json::from_str(text).unwrap(),
+            other => panic!("unexpected body: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn parameters_are_extracted_from_the_path() {
+        let response = send(Method::GET, "/courses/abc/sections/s%201/").await;
+
+        assert_eq!(response.status_code, 200);
+        assert_eq!(body(&response)["route"], "section");
+        assert_eq!(body(&response)["params"], json!({ "id": "abc", "section_id": "s 1" }));
+    }
+
+    #[tokio::test]
+    async fn literal_segments_win_over_parameters() {
+        let response = send(Method::GET, "/courses/mine").await;
+        assert_eq!(body(&response)["route"], "mine");
+
+        let response = send(Method::GET, "/courses/theirs").await;
+        assert_eq!(body(&response)["route"], "get");
+        assert_eq!(body(&response)["params"], json!({ "id": "theirs" }));
+    }
+
+    #[tokio::test]
+    async fn unknown_paths_are_not_found() {
+        let response = send(Method::GET, "/lessons/abc").await;
+
+        assert_eq!(response.status_code, 404);
+        assert_eq!(body(&response)["error"]["code"], "ROUTE_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn other_methods_are_not_allowed() {
+        let response = send(Method::DELETE, "/courses/abc").await;
+
+        assert_eq!(response.status_code, 405);
+        assert_eq!(body(&response)["error"]["code"], "METHOD_NOT_ALLOWED");
+        assert_eq!(response.headers["Allow"], "GET, OPTIONS, PUT");
+    }
+
+    #[test]
+    fn escapes_are_decoded() {
+        assert_eq!(percent_decode("caf%C3%A9%20au%20lait"), "café au lait");
+        assert_eq!(percent_decode("%2f%2F"), "//");
+    }
+
+    #[test]
+    fn malformed_escapes_are_kept() {
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%4"), "%4");
+        assert_eq!(percent_decode("%zz"), "%zz");
+        assert_eq!(percent_decode("%+1"), "%+1");
+        assert_eq!(percent_decode("%-1x"), "%-1x");
+        // Escapes that do not form valid UTF-8 leave the segment as sent
+        assert_eq!(percent_decode("%FFabc"), "%FFabc");
+    }
+
+    #[tokio::test]
+    async fn only_the_router_attaches_the_caller() {
+        let forged = AuthContext {
+            user: Thing::from(("user", "admin")),
+            role: UserRole::Admin,
+            token: TokenMetadata {
+                id: "forged".to_string(),
+                issued_at: Utc::now(),
+                expires_at: Utc::now(),
+                session: None,
+            },
+            scopes: None,
+        };
+        let mut request = ApiGatewayProxyRequest::default();
+        forged.attach(&mut request).unwrap();
+
+        let result = authenticate(&mut request, AuthRequirement::Authenticated, None).await;
+        assert!(matches!(result, Err(AppError::Authentication(ErrorCode::AuthenticationRequired, _))));
+        assert!(AuthContext::optional(&request).unwrap().is_none());
+    }
+}
@@ -0,0 +1,318 @@
+//! Distributed tracing exported over OTLP.
+//!
+//! [`layer`] hands `tracing` spans to the OpenTelemetry SDK, whose batch processor exports
+//! them to `OTEL_EXPORTER_OTLP_ENDPOINT` as OTLP/HTTP JSON. The Lambda function is frozen
+//! between invocations, so `function_handler` calls [`flush`] at the end of every request
+//! instead of waiting for the next scheduled export.
+//!
+//! The request span continues the caller's trace when the request carries a W3C
+//! `traceparent` header or an X-Ray trace header. Spans use OpenTelemetry attribute names,
+//! plus `otel.kind` for the span kind and `otel.status_code = "ERROR"` for failures.
+
+use std::time::Duration;
+
+use aws_lambda_events::event::apigw::ApiGatewayProxyRequest;
+use lambda_runtime::Context as LambdaContext;
+use once_cell::sync::OnceCell;
+use opentelemetry::trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState, TracerProvider};
+use opentelemetry_otlp::{Protocol, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::trace::{SdkTracerProvider, Tracer};
+use opentelemetry_sdk::Resource;
+use tracing::{warn, Span, Subscriber};
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::common::config::Config;
+
+/// How long an export may hold up the response before it is abandoned
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Position of a span in a trace, as carried between services
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceContext {
+    pub trace_id: [u8; 16],
+    /// The caller's span. X-Ray headers may name only the trace.
+    pub span_id: Option<[u8; 8]>,
+    pub sampled: bool,
+}
+
+impl TraceContext {
+    /// The caller's trace context, from a `traceparent` header, an `X-Amzn-Trace-Id` header,
+    /// or the X-Ray trace ID Lambda passes when active tracing is enabled
+    pub fn from_request(request: &ApiGatewayProxyRequest, context: &LambdaContext) -> Option<Self> {
+        let header = |name: &str| request.headers.get(name).and_then(|value| value.to_str().ok());
+
+        header("traceparent")
+            .and_then(Self::from_traceparent)
+            .or_else(|| header("X-Amzn-Trace-Id").and_then(Self::from_xray))
+            .or_else(|| context.xray_trace_id.as_deref().and_then(Self::from_xray))
+    }
+
+    /// Parse a W3C `traceparent` value, such as `00-<trace id>-<span id>-01`
+    pub fn from_traceparent(value: &str) -> Option<Self> {
+        let mut parts = value.trim().split('-');
+        let (version, trace_id, span_id, flags) = (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+        if version.len() != 2 || version == "ff" || (version == "00" && parts.next().is_some()) {
+            return None;
+        }
+
+        let trace_id = decode::<16>(trace_id).filter(|id| id != &[0; 16])?;
+        let span_id = decode::<8>(span_id)?;
+        let flags = decode::<1>(flags)?;
+
+        Some(Self {
+            trace_id,
+            span_id: Some(span_id).filter(|id| id != &[0; 8]),
+            sampled: flags[0] & 1 == 1,
+        })
+    }
+
+    /// Parse an X-Ray trace header, such as `Root=1-5759e988-bd862e3fe1be46a994272793;Parent=53995c3f42cd8ad8;Sampled=1`
+    pub fn from_xray(value: &str) -> Option<Self> {
+        let mut root = None;
+        let mut parent = None;
+        let mut sampled = true;
+
+        for field in value.split(';') {
+            match field.trim().split_once('=') {
+                Some(("Root", value)) => root = Some(value),
+                Some(("Parent", value)) => parent = decode::<8>(value),
+                Some(("Sampled", value)) => sampled = value != "0",
+                _ => {}
+            }
+        }
+
+        // X-Ray trace IDs are the OpenTelemetry ID split after the 8-digit timestamp
+        let (time, random) = root?.strip_prefix("1-")?.split_once('-')?;
+        if time.len() != 8 {
+            return None;
+        }
+        let trace_id = decode::<16>(&format!("{}{}", time, random))?;
+
+        Some(Self { trace_id, span_id: parent, sampled })
+    }
+
+    /// The caller's span as a remote OpenTelemetry parent. Without a span ID the trace is
+    /// still continued, with the request span as its local root.
+    pub fn to_context(self) -> opentelemetry::Context {
+        let flags = if self.sampled { TraceFlags::SAMPLED } else { TraceFlags::default() };
+        let parent = SpanContext::new(
+            TraceId::from_bytes(self.trace_id),
+            self.span_id.map_or(SpanId::INVALID, SpanId::from_bytes),
+            flags,
+            true,
+            TraceState::default(),
+        );
+
+        opentelemetry::Context::new().with_remote_span_context(parent)
+    }
+}
+
+fn decode<const N: usize>(value: &str) -> Option<[u8; N]> {
+    let mut bytes = [0; N];
+    hex::decode_to_slice(value, &mut bytes).ok()?;
+    Some(bytes)
+}
+
+/// Make `span` a child of the caller's span. Must be called before the span is entered,
+/// and does nothing when export is disabled.
+pub fn continue_trace(span: &Span, parent: TraceContext) {
+    let _ = span.set_parent(parent.to_context());
+}
+
+/// Mark a span as failed. The span must declare an empty `otel.status_code` field.
+pub fn record_error(span: &Span) {
+    span.record("otel.status_code", "ERROR");
+}
+
+/// The installed exporter, kept for [`flush`]
+static PROVIDER: OnceCell<SdkTracerProvider> = OnceCell::new();
+
+/// The layer to install, if `OTEL_EXPORTER_OTLP_ENDPOINT` is set
+pub fn layer<S>(config: &Config) -> Option<OpenTelemetryLayer<S, Tracer>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    if config.otel_exporter_otlp_endpoint.is_empty() {
+        return None;
+    }
+
+    let url = format!("{}/v1/traces", config.otel_exporter_otlp_endpoint.trim_end_matches('/'));
+    let exporter = match SpanExporter::builder()
+        .with_http()
+        .with_protocol(Protocol::HttpJson)
+        .with_endpoint(&url)
+        .with_timeout(EXPORT_TIMEOUT)
+        .build()
+    {
+        Ok(exporter) => exporter,
+        Err(err) => {
+            // The subscriber is not installed yet, so this cannot be logged
+            eprintln!("Failed to set up trace export to {}: {}", url, err);
+            return None;
+        }
+    };
+
+    let provider = PROVIDER.get_or_init(|| {
+        SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(Resource::builder().with_service_name(config.otel_service_name.clone()).build())
+            .build()
+    });
+
+    Some(tracing_opentelemetry::layer().with_tracer(provider.tracer(env!("CARGO_PKG_NAME"))))
+}
+
+/// Export every finished span. Failures are logged and the spans dropped, so a collector
+/// outage never fails a request.
+pub async fn flush() {
+    let Some(provider) = PROVIDER.get().cloned() else {
+        return;
+    };
+
+    // Flushing waits for the batch processor's export thread, which would stall the runtime
+    match tokio::task::spawn_blocking(move || provider.force_flush()).await {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => warn!("Failed to export spans: {}", err),
+        Err(err) => warn!("Failed to export spans: {}", err),
+    }
+}
+
+/// Span around an outbound AWS SDK call, such as `aws_span("SQS", "SendMessage")`
+pub fn aws_span(service: &'static str, operation: &'static str) -> Span {
+    tracing::info_span!(
+        "aws_sdk",
+        otel.kind = "client",
+        rpc.system = "aws-api",
+        rpc.service = service,
+        rpc.method = operation,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::trace::{SpanKind, Status};
+    use opentelemetry::Value;
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SpanData};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+
+    #[test]
+    fn traceparent_headers_are_parsed() {
+        let parent = TraceContext::from_traceparent(&format!("00-{}-00f067aa0ba902b7-01", TRACE_ID)).unwrap();
+        assert_eq!(hex::encode(parent.trace_id), TRACE_ID);
+        assert_eq!(parent.span_id.map(hex::encode).as_deref(), Some("00f067aa0ba902b7"));
+        assert!(parent.sampled);
+
+        let unsampled = TraceContext::from_traceparent(&format!("00-{}-00f067aa0ba902b7-00", TRACE_ID)).unwrap();
+        assert!(!unsampled.sampled);
+
+        assert!(TraceContext::from_traceparent("00-00000000000000000000000000000000-00f067aa0ba902b7-01").is_none());
+        assert!(TraceContext::from_traceparent(&format!("ff-{}-00f067aa0ba902b7-01", TRACE_ID)).is_none());
+        assert!(TraceContext::from_traceparent("garbage").is_none());
+    }
+
+    #[test]
+    fn xray_headers_are_parsed() {
+        let parent = TraceContext::from_xray("Root=1-5759e988-bd862e3fe1be46a994272793;Parent=53995c3f42cd8ad8;Sampled=1").unwrap();
+        assert_eq!(hex::encode(parent.trace_id), "5759e988bd862e3fe1be46a994272793");
+        assert_eq!(parent.span_id.map(hex::encode).as_deref(), Some("53995c3f42cd8ad8"));
+        assert!(parent.sampled);
+
+        // API Gateway may pass only the root
+        let root_only = TraceContext::from_xray("Root=1-5759e988-bd862e3fe1be46a994272793").unwrap();
+        assert_eq!(root_only.span_id, None);
+
+        assert!(!TraceContext::from_xray("Root=1-5759e988-bd862e3fe1be46a994272793;Sampled=0").unwrap().sampled);
+        assert!(TraceContext::from_xray("Parent=53995c3f42cd8ad8").is_none());
+    }
+
+    #[test]
+    fn traceparent_takes_precedence_over_xray() {
+        let mut request = ApiGatewayProxyRequest::default();
+        request.headers.insert("X-Amzn-Trace-Id", "Root=1-5759e988-bd862e3fe1be46a994272793".parse().unwrap());
+        let context = LambdaContext::default();
+        assert_eq!(
+            hex::encode(TraceContext::from_request(&request, &context).unwrap().trace_id),
+            "5759e988bd862e3fe1be46a994272793"
+        );
+
+        request.headers.insert("traceparent", format!("00-{}-00f067aa0ba902b7-01", TRACE_ID).parse().unwrap());
+        assert_eq!(hex::encode(TraceContext::from_request(&request, &context).unwrap().trace_id), TRACE_ID);
+    }
+
+    /// Run `f` with spans exported to memory, returning them in the order they closed
+    fn record_spans(f: impl FnOnce()) -> Vec<SpanData> {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder().with_simple_exporter(exporter.clone()).build();
+        let subscriber = tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+
+        tracing::subscriber::with_default(subscriber, f);
+        exporter.get_finished_spans().unwrap()
+    }
+
+    fn attribute<'a>(span: &'a SpanData, key: &str) -> Option<&'a Value> {
+        span.attributes
+            .iter()
+            .find(|attribute| attribute.key.as_str() == key)
+            .map(|attribute| &attribute.value)
+    }
+
+    #[test]
+    fn spans_join_the_remote_trace() {
+        let spans = record_spans(|| {
+            let parent = TraceContext::from_traceparent(&format!("00-{}-00f067aa0ba902b7-01", TRACE_ID)).unwrap();
+            let request = tracing::info_span!("request", otel.kind = "server", route = tracing::field::Empty);
+            continue_trace(&request, parent);
+            let _entered = request.enter();
+            request.record("route", "/courses");
+
+            let query = tracing::info_span!("db.query", otel.kind = "client", db.system = "surrealdb", otel.status_code = tracing::field::Empty);
+            record_error(&query);
+            drop(query);
+        });
+
+        assert_eq!(spans.len(), 2);
+        let (query, request) = (&spans[0], &spans[1]);
+
+        assert_eq!(request.span_context.trace_id().to_string(), TRACE_ID);
+        assert_eq!(request.parent_span_id.to_string(), "00f067aa0ba902b7");
+        assert_eq!(request.span_kind, SpanKind::Server);
+        assert_eq!(attribute(request, "route"), Some(&Value::from("/courses")));
+
+        assert_eq!(query.span_context.trace_id().to_string(), TRACE_ID);
+        assert_eq!(query.parent_span_id, request.span_context.span_id());
+        assert_eq!(query.span_kind, SpanKind::Client);
+        assert!(matches!(query.status, Status::Error { .. }));
+        assert_eq!(attribute(query, "db.system"), Some(&Value::from("surrealdb")));
+    }
+
+    #[test]
+    fn xray_roots_without_a_parent_continue_the_trace() {
+        let spans = record_spans(|| {
+            let request = tracing::info_span!("request");
+            continue_trace(&request, TraceContext::from_xray("Root=1-5759e988-bd862e3fe1be46a994272793").unwrap());
+            drop(request);
+        });
+
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].span_context.trace_id().to_string(), "5759e988bd862e3fe1be46a994272793");
+        assert_eq!(spans[0].parent_span_id, SpanId::INVALID);
+    }
+
+    #[test]
+    fn unsampled_traces_are_not_exported() {
+        let spans = record_spans(|| {
+            let parent = TraceContext::from_traceparent(&format!("00-{}-00f067aa0ba902b7-00", TRACE_ID)).unwrap();
+            let request = tracing::info_span!("request");
+            continue_trace(&request, parent);
+            let _entered = request.enter();
+            drop(tracing::info_span!("db.query"));
+        });
+
+        assert!(spans.is_empty());
+    }
+}
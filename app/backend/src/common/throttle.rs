@@ -0,0 +1,211 @@
+//! Login throttling.
+//!
+//! Failed logins are counted per account (by email, whether or not the account exists) and
+//! per source IP in the `login_throttle` table, so the limits hold across Lambda instances.
+//! From `login_backoff_after` failures an account must wait `login_backoff_base_secs`
+//! between attempts, doubling with each further failure. At `login_lockout_after` failures
+//! for an account, or `login_ip_lockout_after` from one IP, logins are locked for
+//! `login_lockout_minutes`. Failures older than `login_failure_window_minutes` are
+//! forgotten, and a successful login clears the account's counter.
+//!
+//! Lockouts and their expiry are written to the security event log.
+
+use chrono::{DateTime, Duration, Utc};
+use tracing::warn;
+
+use crate::common::config::{Config, CONFIG};
+use crate::common::error::AppError;
+use crate::models::security::{SecurityEvent, SecurityEventKind};
+use crate::repository::SecurityRepository;
+
+/// What a run of failures costs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Penalty {
+    /// Refuse logins for a short while
+    Delay(Duration),
+    /// Refuse logins for the lockout period and log it
+    Lockout(Duration),
+}
+
+/// Thresholds for one kind of throttle key
+#[derive(Debug, Clone, Copy)]
+pub struct ThrottlePolicy {
+    backoff_after: u32,
+    lockout_after: u32,
+    base: Duration,
+    lockout: Duration,
+}
+
+impl ThrottlePolicy {
+    /// Backoff and then lockout for a single account
+    pub fn account(config: &Config) -> Self {
+        Self {
+            backoff_after: config.login_backoff_after,
+            lockout_after: config.login_lockout_after,
+            base: Duration::seconds(config.login_backoff_base_secs as i64),
+            lockout: Duration::minutes(config.login_lockout_minutes as i64),
+        }
+    }
+
+    /// Lockout only, for a source IP that may be shared by many users
+    pub fn ip(config: &Config) -> Self {
+        Self {
+            backoff_after: config.login_ip_lockout_after,
+            lockout_after: config.login_ip_lockout_after,
+            base: Duration::seconds(config.login_backoff_base_secs as i64),
+            lockout: Duration::minutes(config.login_lockout_minutes as i64),
+        }
+    }
+
+    /// The penalty after `failures` recent failed logins, if any
+    pub fn penalty(&self, failures: u32) -> Option<Penalty> {
+        if failures >= self.lockout_after {
+            return Some(Penalty::Lockout(self.lockout));
+        }
+        if failures < self.backoff_after {
+            return None;
+        }
+
+        let doublings = (failures - self.backoff_after).min(30);
+        let delay = self.base.checked_mul(1 << doublings).unwrap_or(self.lockout);
+        Some(Penalty::Delay(delay.min(self.lockout)))
+    }
+}
+
+/// The throttle keys a login attempt counts against
+#[derive(Debug, Clone)]
+pub struct LoginAttempt {
+    account: String,
+    ip: Option<String>,
+}
+
+impl LoginAttempt {
+    /// Describe an attempt to log in as `email` from `ip`
+    pub fn new(email: &str, ip: Option<&str>) -> Self {
+        Self {
+            account: account_key(email),
+            ip: ip.filter(|ip| !ip.is_empty()).map(str::to_string),
+        }
+    }
+
+    /// Every key with the policy that applies to it
+    fn keys(&self) -> Vec<(String, ThrottlePolicy)> {
+        let mut keys = vec![(self.account.clone(), ThrottlePolicy::account(&CONFIG))];
+        if let Some(ip) = &self.ip {
+            keys.push((ip_key(ip), ThrottlePolicy::ip(&CONFIG)));
+        }
+        keys
+    }
+}
+
+/// Throttle key for an account
+pub fn account_key(email: &str) -> String {
+    format!("account:{}", email.trim().to_lowercase())
+}
+
+/// Throttle key for a source IP
+pub fn ip_key(ip: &str) -> String {
+    format!("ip:{}", ip.trim())
+}
+
+/// Seconds until the attempt may be made, or `None` if it is allowed now
+pub async fn retry_after(repo: &impl SecurityRepository, attempt: &LoginAttempt, now: DateTime<Utc>) -> Result<Option<u64>, AppError> {
+    let mut wait = None;
+
+    for (key, _) in attempt.keys() {
+        let Some(throttle) = repo.find_login_throttle(&key).await? else {
+            continue;
+        };
+
+        if throttle.blocked_until > now {
+            let secs = (throttle.blocked_until - now).num_seconds().max(1) as u64;
+            wait = wait.max(Some(secs));
+        } else if throttle.locked && repo.release_expired_lock(&key, now).await? {
+            let event = SecurityEvent::new(SecurityEventKind::LoginUnlock, &key, "Lockout expired".to_string());
+            repo.record_security_event(event).await?;
+        }
+    }
+
+    Ok(wait)
+}
+
+/// Count a failed login and apply any penalty it earns
+pub async fn record_failure(repo: &impl SecurityRepository, attempt: &LoginAttempt, now: DateTime<Utc>) -> Result<(), AppError> {
+    let since = now - Duration::minutes(CONFIG.login_failure_window_minutes as i64);
+
+    for (key, policy) in attempt.keys() {
+        let throttle = repo.record_login_failure(&key, now, since).await?;
+
+        match policy.penalty(throttle.failures) {
+            Some(Penalty::Delay(delay)) => repo.delay_login(&key, now + delay).await?,
+            Some(Penalty::Lockout(lockout)) if repo.lock_login(&key, now + lockout).await? => {
+                warn!("Locked logins for {} after {} failures", key, throttle.failures);
+
+                let mut event = SecurityEvent::new(
+                    SecurityEventKind::LoginLockout,
+                    &key,
+                    format!(
+                        "Locked for {} minutes after {} failed logins",
+                        lockout.num_minutes(),
+                        throttle.failures
+                    ),
+                );
+                event.ip = attempt.ip.clone();
+                repo.record_security_event(event).await?;
+            }
+            // Already locked by a concurrent failure, which logged the lockout
+            Some(Penalty::Lockout(_)) | None => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Forget the account's failed logins after a successful one.
+///
+/// The IP counter is kept, so logging into one account does not reset guessing at others.
+pub async fn record_success(repo: &impl SecurityRepository, attempt: &LoginAttempt) -> Result<(), AppError> {
+    repo.clear_login_failures(&attempt.account).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn account_delays_double_until_lockout() {
+        let policy = ThrottlePolicy::account(&Config::default());
+        let delays: Vec<_> = (1..=10).map(|failures| policy.penalty(failures)).collect();
+
+        assert_eq!(delays[..2], [None, None]);
+        assert_eq!(delays[2], Some(Penalty::Delay(Duration::seconds(1))));
+        assert_eq!(delays[3], Some(Penalty::Delay(Duration::seconds(2))));
+        assert_eq!(delays[8], Some(Penalty::Delay(Duration::seconds(64))));
+        assert_eq!(delays[9], Some(Penalty::Lockout(Duration::minutes(15))));
+    }
+
+    #[test]
+    fn delays_never_exceed_the_lockout() {
+        let policy = ThrottlePolicy::account(&Config {
+            login_backoff_base_secs: 60,
+            login_lockout_after: 1000,
+            ..Config::default()
+        });
+
+        assert_eq!(policy.penalty(999), Some(Penalty::Delay(Duration::minutes(15))));
+    }
+
+    #[test]
+    fn ips_are_only_locked_out() {
+        let policy = ThrottlePolicy::ip(&Config::default());
+
+        assert_eq!(policy.penalty(49), None);
+        assert_eq!(policy.penalty(50), Some(Penalty::Lockout(Duration::minutes(15))));
+    }
+
+    #[test]
+    fn account_keys_ignore_case_and_whitespace() {
+        assert_eq!(account_key(" Ada@Example.com "), "account:ada@example.com");
+    }
+}
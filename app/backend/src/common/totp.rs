@@ -0,0 +1,189 @@
+//! Time-based one-time passwords (RFC 6238).
+//!
+//! Codes are six digits from HMAC-SHA1 over 30 second steps, which is what every common
+//! authenticator app expects from an `otpauth://` URI. A code from the step before or
+//! after the current one is accepted to allow for clock drift.
+
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha1::Sha1;
+
+use crate::common::auth;
+
+/// Seconds each code is valid for
+pub const STEP_SECS: u64 = 30;
+
+/// Digits in each code
+pub const DIGITS: u32 = 6;
+
+/// Steps either side of the current one whose codes are still accepted
+const DRIFT_STEPS: u64 = 1;
+
+/// Bytes of shared secret, the HMAC-SHA1 output size recommended by RFC 4226
+const SECRET_BYTES: usize = 20;
+
+/// Recovery codes issued at enrollment
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Generate a new shared secret, base32 encoded for authenticator apps
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; SECRET_BYTES];
+    rand::rng().fill_bytes(&mut bytes);
+    BASE32_NOPAD.encode(&bytes)
+}
+
+/// The `otpauth://` URI that authenticator apps scan from a QR code
+pub fn provisioning_uri(secret: &str, account: &str, issuer: &str) -> String {
+    let label = urlencoding::encode(&format!("{}:{}", issuer, account)).into_owned();
+
+    format!(
+        "otpauth://totp/{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        label,
+        secret,
+        urlencoding::encode(issuer),
+        DIGITS,
+        STEP_SECS
+    )
+}
+
+/// The time step a Unix timestamp falls in
+pub fn step_at(unix_secs: u64) -> u64 {
+    unix_secs / STEP_SECS
+}
+
+/// The code for a base32 secret at a time step, or `None` if the secret is not valid base32
+pub fn code_at(secret: &str, step: u64) -> Option<String> {
+    let key = BASE32_NOPAD.decode(secret.trim_end_matches('=').as_bytes()).ok()?;
+    Some(hotp(&key, step))
+}
+
+/// Check a code against the secret at `unix_secs`.
+///
+/// Returns the step the code belongs to, so the caller can refuse it once that step has
+/// been used. Steps at or before `last_used_step` never match.
+pub fn verify(secret: &str, code: &str, unix_secs: u64, last_used_step: u64) -> Option<u64> {
+    let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+    if code.len() != DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let key = BASE32_NOPAD.decode(secret.trim_end_matches('=').as_bytes()).ok()?;
+    let current = step_at(unix_secs);
+
+    (current.saturating_sub(DRIFT_STEPS)..=current + DRIFT_STEPS)
+        .filter(|step| *step > last_used_step)
+        .find(|step| constant_time_eq(hotp(&key, *step).as_bytes(), code.as_bytes()))
+}
+
+/// Generate a set of single-use recovery codes, such as `3f9a1-c07be`
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut bytes = [0u8; 5];
+            rand::rng().fill_bytes(&mut bytes);
+            let hex = hex::encode(bytes);
+            format!("{}-{}", &hex[..5], &hex[5..])
+        })
+        .collect()
+}
+
+/// Hash a recovery code for storage, ignoring case, spaces and dashes
+pub fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+
+    auth::hash_opaque_token(&normalized)
+}
+
+/// HOTP value (RFC 4226) for a counter
+fn hotp(key: &[u8], counter: u64) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    // Dynamic truncation
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([digest[offset], digest[offset + 1], digest[offset + 2], digest[offset + 3]]) & 0x7fff_ffff;
+
+    format!("{:0width$}", binary % 10u32.pow(DIGITS), width = DIGITS as usize)
+}
+
+/// Compare two byte strings without stopping at the first difference
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The SHA1 secret from RFC 6238 appendix B
+    fn rfc_secret() -> String {
+        BASE32_NOPAD.encode(b"12345678901234567890")
+    }
+
+    #[test]
+    fn matches_rfc_6238_test_vectors() {
+        let secret = rfc_secret();
+
+        // The RFC lists eight digits; six-digit codes are their last six
+        assert_eq!(code_at(&secret, step_at(59)).unwrap(), "287082");
+        assert_eq!(code_at(&secret, step_at(1111111109)).unwrap(), "081804");
+        assert_eq!(code_at(&secret, step_at(1234567890)).unwrap(), "005924");
+        assert_eq!(code_at(&secret, step_at(20000000000)).unwrap(), "353130");
+    }
+
+    #[test]
+    fn accepts_one_step_of_drift() {
+        let secret = rfc_secret();
+        let now = 1111111109;
+        let previous = code_at(&secret, step_at(now) - 1).unwrap();
+        let stale = code_at(&secret, step_at(now) - 2).unwrap();
+
+        assert_eq!(verify(&secret, &previous, now, 0), Some(step_at(now) - 1));
+        assert_eq!(verify(&secret, &stale, now, 0), None);
+    }
+
+    #[test]
+    fn used_steps_are_refused() {
+        let secret = rfc_secret();
+        let now = 1111111109;
+        let code = code_at(&secret, step_at(now)).unwrap();
+
+        assert_eq!(verify(&secret, &code, now, step_at(now) - 1), Some(step_at(now)));
+        assert_eq!(verify(&secret, &code, now, step_at(now)), None);
+    }
+
+    #[test]
+    fn malformed_codes_and_secrets_are_refused() {
+        assert_eq!(verify(&rfc_secret(), "28708", 59, 0), None);
+        assert_eq!(verify(&rfc_secret(), "28708a", 59, 0), None);
+        assert_eq!(verify("not base32!", "287082", 59, 0), None);
+        assert_eq!(verify(&rfc_secret(), "287 082", 59, 0), Some(1));
+    }
+
+    #[test]
+    fn provisioning_uri_names_issuer_and_account() {
+        let uri = provisioning_uri("JBSWY3DPEHPK3PXP", "ada@example.com", "Kaiju Academy");
+
+        assert_eq!(
+            uri,
+            "otpauth://totp/Kaiju%20Academy%3Aada%40example.com?secret=JBSWY3DPEHPK3PXP\
+             &issuer=Kaiju%20Academy&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+
+    #[test]
+    fn recovery_codes_hash_the_same_however_they_are_typed() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+
+        let code = &codes[0];
+        assert_eq!(hash_recovery_code(code), hash_recovery_code(&code.to_uppercase().replace('-', " ")));
+        assert_ne!(hash_recovery_code(code), hash_recovery_code(&codes[1]));
+    }
+}
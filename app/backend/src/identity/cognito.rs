@@ -0,0 +1,438 @@
+//! Cognito identity provider.
+//!
+//! Passwords, confirmation codes and reset codes are handled by a Cognito user pool app
+//! client (`cognito_client_id`, plus `cognito_client_secret` if the client has one). The
+//! client must allow the `USER_PASSWORD_AUTH` flow. Point `cognito_endpoint_url` at a local
+//! mock such as cognito-local to run without AWS.
+
+use async_trait::async_trait;
+use aws_sdk_cognitoidentityprovider::error::{DisplayErrorContext, ProvideErrorMetadata, SdkError};
+use aws_sdk_cognitoidentityprovider::types::{AttributeType, AuthFlowType};
+use aws_sdk_cognitoidentityprovider::Client;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use surrealdb::sql::Thing;
+use tracing::{error, info, Instrument};
+
+use crate::common::config::CONFIG;
+use crate::common::error::{AppError, ErrorCode};
+use crate::common::password::POLICY;
+use crate::common::telemetry;
+use crate::identity::IdentityProvider;
+use crate::models::token::ResetPasswordRequest;
+use crate::models::user::{User, UserRegistrationRequest};
+use crate::repository::UserRepository;
+
+/// Identity provider backed by a Cognito user pool
+pub struct CognitoIdentityProvider<R> {
+    client: Client,
+    client_id: String,
+    client_secret: Option<String>,
+    repo: R,
+}
+
+impl<R: UserRepository> CognitoIdentityProvider<R> {
+    /// Create a provider for an app client
+    pub fn new(client: Client, client_id: String, client_secret: Option<String>, repo: R) -> Self {
+        Self { client, client_id, client_secret, repo }
+    }
+
+    /// Create a provider from the `cognito_*` settings
+    pub async fn connect(repo: R) -> Result<Self, AppError> {
+        if CONFIG.cognito_client_id.is_empty() {
+            return Err(AppError::Internal("COGNITO_CLIENT_ID must be set when IDENTITY_PROVIDER=cognito".to_string()));
+        }
+
+        let sdk_config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+        let mut builder = aws_sdk_cognitoidentityprovider::config::Builder::from(&sdk_config);
+        if !CONFIG.cognito_endpoint_url.is_empty() {
+            builder = builder.endpoint_url(&CONFIG.cognito_endpoint_url);
+        }
+
+        let client_secret = Some(CONFIG.cognito_client_secret.clone()).filter(|secret| !secret.is_empty());
+
+        Ok(Self::new(
+            Client::from_conf(builder.build()),
+            CONFIG.cognito_client_id.clone(),
+            client_secret,
+            repo,
+        ))
+    }
+
+    /// The `SECRET_HASH` Cognito requires from app clients that have a secret
+    fn secret_hash(&self, username: &str) -> Option<String> {
+        let secret = self.client_secret.as_ref()?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).ok()?;
+        mac.update(username.as_bytes());
+        mac.update(self.client_id.as_bytes());
+        Some(STANDARD.encode(mac.finalize().into_bytes()))
+    }
+
+    /// Load the local user record for a Cognito account
+    async fn local_user(&self, email: &str) -> Result<User, AppError> {
+        self.repo.find_user_by_email(email).await?.ok_or_else(|| {
+            error!("Cognito account {} has no local user record", email);
+            AppError::Internal("User profile not found".to_string())
+        })
+    }
+}
+
+#[async_trait]
+impl<R: UserRepository> IdentityProvider for CognitoIdentityProvider<R> {
+    async fn sign_up(&self, registration: UserRegistrationRequest) -> Result<User, AppError> {
+        if self.repo.find_user_by_email(&registration.email).await?.is_some() {
+            return Err(AppError::Validation(ErrorCode::EmailTaken, "User with this email already exists".to_string()));
+        }
+        POLICY.check(&registration.password, &registration.email)?;
+
+        // Cognito sends the confirmation code itself
+        self.client
+            .sign_up()
+            .client_id(&self.client_id)
+            .set_secret_hash(self.secret_hash(&registration.email))
+            .username(&registration.email)
+            .password(&registration.password)
+            .user_attributes(attribute("email", &registration.email)?)
+            .user_attributes(attribute("name", &registration.name)?)
+            .send()
+            .instrument(telemetry::aws_span("CognitoIdentityProvider", "SignUp"))
+            .await
+            .map_err(cognito_error)?;
+
+        info!("Created Cognito user for {}", registration.email);
+
+        // The local record holds the profile and role; the password stays in Cognito
+        let user = User::new(registration.email, registration.name, registration.role);
+        self.repo.create_user(user).await
+    }
+
+    async fn sign_in(&self, email: &str, password: &str) -> Result<User, AppError> {
+        let mut request = self
+            .client
+            .initiate_auth()
+            .client_id(&self.client_id)
+            .auth_flow(AuthFlowType::UserPasswordAuth)
+            .auth_parameters("USERNAME", email)
+            .auth_parameters("PASSWORD", password);
+        if let Some(hash) = self.secret_hash(email) {
+            request = request.auth_parameters("SECRET_HASH", hash);
+        }
+
+        let output = request
+            .send()
+            .instrument(telemetry::aws_span("CognitoIdentityProvider", "InitiateAuth"))
+            .await
+            .map_err(cognito_error)?;
+
+        // Kaiju issues its own tokens, so only a completed sign-in matters here
+        if output.authentication_result().is_none() {
+            let challenge = output
+                .challenge_name()
+                .map(|challenge| challenge.as_str().to_string())
+                .unwrap_or_default();
+            return Err(AppError::Authentication(
+                ErrorCode::SignInFailed,
+                format!("Unsupported sign-in challenge: {}", challenge),
+            ));
+        }
+
+        self.local_user(email).await
+    }
+
+    async fn confirm(&self, email: &str, code: &str) -> Result<(), AppError> {
+        self.client
+            .confirm_sign_up()
+            .client_id(&self.client_id)
+            .set_secret_hash(self.secret_hash(email))
+            .username(email)
+            .confirmation_code(code)
+            .send()
+            .instrument(telemetry::aws_span("CognitoIdentityProvider", "ConfirmSignUp"))
+            .await
+            .map_err(|err| match err.code() {
+                Some("UserNotFoundException") => {
+                    AppError::Validation(ErrorCode::InvalidVerificationCode, "Invalid or expired verification code".to_string())
+                }
+                _ => cognito_error(err),
+            })?;
+
+        let user = self.local_user(email).await?;
+        if let Some(id) = &user.id {
+            self.repo.mark_email_verified(id).await?;
+        }
+
+        info!("Verified email for user: {}", email);
+        Ok(())
+    }
+
+    async fn resend_confirmation(&self, email: &str) -> Result<(), AppError> {
+        let result = self
+            .client
+            .resend_confirmation_code()
+            .client_id(&self.client_id)
+            .set_secret_hash(self.secret_hash(email))
+            .username(email)
+            .send()
+            .instrument(telemetry::aws_span("CognitoIdentityProvider", "ResendConfirmationCode"))
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            // Confirmed users are rejected as not authorized; neither case is reported to the caller
+            Err(err) if matches!(err.code(), Some("UserNotFoundException" | "InvalidParameterException")) => Ok(()),
+            Err(err) => Err(cognito_error(err)),
+        }
+    }
+
+    async fn forgot_password(&self, email: &str) -> Result<(), AppError> {
+        let result = self
+            .client
+            .forgot_password()
+            .client_id(&self.client_id)
+            .set_secret_hash(self.secret_hash(email))
+            .username(email)
+            .send()
+            .instrument(telemetry::aws_span("CognitoIdentityProvider", "ForgotPassword"))
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(err) if err.code() == Some("UserNotFoundException") => {
+                info!("Password reset requested for unknown email");
+                Ok(())
+            }
+            Err(err) => Err(cognito_error(err)),
+        }
+    }
+
+    async fn reset_password(&self, reset: ResetPasswordRequest) -> Result<Thing, AppError> {
+        let email = reset
+            .email
+            .filter(|email| !email.is_empty())
+            .ok_or_else(|| AppError::invalid_field("email", ErrorCode::Required, "Email is required"))?;
+        POLICY.check(&reset.password, &email)?;
+
+        self.client
+            .confirm_forgot_password()
+            .client_id(&self.client_id)
+            .set_secret_hash(self.secret_hash(&email))
+            .username(&email)
+            .confirmation_code(&reset.token)
+            .password(&reset.password)
+            .send()
+            .instrument(telemetry::aws_span("CognitoIdentityProvider", "ConfirmForgotPassword"))
+            .await
+            .map_err(|err| match err.code() {
+                Some("CodeMismatchException" | "ExpiredCodeException" | "UserNotFoundException") => {
+                    AppError::Validation(ErrorCode::InvalidResetToken, "Invalid or expired reset token".to_string())
+                }
+                _ => cognito_error(err),
+            })?;
+
+        self.local_user(&email)
+            .await?
+            .id
+            .ok_or_else(|| AppError::Internal("User record has no ID".to_string()))
+    }
+}
+
+/// Build a user attribute
+fn attribute(name: &str, value: &str) -> Result<AttributeType, AppError> {
+    AttributeType::builder()
+        .name(name)
+        .value(value)
+        .build()
+        .map_err(|e| AppError::Internal(format!("Invalid Cognito attribute {}: {}", name, e)))
+}
+
+/// Translate a Cognito error into the matching application error
+fn cognito_error<E>(err: SdkError<E>) -> AppError
+where
+    E: ProvideErrorMetadata + std::error::Error + 'static,
+{
+    let message = err.message().unwrap_or_default().to_string();
+
+    match err.code() {
+        Some("UsernameExistsException") => AppError::Validation(
+            ErrorCode::EmailTaken,
+            "User with this email already exists".to_string(),
+        ),
+        Some("InvalidPasswordException") => AppError::invalid_field("password", ErrorCode::InvalidFormat, message),
+        Some("InvalidParameterException") => AppError::Validation(ErrorCode::ValidationFailed, message),
+        Some("NotAuthorizedException" | "UserNotFoundException") => {
+            AppError::Authentication(ErrorCode::InvalidCredentials, "Invalid email or password".to_string())
+        }
+        Some("UserNotConfirmedException") => {
+            AppError::Authorization(ErrorCode::EmailNotVerified, "Email address has not been verified".to_string())
+        }
+        Some("PasswordResetRequiredException") => {
+            AppError::Authorization(ErrorCode::PasswordResetRequired, "Password reset required".to_string())
+        }
+        Some("CodeMismatchException" | "ExpiredCodeException") => {
+            AppError::Validation(ErrorCode::InvalidVerificationCode, "Invalid or expired verification code".to_string())
+        }
+        Some("LimitExceededException" | "TooManyRequestsException" | "TooManyFailedAttemptsException") => {
+            AppError::RateLimit(ErrorCode::RateLimited, message)
+        }
+        _ => {
+            error!("Cognito request failed: {}", DisplayErrorContext(&err));
+            AppError::ExternalService("Identity provider request failed".to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+    use std::sync::{Arc, Mutex};
+
+    use aws_sdk_cognitoidentityprovider::config::{BehaviorVersion, Credentials, Region};
+    use http_body_util::{BodyExt, Full};
+    use hyper::body::{Bytes, Incoming};
+    use hyper::server::conn::http1;
+    use hyper::service::service_fn;
+    use hyper::{Request, Response};
+    use hyper_util::rt::TokioIo;
+    use surrealdb::engine::any::Any;
+    use surrealdb::Surreal;
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::repository::SurrealRepository;
+
+    /// Requests received by the mock, as `(operation, body)`
+    type Received = Arc<Mutex<Vec<(String, serde_json::Value)>>>;
+
+    /// Serve canned Cognito responses keyed by operation name, recording every request
+    async fn mock_cognito(responses: &'static [(&'static str, u16, &'static str)]) -> (SocketAddr, Received) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let received: Received = Arc::default();
+        let log = received.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let log = log.clone();
+                let service = service_fn(move |request: Request<Incoming>| {
+                    let log = log.clone();
+                    async move {
+                        let operation = request
+                            .headers()
+                            .get("x-amz-target")
+                            .and_then(|value| value.to_str().ok())
+                            .and_then(|target| target.rsplit('.').next())
+                            .unwrap_or_default()
+                            .to_string();
+                        let body = request.into_body().collect().await.unwrap().to_bytes();
+                        log.lock().unwrap().push((operation.clone(), serde_json::from_slice(&body).unwrap()));
+
+                        let (_, status, body) = responses
+                            .iter()
+                            .find(|(name, _, _)| *name == operation)
+                            .copied()
+                            .unwrap_or(("", 500, "{}"));
+                        let mut response = Response::new(Full::new(Bytes::from(body)));
+                        *response.status_mut() = hyper::StatusCode::from_u16(status).unwrap();
+                        response
+                            .headers_mut()
+                            .insert("content-type", "application/x-amz-json-1.1".parse().unwrap());
+                        Ok::<_, Infallible>(response)
+                    }
+                });
+                tokio::spawn(http1::Builder::new().serve_connection(TokioIo::new(stream), service));
+            }
+        });
+
+        (addr, received)
+    }
+
+    /// A provider talking to the mock. The repository is never connected; these tests only
+    /// cover paths that fail or finish before touching the database.
+    fn provider(addr: SocketAddr, secret: Option<&str>) -> CognitoIdentityProvider<SurrealRepository<Any>> {
+        let config = aws_sdk_cognitoidentityprovider::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .credentials_provider(Credentials::new("test", "test", None, None, "test"))
+            .endpoint_url(format!("http://{}", addr))
+            .build();
+
+        CognitoIdentityProvider::new(
+            Client::from_conf(config),
+            "client".to_string(),
+            secret.map(str::to_string),
+            SurrealRepository::new(Surreal::init()),
+        )
+    }
+
+    #[tokio::test]
+    async fn wrong_password_is_an_authentication_error() {
+        let (addr, received) = mock_cognito(&[(
+            "InitiateAuth",
+            400,
+            r#"{"__type":"NotAuthorizedException","message":"Incorrect username or password."}"#,
+        )])
+        .await;
+
+        let result = provider(addr, Some("secret")).sign_in("ada@example.com", "wrong").await;
+        assert!(matches!(result, Err(AppError::Authentication(..))));
+
+        let received = received.lock().unwrap();
+        let (operation, body) = &received[0];
+        assert_eq!(operation, "InitiateAuth");
+        assert_eq!(body["AuthFlow"], "USER_PASSWORD_AUTH");
+        assert_eq!(body["ClientId"], "client");
+
+        // base64(HMAC-SHA256("secret", "ada@example.com" + "client"))
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+        mac.update(b"ada@example.comclient");
+        assert_eq!(body["AuthParameters"]["SECRET_HASH"], STANDARD.encode(mac.finalize().into_bytes()));
+    }
+
+    #[tokio::test]
+    async fn unconfirmed_users_are_told_to_verify() {
+        let (addr, _) = mock_cognito(&[(
+            "InitiateAuth",
+            400,
+            r#"{"__type":"UserNotConfirmedException","message":"User is not confirmed."}"#,
+        )])
+        .await;
+
+        let result = provider(addr, None).sign_in("ada@example.com", "password").await;
+        assert!(matches!(result, Err(AppError::Authorization(..))));
+    }
+
+    #[tokio::test]
+    async fn forgot_password_hides_unknown_users() {
+        let (addr, received) = mock_cognito(&[(
+            "ForgotPassword",
+            400,
+            r#"{"__type":"UserNotFoundException","message":"Username/client id combination not found."}"#,
+        )])
+        .await;
+
+        provider(addr, None).forgot_password("nobody@example.com").await.unwrap();
+        assert_eq!(received.lock().unwrap()[0].1["Username"], "nobody@example.com");
+    }
+
+    #[tokio::test]
+    async fn bad_reset_code_is_a_validation_error() {
+        let (addr, _) = mock_cognito(&[(
+            "ConfirmForgotPassword",
+            400,
+            r#"{"__type":"CodeMismatchException","message":"Invalid verification code provided."}"#,
+        )])
+        .await;
+
+        let reset = ResetPasswordRequest {
+            token: "123456".to_string(),
+            password: "new password".to_string(),
+            email: Some("ada@example.com".to_string()),
+        };
+        let result = provider(addr, None).reset_password(reset).await;
+        assert!(matches!(result, Err(AppError::Validation(..))));
+    }
+}
@@ -0,0 +1,67 @@
+//! Identity providers.
+//!
+//! An [`IdentityProvider`] owns credentials: it creates accounts, checks passwords and runs
+//! the email confirmation and password reset flows. `identity_provider` selects the
+//! implementation: `native` keeps Argon2 hashes in SurrealDB (see [`native`]) and `cognito`
+//! delegates to an Amazon Cognito app client (see [`cognito`]). Either way the `user`
+//! record in SurrealDB holds the profile and role, and sessions are Kaiju tokens from
+//! `common::auth`.
+//!
+//! Users can also sign in through external OpenID Connect providers (see [`oidc`]), which
+//! link to the same `user` records.
+
+pub mod cognito;
+pub mod native;
+pub mod oidc;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use surrealdb::sql::Thing;
+
+use crate::common::config::{IdentityProviderKind, CONFIG};
+use crate::common::error::AppError;
+use crate::common::mailer;
+use crate::models::token::ResetPasswordRequest;
+use crate::models::user::{User, UserRegistrationRequest};
+use crate::repository::DbRepository;
+
+pub use cognito::CognitoIdentityProvider;
+pub use native::NativeIdentityProvider;
+
+/// Account and credential operations
+#[async_trait]
+pub trait IdentityProvider: Send + Sync {
+    /// Create an account and send its confirmation code, returning the stored user.
+    ///
+    /// The password must pass the password policy.
+    async fn sign_up(&self, registration: UserRegistrationRequest) -> Result<User, AppError>;
+
+    /// Check an email and password, returning the user they belong to
+    async fn sign_in(&self, email: &str, password: &str) -> Result<User, AppError>;
+
+    /// Confirm an email address with the code sent at sign-up
+    async fn confirm(&self, email: &str, code: &str) -> Result<(), AppError>;
+
+    /// Send a new confirmation code. Unknown and confirmed addresses are ignored.
+    async fn resend_confirmation(&self, email: &str) -> Result<(), AppError>;
+
+    /// Send a password reset code or link. Unknown addresses are ignored.
+    async fn forgot_password(&self, email: &str) -> Result<(), AppError>;
+
+    /// Set a new password with a reset code, returning the user whose password changed.
+    ///
+    /// The password must pass the password policy.
+    async fn reset_password(&self, reset: ResetPasswordRequest) -> Result<Thing, AppError>;
+}
+
+/// Build the identity provider selected by `identity_provider`
+pub async fn connect(repo: DbRepository) -> Result<Box<dyn IdentityProvider>, AppError> {
+    match CONFIG.identity_provider {
+        IdentityProviderKind::Native => {
+            let mailer = mailer::connect().await?;
+            Ok(Box::new(NativeIdentityProvider::new(repo, Arc::from(mailer))))
+        }
+        IdentityProviderKind::Cognito => Ok(Box::new(CognitoIdentityProvider::connect(repo).await?)),
+    }
+}
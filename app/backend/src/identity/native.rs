@@ -0,0 +1,274 @@
+//! Native identity provider: Argon2 password hashes and one-time codes in SurrealDB,
+//! with emails sent through a [`Mailer`].
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use rand::Rng;
+use surrealdb::sql::Thing;
+use tracing::{error, info};
+
+use crate::common::auth::{self, PasswordMatch};
+use crate::common::config::CONFIG;
+use crate::common::error::{AppError, ErrorCode};
+use crate::common::mailer::{Email, Mailer};
+use crate::common::password::POLICY;
+use crate::identity::IdentityProvider;
+use crate::models::token::{EmailVerification, PasswordReset, ResetPasswordRequest};
+use crate::models::user::{User, UserRegistrationRequest};
+use crate::repository::{TokenRepository, UserRepository, VerificationRepository};
+
+/// Identity provider storing credentials alongside the user records
+pub struct NativeIdentityProvider<R> {
+    repo: R,
+    mailer: Arc<dyn Mailer>,
+}
+
+impl<R> NativeIdentityProvider<R>
+where
+    R: UserRepository + TokenRepository + VerificationRepository,
+{
+    /// Create a provider over the given repository and mailer
+    pub fn new(repo: R, mailer: Arc<dyn Mailer>) -> Self {
+        Self { repo, mailer }
+    }
+
+    /// Find a user by email, failing if the record has no ID
+    async fn find_user(&self, email: &str) -> Result<Option<(Thing, User)>, AppError> {
+        match self.repo.find_user_by_email(email).await? {
+            Some(user) => {
+                let id = user
+                    .id
+                    .clone()
+                    .ok_or_else(|| AppError::Internal("User record has no ID".to_string()))?;
+                Ok(Some((id, user)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Replace the user's verification code with a fresh one and email it
+    async fn send_verification_code(&self, user_id: &Thing, user: &User) -> Result<(), AppError> {
+        let code = format!("{:06}", rand::rng().random_range(0..1_000_000));
+        let expiry_minutes = CONFIG.email_verification_expiry_minutes;
+        let expires_at = Utc::now() + Duration::minutes(expiry_minutes as i64);
+
+        self.repo
+            .replace_email_verification(EmailVerification::new(
+                auth::hash_opaque_token(&code),
+                user_id.clone(),
+                expires_at,
+            ))
+            .await?;
+
+        let body = format!(
+            "Hi {},\n\nWelcome to Kaiju Academy! Your verification code is:\n\n{}\n\nThe code expires in {} minutes. If you did not create an account, you can ignore this email.",
+            user.name, code, expiry_minutes
+        );
+
+        self.mailer
+            .send(&Email::new(&user.email, "Verify your Kaiju Academy email", body))
+            .await
+    }
+
+    /// Replace a hash made with older Argon2 costs. Failures are only logged; the next
+    /// login tries again.
+    async fn rehash_password(&self, user: &User, password: &str) {
+        let Some(id) = &user.id else {
+            return;
+        };
+
+        let result = match auth::hash_password(password) {
+            Ok(hash) => self.repo.update_password(id, &hash).await,
+            Err(err) => Err(err),
+        };
+
+        match result {
+            Ok(()) => info!("Rehashed password for {} with the current Argon2 costs", user.email),
+            Err(err) => error!("Failed to rehash password for {}: {}", user.email, err),
+        }
+    }
+
+    /// Replace any outstanding reset token with a new one and email the link
+    async fn send_reset_link(&self, user_id: &Thing, user: &User) -> Result<(), AppError> {
+        let now = Utc::now();
+        let expiry_minutes = CONFIG.password_reset_expiry_minutes;
+
+        // Only the most recent link works
+        self.repo.invalidate_password_resets(user_id, now).await?;
+
+        let token = auth::generate_opaque_token();
+        let expires_at = now + Duration::minutes(expiry_minutes as i64);
+        self.repo
+            .create_password_reset(PasswordReset::new(auth::hash_opaque_token(&token), user_id.clone(), expires_at))
+            .await?;
+
+        let link = format!("{}/password-reset?token={}", CONFIG.app_base_url.trim_end_matches('/'), token);
+        let body = format!(
+            "Hi {},\n\nWe received a request to reset your Kaiju Academy password. Open this link to choose a new one:\n\n{}\n\nThe link expires in {} minutes and can be used once. If you did not ask for a reset, you can ignore this email.",
+            user.name, link, expiry_minutes
+        );
+
+        self.mailer
+            .send(&Email::new(&user.email, "Reset your Kaiju Academy password", body))
+            .await
+    }
+}
+
+#[async_trait]
+impl<R> IdentityProvider for NativeIdentityProvider<R>
+where
+    R: UserRepository + TokenRepository + VerificationRepository,
+{
+    async fn sign_up(&self, registration: UserRegistrationRequest) -> Result<User, AppError> {
+        // Check if user with this email already exists
+        if self.repo.find_user_by_email(&registration.email).await?.is_some() {
+            return Err(AppError::Validation(ErrorCode::EmailTaken, "User with this email already exists".to_string()));
+        }
+
+        POLICY.check(&registration.password, &registration.email)?;
+
+        // Create password hash
+        let password_hash = auth::hash_password(&registration.password).map_err(|err| {
+            error!("{}", err);
+            AppError::Internal("Failed to process credentials".to_string())
+        })?;
+
+        let mut new_user = User::new(registration.email, registration.name, registration.role);
+        new_user.password = Some(password_hash);
+
+        let user = self.repo.create_user(new_user).await?;
+
+        // Email a verification code; the account works without it unless verification is required
+        if let Some(id) = &user.id
+            && let Err(err) = self.send_verification_code(id, &user).await
+        {
+            error!("Failed to send verification code to {}: {}", user.email, err);
+        }
+
+        Ok(user)
+    }
+
+    async fn sign_in(&self, email: &str, password: &str) -> Result<User, AppError> {
+        let invalid = || AppError::Authentication(ErrorCode::InvalidCredentials, "Invalid email or password".to_string());
+
+        let user = self.repo.find_user_by_email(email).await?.ok_or_else(invalid)?;
+        let stored_password = user.password.as_deref().ok_or_else(invalid)?;
+
+        match auth::verify_password(password, stored_password) {
+            PasswordMatch::Valid => {}
+            PasswordMatch::Outdated => self.rehash_password(&user, password).await,
+            PasswordMatch::Invalid => return Err(invalid()),
+        }
+
+        Ok(user)
+    }
+
+    async fn confirm(&self, email: &str, code: &str) -> Result<(), AppError> {
+        let invalid = || AppError::Validation(
+            ErrorCode::InvalidVerificationCode,
+            "Invalid or expired verification code".to_string(),
+        );
+
+        let (user_id, user) = self.find_user(email).await?.ok_or_else(invalid)?;
+        if user.email_verified {
+            return Ok(());
+        }
+
+        // Count this guess before checking it, so parallel guesses cannot exceed the limit
+        let verification = self
+            .repo
+            .record_verification_attempt(&user_id, CONFIG.email_verification_max_attempts)
+            .await?
+            .ok_or_else(|| {
+                AppError::RateLimit(
+                    ErrorCode::TooManyIncorrectCodes,
+                    "Too many incorrect codes. Request a new verification code.".to_string(),
+                )
+            })?;
+
+        if verification.expires_at <= Utc::now() || verification.code_hash != auth::hash_opaque_token(code) {
+            return Err(invalid());
+        }
+
+        self.repo.mark_email_verified(&user_id).await?;
+        self.repo.delete_email_verification(&user_id).await?;
+
+        info!("Verified email for user: {}", user.email);
+        Ok(())
+    }
+
+    async fn resend_confirmation(&self, email: &str) -> Result<(), AppError> {
+        let Some((user_id, user)) = self.find_user(email).await? else {
+            return Ok(());
+        };
+        if user.email_verified {
+            return Ok(());
+        }
+
+        let cooldown = Duration::seconds(CONFIG.email_verification_resend_secs as i64);
+        if let Some(previous) = self.repo.find_email_verification(&user_id).await?
+            && previous.created_at + cooldown > Utc::now()
+        {
+            let wait = (previous.created_at + cooldown - Utc::now()).num_seconds().max(1);
+            return Err(AppError::RateLimit(ErrorCode::CodeRecentlySent, format!(
+                "A verification code was sent recently. Try again in {} seconds.",
+                wait
+            )));
+        }
+
+        self.send_verification_code(&user_id, &user).await?;
+
+        info!("Resent verification code to {}", user.email);
+        Ok(())
+    }
+
+    async fn forgot_password(&self, email: &str) -> Result<(), AppError> {
+        match self.find_user(email).await? {
+            Some((user_id, user)) => {
+                // Failures are logged rather than returned, so they do not reveal that the account exists
+                if let Err(err) = self.send_reset_link(&user_id, &user).await {
+                    error!("Failed to send password reset for {}: {}", user.email, err);
+                }
+            }
+            None => {
+                info!("Password reset requested for unknown email");
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn reset_password(&self, reset: ResetPasswordRequest) -> Result<Thing, AppError> {
+        let invalid = || AppError::Validation(ErrorCode::InvalidResetToken, "Invalid or expired reset token".to_string());
+        let token_hash = auth::hash_opaque_token(&reset.token);
+        let now = Utc::now();
+
+        // Check the new password against the account before spending the token
+        let pending = self
+            .repo
+            .find_password_reset(&token_hash)
+            .await?
+            .filter(|pending| pending.used_at.is_none() && pending.expires_at > now)
+            .ok_or_else(invalid)?;
+        let user = self.repo.find_user(&pending.user).await?.ok_or_else(invalid)?;
+        POLICY.check(&reset.password, &user.email)?;
+
+        let password_hash = auth::hash_password(&reset.password).map_err(|err| {
+            error!("{}", err);
+            AppError::Internal("Failed to process credentials".to_string())
+        })?;
+
+        // Use up the token; this fails if a concurrent reset spent it first
+        let consumed = self
+            .repo
+            .consume_password_reset(&token_hash, now)
+            .await?
+            .ok_or_else(invalid)?;
+
+        self.repo.update_password(&consumed.user, &password_hash).await?;
+
+        Ok(consumed.user)
+    }
+}
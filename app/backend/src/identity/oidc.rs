@@ -0,0 +1,624 @@
+//! OpenID Connect sign-in.
+//!
+//! Each entry in `oidc_providers` is an OpenID provider users can sign in with, using the
+//! authorization code flow with PKCE:
+//!
+//! 1. `POST /auth/oidc/{provider}/authorize` reads the provider's discovery document, stores
+//!    a random `state` (hashed), nonce and PKCE verifier, and returns the authorization URL.
+//! 2. The provider sends the user back to the provider's `redirect_uri` on the frontend,
+//!    which posts the `code` and `state` to `POST /auth/oidc/{provider}/callback`.
+//! 3. The callback spends the state, redeems the code with the verifier, and checks the ID
+//!    token's signature against the provider's JWKS along with its issuer, audience, expiry
+//!    and nonce.
+//!
+//! Provider accounts are linked to users by [`link_account`]: first by the provider's
+//! `sub`, then by email address, but only when the provider says the address is verified.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{Duration, Utc};
+use jsonwebtoken::jwk::{AlgorithmParameters, EllipticCurve, JwkSet};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Deserializer};
+use sha2::{Digest, Sha256};
+use tracing::{error, info};
+
+use crate::common::auth;
+use crate::common::config::{OidcProviderConfig, OidcProviders, CONFIG};
+use crate::common::error::{AppError, ErrorCode};
+use crate::models::oidc::{OidcState, UserIdentity};
+use crate::models::user::{User, UserRole};
+use crate::repository::{OidcRepository, UserRepository};
+
+/// How long to wait for the provider before giving up
+const HTTP_TIMEOUT_SECS: u64 = 10;
+
+/// The parts of a provider's discovery document the flow uses
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+/// A started sign-in: where to send the user, and what to store until they come back
+#[derive(Debug, Clone)]
+pub struct Authorization {
+    pub url: String,
+    pub state: String,
+    pub pending: OidcState,
+}
+
+/// ID token claims used to find or create the user
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default, deserialize_with = "bool_or_string")]
+    pub email_verified: bool,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub nonce: Option<String>,
+}
+
+/// Successful token endpoint response
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    #[serde(default)]
+    id_token: Option<String>,
+}
+
+/// Client for one configured provider
+pub struct OidcClient {
+    provider: OidcProviderConfig,
+    http: reqwest::Client,
+}
+
+impl OidcClient {
+    /// Create a client for a provider
+    pub fn new(provider: OidcProviderConfig) -> Result<Self, AppError> {
+        let http = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(HTTP_TIMEOUT_SECS))
+            .build()
+            .map_err(|e| AppError::Internal(format!("Failed to build HTTP client: {}", e)))?;
+
+        Ok(Self { provider, http })
+    }
+
+    /// Create a client for the provider named in a route
+    pub fn from_config(providers: &OidcProviders, name: &str) -> Result<Self, AppError> {
+        let provider = providers
+            .get(name)
+            .ok_or_else(|| AppError::NotFound(ErrorCode::ProviderNotFound, format!("Unknown sign-in provider: {}", name)))?;
+
+        Self::new(provider.clone())
+    }
+
+    /// The provider's configured name
+    pub fn name(&self) -> &str {
+        &self.provider.name
+    }
+
+    /// Read the provider's discovery document, checking it belongs to the configured issuer
+    pub async fn discover(&self) -> Result<ProviderMetadata, AppError> {
+        let url = format!("{}/.well-known/openid-configuration", self.provider.issuer.trim_end_matches('/'));
+        let metadata: ProviderMetadata = self.get_json(&url).await?;
+
+        if metadata.issuer.trim_end_matches('/') != self.provider.issuer.trim_end_matches('/') {
+            error!("Discovery for {} names issuer {}", self.provider.issuer, metadata.issuer);
+            return Err(AppError::ExternalService("Sign-in provider is misconfigured".to_string()));
+        }
+
+        Ok(metadata)
+    }
+
+    /// Start a sign-in, generating the state, nonce and PKCE verifier
+    pub fn authorize(&self, metadata: &ProviderMetadata) -> Result<Authorization, AppError> {
+        let state = auth::generate_opaque_token();
+        let nonce = auth::generate_opaque_token();
+        let code_verifier = auth::generate_opaque_token();
+        let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+
+        let mut url = Url::parse(&metadata.authorization_endpoint)
+            .map_err(|e| AppError::ExternalService(format!("Invalid authorization endpoint: {}", e)))?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.provider.client_id)
+            .append_pair("redirect_uri", &self.provider.redirect_uri)
+            .append_pair("scope", &self.provider.scopes)
+            .append_pair("state", &state)
+            .append_pair("nonce", &nonce)
+            .append_pair("code_challenge", &code_challenge)
+            .append_pair("code_challenge_method", "S256");
+
+        let now = Utc::now();
+        let pending = OidcState {
+            id: None,
+            provider: self.provider.name.clone(),
+            nonce,
+            code_verifier,
+            expires_at: now + Duration::minutes(CONFIG.oidc_state_expiry_minutes as i64),
+            created_at: now,
+        };
+
+        Ok(Authorization { url: url.to_string(), state, pending })
+    }
+
+    /// Redeem an authorization code and return the verified ID token claims
+    pub async fn exchange_code(&self, metadata: &ProviderMetadata, code: &str, pending: &OidcState) -> Result<IdTokenClaims, AppError> {
+        let form = [
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", self.provider.redirect_uri.as_str()),
+            ("client_id", self.provider.client_id.as_str()),
+            ("code_verifier", pending.code_verifier.as_str()),
+        ];
+
+        let mut request = self.http.post(&metadata.token_endpoint).form(&form);
+        if let Some(secret) = &self.provider.client_secret {
+            request = request.basic_auth(&self.provider.client_id, Some(secret));
+        }
+
+        let response = request.send().await.map_err(|e| {
+            error!("Token request to {} failed: {}", self.provider.name, e);
+            AppError::ExternalService("Sign-in provider is unavailable".to_string())
+        })?;
+
+        match response.status() {
+            status if status.is_success() => {}
+            // A bad, expired or replayed code, or a verifier that does not match
+            StatusCode::BAD_REQUEST => {
+                let body = response.text().await.unwrap_or_default();
+                info!("{} rejected an authorization code: {}", self.provider.name, body);
+                return Err(AppError::Authentication(
+                    ErrorCode::SignInFailed,
+                    "Sign-in was rejected by the provider. Try again.".to_string(),
+                ));
+            }
+            status => {
+                error!("Token request to {} returned {}", self.provider.name, status);
+                return Err(AppError::ExternalService("Sign-in provider is unavailable".to_string()));
+            }
+        }
+
+        let tokens: TokenResponse = response.json().await.map_err(|e| {
+            error!("Invalid token response from {}: {}", self.provider.name, e);
+            AppError::ExternalService("Invalid response from sign-in provider".to_string())
+        })?;
+        let id_token = tokens
+            .id_token
+            .ok_or_else(|| AppError::ExternalService("Sign-in provider returned no ID token".to_string()))?;
+
+        let jwks: JwkSet = self.get_json(&metadata.jwks_uri).await?;
+        self.verify_id_token(metadata, &jwks, &id_token, &pending.nonce)
+    }
+
+    /// Check an ID token's signature, issuer, audience, expiry and nonce
+    pub fn verify_id_token(&self, metadata: &ProviderMetadata, jwks: &JwkSet, id_token: &str, nonce: &str) -> Result<IdTokenClaims, AppError> {
+        let invalid = |reason: &str| {
+            info!("Rejected ID token from {}: {}", self.provider.name, reason);
+            AppError::Authentication(ErrorCode::SignInFailed, "Invalid ID token".to_string())
+        };
+
+        let header = decode_header(id_token).map_err(|_| invalid("malformed header"))?;
+        let jwk = match &header.kid {
+            Some(kid) => jwks.find(kid),
+            None if jwks.keys.len() == 1 => jwks.keys.first(),
+            None => None,
+        }
+        .ok_or_else(|| invalid("unknown signing key"))?;
+
+        // The algorithm must suit the published key; symmetric keys are never accepted
+        let allowed: &[Algorithm] = match &jwk.algorithm {
+            AlgorithmParameters::RSA(_) => &[
+                Algorithm::RS256,
+                Algorithm::RS384,
+                Algorithm::RS512,
+                Algorithm::PS256,
+                Algorithm::PS384,
+                Algorithm::PS512,
+            ],
+            AlgorithmParameters::EllipticCurve(params) if params.curve == EllipticCurve::P256 => &[Algorithm::ES256],
+            AlgorithmParameters::EllipticCurve(params) if params.curve == EllipticCurve::P384 => &[Algorithm::ES384],
+            AlgorithmParameters::OctetKeyPair(params) if params.curve == EllipticCurve::Ed25519 => &[Algorithm::EdDSA],
+            _ => &[],
+        };
+        if !allowed.contains(&header.alg) {
+            return Err(invalid("unsupported algorithm"));
+        }
+
+        let key = DecodingKey::from_jwk(jwk).map_err(|_| invalid("unusable signing key"))?;
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&metadata.issuer]);
+        validation.set_audience(&[&self.provider.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+        let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map_err(|e| invalid(&e.to_string()))?
+            .claims;
+
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(invalid("nonce mismatch"));
+        }
+
+        Ok(claims)
+    }
+
+    /// GET a JSON document from the provider
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> Result<T, AppError> {
+        let response = self
+            .http
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| {
+                error!("Request to {} failed: {}", url, e);
+                AppError::ExternalService("Sign-in provider is unavailable".to_string())
+            })?;
+
+        response.json().await.map_err(|e| {
+            error!("Invalid response from {}: {}", url, e);
+            AppError::ExternalService("Invalid response from sign-in provider".to_string())
+        })
+    }
+}
+
+/// Find the user a provider account belongs to, linking or creating one on first sign-in.
+///
+/// An unlinked provider account is linked to the user with the same email address, or a new
+/// student account is created for it, but only if the provider has verified the address.
+/// Existing users are only linked once they have verified the address themselves.
+pub async fn link_account(
+    repo: &(impl UserRepository + OidcRepository),
+    provider: &str,
+    claims: &IdTokenClaims,
+) -> Result<User, AppError> {
+    if let Some(identity) = repo.find_user_identity(provider, &claims.sub).await? {
+        return repo
+            .find_user(&identity.user)
+            .await?
+            .ok_or_else(|| AppError::Internal("Linked user no longer exists".to_string()));
+    }
+
+    let email = match claims.email.as_deref().map(str::trim) {
+        Some(email) if claims.email_verified && !email.is_empty() => email,
+        _ => {
+            return Err(AppError::Authentication(
+                ErrorCode::EmailNotVerified,
+                "The sign-in provider has not verified your email address".to_string(),
+            ));
+        }
+    };
+
+    let user = match repo.find_user_by_email(email).await? {
+        // Whoever registered an unverified account may not own the address, and would keep
+        // signing in with their password after the link
+        Some(user) if !user.email_verified => {
+            return Err(AppError::Authorization(
+                ErrorCode::EmailNotVerified,
+                "An account with this email address exists but has not been verified. Verify it or sign in with your password first."
+                    .to_string(),
+            ));
+        }
+        Some(user) => user,
+        None => {
+            let name = claims
+                .name
+                .clone()
+                .filter(|name| !name.trim().is_empty())
+                .unwrap_or_else(|| email.split('@').next().unwrap_or_default().to_string());
+
+            let mut user = User::new(email.to_string(), name, UserRole::Student);
+            user.email_verified = true;
+            repo.create_user(user).await?
+        }
+    };
+
+    let Some(user_id) = user.id.clone() else {
+        return Err(AppError::Internal("User record has no ID".to_string()));
+    };
+    repo.create_user_identity(UserIdentity::new(user_id.clone(), provider, &claims.sub, email))
+        .await?;
+    info!("Linked {} account {} to {}", provider, claims.sub, user_id);
+
+    Ok(user)
+}
+
+/// Accept `email_verified` as a boolean or as the string `"true"`, which some providers send
+fn bool_or_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Flag {
+        Bool(bool),
+        String(String),
+    }
+
+    Ok(match Flag::deserialize(deserializer)? {
+        Flag::Bool(flag) => flag,
+        Flag::String(flag) => flag.eq_ignore_ascii_case("true"),
+    })
+}
+
+/// A local OpenID provider for tests
+#[cfg(test)]
+pub(crate) mod mock {
+    use std::collections::HashMap;
+    use std::convert::Infallible;
+    use std::sync::{Arc, Mutex};
+
+    use http_body_util::{BodyExt, Full};
+    use hyper::body::{Bytes, Incoming};
+    use hyper::server::conn::http1;
+    use hyper::service::service_fn;
+    use hyper::{Request, Response};
+    use hyper_util::rt::TokioIo;
+    use jsonwebtoken::{encode, Header};
+    use ring::rand::SystemRandom;
+    use ring::signature::Ed25519KeyPair;
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::common::config::Config;
+    use crate::common::keys::KeyRing;
+
+    pub const CLIENT_ID: &str = "kaiju";
+    pub const CLIENT_SECRET: &str = "mock-secret";
+    pub const REDIRECT_URI: &str = "http://localhost:5173/oidc/callback";
+
+    /// Codes issued by [`MockProvider::approve`], with the PKCE challenge and claims for each
+    type Codes = Arc<Mutex<HashMap<String, (String, Value)>>>;
+
+    /// Serves discovery, JWKS and a token endpoint that checks PKCE and client credentials
+    pub struct MockProvider {
+        pub issuer: String,
+        keys: Arc<KeyRing>,
+        codes: Codes,
+    }
+
+    impl MockProvider {
+        pub async fn start() -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let issuer = format!("http://{}", listener.local_addr().unwrap());
+
+            let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+            let keys = Arc::new(
+                KeyRing::from_config(&Config {
+                    jwt_signing_key: pem::encode(&pem::Pem::new("PRIVATE KEY", pkcs8.as_ref())),
+                    jwt_key_id: "mock-key".to_string(),
+                    ..Config::default()
+                })
+                .unwrap(),
+            );
+            let codes = Codes::default();
+
+            let (server_issuer, server_keys, server_codes) = (issuer.clone(), keys.clone(), codes.clone());
+            tokio::spawn(async move {
+                loop {
+                    let (stream, _) = listener.accept().await.unwrap();
+                    let (issuer, keys, codes) = (server_issuer.clone(), server_keys.clone(), server_codes.clone());
+                    let service = service_fn(move |request: Request<Incoming>| {
+                        let (issuer, keys, codes) = (issuer.clone(), keys.clone(), codes.clone());
+                        async move { Ok::<_, Infallible>(respond(request, &issuer, &keys, &codes).await) }
+                    });
+                    tokio::spawn(http1::Builder::new().serve_connection(TokioIo::new(stream), service));
+                }
+            });
+
+            Self { issuer, keys, codes }
+        }
+
+        /// Provider settings pointing at the mock
+        pub fn config(&self, name: &str) -> OidcProviderConfig {
+            OidcProviderConfig {
+                name: name.to_string(),
+                issuer: self.issuer.clone(),
+                client_id: CLIENT_ID.to_string(),
+                client_secret: Some(CLIENT_SECRET.to_string()),
+                redirect_uri: REDIRECT_URI.to_string(),
+                scopes: "openid email profile".to_string(),
+            }
+        }
+
+        /// Act as the user approving an authorization request, returning the code the
+        /// provider would redirect back with. `claims` are added to, or override, the
+        /// standard ID token claims.
+        pub fn approve(&self, authorization_url: &str, claims: Value) -> String {
+            let url = Url::parse(authorization_url).unwrap();
+            let param = |name: &str| url.query_pairs().find(|(key, _)| key == name).unwrap().1.into_owned();
+            assert_eq!(param("code_challenge_method"), "S256");
+
+            let now = Utc::now().timestamp();
+            let mut id_token = json!({
+                "iss": self.issuer,
+                "aud": CLIENT_ID,
+                "iat": now,
+                "exp": now + 300,
+                "nonce": param("nonce"),
+            });
+            for (key, value) in claims.as_object().unwrap() {
+                id_token[key] = value.clone();
+            }
+
+            let code = auth::generate_opaque_token();
+            self.codes
+                .lock()
+                .unwrap()
+                .insert(code.clone(), (param("code_challenge"), id_token));
+            code
+        }
+
+        /// Sign claims with the provider's key
+        pub fn sign(&self, claims: &Value) -> String {
+            sign(&self.keys, claims)
+        }
+
+        /// The provider's public keys
+        pub fn jwks(&self) -> JwkSet {
+            self.keys.jwks().clone()
+        }
+    }
+
+    fn sign(keys: &KeyRing, claims: &Value) -> String {
+        let signing = keys.signing_key();
+        let mut header = Header::new(signing.algorithm);
+        header.kid = Some(signing.kid.clone());
+        encode(&header, claims, &signing.key).unwrap()
+    }
+
+    async fn respond(request: Request<Incoming>, issuer: &str, keys: &KeyRing, codes: &Codes) -> Response<Full<Bytes>> {
+        let path = request.uri().path().to_string();
+        let authorization = request
+            .headers()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let body = request.into_body().collect().await.unwrap().to_bytes();
+
+        let (status, body) = match path.as_str() {
+            // Served under any prefix, so tests can point a client at the wrong issuer
+            discovery if discovery.ends_with("/.well-known/openid-configuration") => (
+                200,
+                json!({
+                    "issuer": issuer,
+                    "authorization_endpoint": format!("{}/authorize", issuer),
+                    "token_endpoint": format!("{}/token", issuer),
+                    "jwks_uri": format!("{}/jwks", issuer),
+                }),
+            ),
+            "/jwks" => (200, serde_json::to_value(keys.jwks()).unwrap()),
+            "/token" => {
+                let form: HashMap<String, String> = Url::parse(&format!("http://form/?{}", String::from_utf8_lossy(&body)))
+                    .unwrap()
+                    .query_pairs()
+                    .into_owned()
+                    .collect();
+                let expected_auth = format!(
+                    "Basic {}",
+                    base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", CLIENT_ID, CLIENT_SECRET))
+                );
+                let issued = form.get("code").and_then(|code| codes.lock().unwrap().remove(code));
+
+                match issued {
+                    Some((challenge, claims))
+                        if authorization.as_deref() == Some(expected_auth.as_str())
+                            && form.get("redirect_uri").map(String::as_str) == Some(REDIRECT_URI)
+                            && form.get("code_verifier").is_some_and(|verifier| {
+                                URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes())) == challenge
+                            }) =>
+                    {
+                        (200, json!({"access_token": "opaque", "token_type": "Bearer", "id_token": sign(keys, &claims)}))
+                    }
+                    _ => (400, json!({"error": "invalid_grant"})),
+                }
+            }
+            _ => (404, json!({})),
+        };
+
+        let mut response = Response::new(Full::new(Bytes::from(body.to_string())));
+        *response.status_mut() = hyper::StatusCode::from_u16(status).unwrap();
+        response
+            .headers_mut()
+            .insert("content-type", "application/json".parse().unwrap());
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::mock::{MockProvider, CLIENT_ID};
+    use super::*;
+
+    async fn start_sign_in(provider: &MockProvider) -> (OidcClient, ProviderMetadata, Authorization) {
+        let client = OidcClient::new(provider.config("mock")).unwrap();
+        let metadata = client.discover().await.unwrap();
+        let authorization = client.authorize(&metadata).unwrap();
+        (client, metadata, authorization)
+    }
+
+    #[tokio::test]
+    async fn codes_are_exchanged_for_verified_claims_once() {
+        let provider = MockProvider::start().await;
+        let (client, metadata, authorization) = start_sign_in(&provider).await;
+        assert!(authorization.url.starts_with(&format!("{}/authorize?", provider.issuer)));
+
+        let code = provider.approve(
+            &authorization.url,
+            json!({"sub": "248289761001", "email": "ada@example.com", "email_verified": true}),
+        );
+        let claims = client.exchange_code(&metadata, &code, &authorization.pending).await.unwrap();
+        assert_eq!(claims.sub, "248289761001");
+        assert_eq!(claims.email.as_deref(), Some("ada@example.com"));
+        assert!(claims.email_verified);
+
+        let replayed = client.exchange_code(&metadata, &code, &authorization.pending).await;
+        assert!(matches!(replayed, Err(AppError::Authentication(..))));
+    }
+
+    #[tokio::test]
+    async fn codes_need_the_matching_verifier() {
+        let provider = MockProvider::start().await;
+        let (client, metadata, authorization) = start_sign_in(&provider).await;
+        let code = provider.approve(&authorization.url, json!({"sub": "1"}));
+
+        let mut pending = authorization.pending.clone();
+        pending.code_verifier = auth::generate_opaque_token();
+        assert!(matches!(
+            client.exchange_code(&metadata, &code, &pending).await,
+            Err(AppError::Authentication(..))
+        ));
+    }
+
+    #[tokio::test]
+    async fn id_tokens_must_be_for_this_client_and_sign_in() {
+        let provider = MockProvider::start().await;
+        let (client, metadata, authorization) = start_sign_in(&provider).await;
+        let nonce = authorization.pending.nonce.clone();
+        let now = Utc::now().timestamp();
+        let claims = |extra: serde_json::Value| {
+            let mut claims = json!({"iss": provider.issuer, "aud": CLIENT_ID, "sub": "1", "exp": now + 300, "nonce": nonce});
+            for (key, value) in extra.as_object().unwrap() {
+                claims[key] = value.clone();
+            }
+            provider.sign(&claims)
+        };
+        let verify = |token: &str| client.verify_id_token(&metadata, &provider.jwks(), token, &nonce);
+
+        assert!(verify(&claims(json!({}))).is_ok());
+        assert!(verify(&claims(json!({"aud": "someone-else"}))).is_err());
+        assert!(verify(&claims(json!({"iss": "https://evil.example.com"}))).is_err());
+        assert!(verify(&claims(json!({"nonce": "replayed"}))).is_err());
+        assert!(verify(&claims(json!({"exp": now - 3600}))).is_err());
+
+        // Signed by a key the provider does not publish
+        let other = MockProvider::start().await;
+        let forged = other.sign(&json!({"iss": provider.issuer, "aud": CLIENT_ID, "sub": "1", "exp": now + 300, "nonce": nonce}));
+        assert!(verify(&forged).is_err());
+    }
+
+    #[tokio::test]
+    async fn discovery_must_name_the_configured_issuer() {
+        let provider = MockProvider::start().await;
+        let mut config = provider.config("mock");
+        config.issuer = format!("{}/tenant", provider.issuer);
+
+        let result = OidcClient::new(config).unwrap().discover().await;
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn email_verified_may_be_a_string() {
+        let claims: IdTokenClaims = serde_json::from_value(json!({"sub": "1", "email_verified": "true"})).unwrap();
+        assert!(claims.email_verified);
+
+        let claims: IdTokenClaims = serde_json::from_value(json!({"sub": "1"})).unwrap();
+        assert!(!claims.email_verified);
+    }
+}
@@ -0,0 +1,172 @@
+use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
+use chrono::{DateTime, Utc};
+use lambda_runtime::{Error, LambdaEvent};
+use serde_json::json;
+use std::str::FromStr;
+use surrealdb::sql::Thing;
+use tracing::error;
+
+use crate::common::error::{AppError, ErrorCode};
+use crate::common::response::json_response;
+use crate::repository::{self, AuditFilter, AuditRepository};
+
+/// Lambda handler for querying the audit log
+pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
+    // Connect to database
+    let repo = match repository::connect().await {
+        Ok(repo) => repo,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    handle(&repo, event.payload).await
+}
+
+/// List audit events, newest first, filtered by actor, target and time range.
+///
+/// The router only lets callers with `audit_event.read` through.
+pub async fn handle(repo: &impl AuditRepository, request: ApiGatewayProxyRequest) -> Result<ApiGatewayProxyResponse, Error> {
+    let filter = match parse_filter(&request) {
+        Ok(filter) => filter,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    let events = match repo.list_audit_events(&filter).await {
+        Ok(events) => events,
+        Err(err) => {
+            error!("Failed to list audit events: {}", err);
+            return Ok(err.into());
+        }
+    };
+
+    // Create successful response
+    let response_body = json!({
+        "events": events,
+        "limit": filter.limit,
+        "offset": filter.offset
+    });
+
+    Ok(json_response(200, response_body))
+}
+
+/// Read the filter from the query string: `actor` (a user ID), `target` (any record ID such
+/// as `course:abc`), `since` and `until` (RFC 3339 times), `limit` and `offset`
+fn parse_filter(request: &ApiGatewayProxyRequest) -> Result<AuditFilter, AppError> {
+    let mut filter = AuditFilter::default();
+    let mut limit = None;
+    let mut offset = None;
+
+    // Handle query parameters
+    for (key, value) in request.query_string_parameters.iter() {
+        match key {
+            "actor" => filter.actor = Some(repository::record_id("user", value)?),
+            "target" => {
+                let target = Thing::from_str(value).map_err(|_| {
+                    AppError::invalid_field("target", ErrorCode::InvalidFormat, format!("Invalid target record ID: {}", value))
+                })?;
+                filter.target = Some(target);
+            }
+            "since" => filter.since = Some(parse_time("since", value)?),
+            "until" => filter.until = Some(parse_time("until", value)?),
+            "limit" => limit = value.parse::<usize>().ok(),
+            "offset" => offset = value.parse::<usize>().ok(),
+            _ => {}
+        }
+    }
+
+    filter.limit = limit.unwrap_or(50).min(200);
+    filter.offset = offset.unwrap_or(0);
+
+    Ok(filter)
+}
+
+fn parse_time(name: &str, value: &str) -> Result<DateTime<Utc>, AppError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|_| {
+            let message = format!("{} must be an RFC 3339 time, such as 2026-01-31T00:00:00Z", name);
+            AppError::invalid_field(name, ErrorCode::InvalidFormat, message)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::lambda::course::{create, delete, update};
+    use crate::models::audit::AuditAction;
+    use crate::models::user::UserRole;
+    use crate::repository::memory::connect;
+    use crate::testing::{request_as, response_json};
+
+    #[tokio::test]
+    async fn course_changes_are_audited() {
+        let repo = connect().await.unwrap();
+        let educator = Thing::from(("user", "educator"));
+        let mut request = request_as(&repo, &educator, UserRole::Educator).await;
+        request.request_context.request_id = Some("req-1".to_string());
+
+        let mut create = request.clone();
+        create.body = Some(r#"{"title":"Rust 101","description":"Ownership","difficulty":"beginner","category":"rust","thumbnail_url":"","modules":[],"tags":[]}"#.to_string());
+        let response = create::handle(&repo, create).await.unwrap();
+        let id = response_json(&response)["course"]["id"].clone();
+        let id: Thing = serde_json::from_value(id).unwrap();
+
+        for body in [r#"{"is_published":true}"#, r#"{"is_published":false}"#, r#"{"title":"Rust 102"}"#] {
+            let mut update = request.clone();
+            update.path_parameters.insert("id".to_string(), id.to_string());
+            update.body = Some(body.to_string());
+            assert_eq!(update::handle(&repo, update).await.unwrap().status_code, 200);
+        }
+
+        let mut delete = request.clone();
+        delete.path_parameters.insert("id".to_string(), id.to_string());
+        assert_eq!(delete::handle(&repo, delete).await.unwrap().status_code, 200);
+
+        let filter = AuditFilter { target: Some(id.clone()), limit: 10, ..Default::default() };
+        let mut events = repo.list_audit_events(&filter).await.unwrap();
+        events.sort_by_key(|event| event.action != AuditAction::CourseCreate);
+        assert_eq!(events.len(), 5);
+        assert!(events.iter().all(|event| event.actor == educator && event.request_id.as_deref() == Some("req-1")));
+        assert_eq!(events[0].action, AuditAction::CourseCreate);
+        assert!(events[0].before.is_none());
+
+        // The publish event only carries the fields that changed
+        let publish = events.iter().find(|event| event.action == AuditAction::CoursePublish).unwrap();
+        assert_eq!(publish.before, Some(serde_json::json!({"is_published": false})));
+        assert_eq!(publish.after, Some(serde_json::json!({"is_published": true})));
+
+        let unpublish = events.iter().find(|event| event.action == AuditAction::CourseUnpublish).unwrap();
+        assert_eq!(unpublish.before, Some(serde_json::json!({"is_published": true})));
+        assert_eq!(unpublish.after, Some(serde_json::json!({"is_published": false})));
+
+        let update = events.iter().find(|event| event.action == AuditAction::CourseUpdate).unwrap();
+        assert_eq!(update.before, Some(serde_json::json!({"title": "Rust 101"})));
+        assert_eq!(update.after, Some(serde_json::json!({"title": "Rust 102"})));
+
+        let delete = events.iter().find(|event| event.action == AuditAction::CourseDelete).unwrap();
+        assert!(delete.after.is_none());
+        assert_eq!(delete.before.as_ref().unwrap()["title"], "Rust 102");
+
+        // Admins query the log over HTTP, filtered by actor and time range
+        let query = |params: &[(&str, &str)]| {
+            let params: HashMap<String, String> =
+                params.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+            ApiGatewayProxyRequest { query_string_parameters: params.into(), ..Default::default() }
+        };
+        let actor = educator.to_string();
+
+        let response = handle(&repo, query(&[("actor", &actor), ("until", "2000-01-01T00:00:00Z")])).await.unwrap();
+        assert!(response_json(&response)["events"].as_array().unwrap().is_empty());
+
+        let response = handle(&repo, query(&[("actor", &actor)])).await.unwrap();
+        assert_eq!(response_json(&response)["events"].as_array().unwrap().len(), 5);
+
+        let response = handle(&repo, query(&[("since", "yesterday")])).await.unwrap();
+        assert_eq!(response.status_code, 400);
+    }
+}
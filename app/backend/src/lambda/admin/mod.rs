@@ -0,0 +1,3 @@
+pub mod audit_events;
+pub mod security_events;
+pub mod unlock;
@@ -0,0 +1,57 @@
+use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
+use lambda_runtime::{Error, LambdaEvent};
+use serde_json::json;
+use tracing::error;
+
+use crate::common::response::json_response;
+use crate::repository::{self, SecurityRepository};
+
+/// Lambda handler for listing security events
+pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
+    // Connect to database
+    let repo = match repository::connect().await {
+        Ok(repo) => repo,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    handle(&repo, event.payload).await
+}
+
+/// List security events, newest first, using the given repository.
+///
+/// The router only lets admins through.
+pub async fn handle(repo: &impl SecurityRepository, request: ApiGatewayProxyRequest) -> Result<ApiGatewayProxyResponse, Error> {
+    let mut limit = None;
+    let mut offset = None;
+
+    // Handle query parameters
+    for (key, value) in request.query_string_parameters.iter() {
+        match key {
+            "limit" => limit = value.parse::<usize>().ok(),
+            "offset" => offset = value.parse::<usize>().ok(),
+            _ => {}
+        }
+    }
+
+    let limit = limit.unwrap_or(50).min(200);
+    let offset = offset.unwrap_or(0);
+
+    let events = match repo.list_security_events(limit, offset).await {
+        Ok(events) => events,
+        Err(err) => {
+            error!("Failed to list security events: {}", err);
+            return Ok(err.into());
+        }
+    };
+
+    // Create successful response
+    let response_body = json!({
+        "events": events,
+        "limit": limit,
+        "offset": offset
+    });
+
+    Ok(json_response(200, response_body))
+}
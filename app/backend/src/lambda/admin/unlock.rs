@@ -0,0 +1,110 @@
+use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
+use chrono::Utc;
+use lambda_runtime::{Error, LambdaEvent};
+use serde_json::json;
+use tracing::{error, info};
+
+use crate::common::error::{AppError, ErrorCode, FieldError};
+use crate::common::extract::AuthContext;
+use crate::common::response::json_response;
+use crate::common::throttle;
+use crate::models::security::{SecurityEvent, SecurityEventKind, UnlockRequest};
+use crate::repository::{self, SecurityRepository};
+
+/// Lambda handler for lifting a login lockout
+pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
+    // Connect to database
+    let repo = match repository::connect().await {
+        Ok(repo) => repo,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    handle(&repo, event.payload).await
+}
+
+/// Clear the failed logins for an account, an IP or both, using the given repository.
+///
+/// The router only lets admins through. Lifting an active block is written to the
+/// security event log.
+pub async fn handle(repo: &impl SecurityRepository, request: ApiGatewayProxyRequest) -> Result<ApiGatewayProxyResponse, Error> {
+    // Authenticate the caller
+    let auth = match AuthContext::from_request(&request) {
+        Ok(auth) => auth,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    // Parse request body
+    let unlock_request = match request.body {
+        Some(body) => match serde_json::from_str::<UnlockRequest>(&body) {
+            Ok(req) => req,
+            Err(err) => {
+                error!("Failed to parse unlock request: {}", err);
+                return Ok(AppError::Validation(ErrorCode::InvalidBody, format!("Invalid request format: {}", err)).into());
+            }
+        },
+        None => {
+            return Ok(AppError::Validation(ErrorCode::MissingBody, "Missing request body".to_string()).into());
+        }
+    };
+
+    let keys: Vec<String> = unlock_request
+        .email
+        .iter()
+        .filter(|email| !email.is_empty())
+        .map(|email| throttle::account_key(email))
+        .chain(unlock_request.ip.iter().filter(|ip| !ip.is_empty()).map(|ip| throttle::ip_key(ip)))
+        .collect();
+
+    if keys.is_empty() {
+        return Ok(AppError::InvalidFields(vec![
+            FieldError::required("email", "Email or IP is required"),
+            FieldError::required("ip", "Email or IP is required"),
+        ])
+        .into());
+    }
+
+    let source_ip = request.request_context.identity.source_ip.clone();
+    let now = Utc::now();
+    let mut unlocked = Vec::new();
+
+    for key in keys {
+        let removed = match repo.clear_login_failures(&key).await {
+            Ok(removed) => removed,
+            Err(err) => {
+                error!("Failed to clear login failures for {}: {}", key, err);
+                return Ok(err.into());
+            }
+        };
+
+        // Only lifting a block that was still in force is worth an event
+        if let Some(throttle) = removed.filter(|throttle| throttle.locked || throttle.blocked_until > now) {
+            let mut event = SecurityEvent::new(
+                SecurityEventKind::LoginUnlock,
+                &key,
+                format!("Unlocked by an admin after {} failed logins", throttle.failures),
+            );
+            event.actor = Some(auth.user.clone());
+            event.ip = source_ip.clone();
+
+            if let Err(err) = repo.record_security_event(event).await {
+                error!("Failed to record unlock of {}: {}", key, err);
+                return Ok(err.into());
+            }
+
+            info!("{} unlocked logins for {}", auth.user, key);
+            unlocked.push(key);
+        }
+    }
+
+    // Create successful response
+    let response_body = json!({
+        "message": "Failed logins cleared",
+        "unlocked": unlocked
+    });
+
+    Ok(json_response(200, response_body))
+}
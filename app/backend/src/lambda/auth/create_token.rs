@@ -0,0 +1,171 @@
+use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
+use chrono::{Duration, Utc};
+use lambda_runtime::{Error, LambdaEvent};
+use serde_json::json;
+use tracing::{error, info};
+
+use crate::common::auth;
+use crate::common::config::CONFIG;
+use crate::common::error::{AppError, ErrorCode, FieldError};
+use crate::common::extract::AuthContext;
+use crate::common::response::json_response;
+use crate::models::token::{CreatePersonalTokenRequest, PersonalToken, PersonalTokenResponse};
+use crate::repository::{self, TokenRepository};
+
+/// Days a personal access token lasts when the request does not say
+const DEFAULT_EXPIRY_DAYS: u64 = 30;
+
+/// Longest accepted token name
+const MAX_NAME_LENGTH: usize = 100;
+
+/// Lambda handler for creating a personal access token
+pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
+    // Connect to database
+    let repo = match repository::connect().await {
+        Ok(repo) => repo,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    handle(&repo, event.payload).await
+}
+
+/// Create a personal access token for the caller.
+///
+/// The token is only returned here; afterwards just its hash is kept.
+pub async fn handle(repo: &impl TokenRepository, request: ApiGatewayProxyRequest) -> Result<ApiGatewayProxyResponse, Error> {
+    // Authenticate the caller
+    let auth = match AuthContext::from_request(&request) {
+        Ok(auth) => auth,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    // Parse request body
+    let create_request = match &request.body {
+        Some(body) => match serde_json::from_str::<CreatePersonalTokenRequest>(body) {
+            Ok(req) => req,
+            Err(err) => {
+                error!("Failed to parse token creation request: {}", err);
+                return Ok(AppError::Validation(ErrorCode::InvalidBody, format!("Invalid request format: {}", err)).into());
+            }
+        },
+        None => {
+            return Ok(AppError::Validation(ErrorCode::MissingBody, "Missing request body".to_string()).into());
+        }
+    };
+
+    // Validate request
+    let name = create_request.name.trim().to_string();
+    if name.is_empty() || name.len() > MAX_NAME_LENGTH {
+        let code = if name.is_empty() { ErrorCode::Required } else { ErrorCode::TooLong };
+        let message = format!("Token name must be 1 to {} characters", MAX_NAME_LENGTH);
+        return Ok(AppError::invalid_field("name", code, message).into());
+    }
+
+    let mut scopes = Vec::new();
+    for scope in create_request.scopes {
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+    if scopes.is_empty() {
+        return Ok(AppError::InvalidFields(vec![FieldError::required("scopes", "At least one scope is required")]).into());
+    }
+
+    let max_days = CONFIG.personal_token_max_expiry_days;
+    let expiry_days = create_request.expires_in_days.unwrap_or(DEFAULT_EXPIRY_DAYS.min(max_days));
+    if expiry_days == 0 || expiry_days > max_days {
+        let message = format!("Tokens must expire within 1 to {} days", max_days);
+        return Ok(AppError::invalid_field("expires_in_days", ErrorCode::OutOfRange, message).into());
+    }
+
+    let token = auth::generate_personal_token();
+    let expires_at = Utc::now() + Duration::days(expiry_days as i64);
+    let record = PersonalToken::new(auth::hash_opaque_token(&token), auth.user.clone(), name, scopes, expires_at);
+
+    let created = match repo.create_personal_token(record).await {
+        Ok(created) => created,
+        Err(err) => {
+            error!("Failed to store personal access token: {}", err);
+            return Ok(err.into());
+        }
+    };
+    info!("Created personal access token {:?} for {}", created.id, auth.user);
+
+    let response_body = json!({
+        "token": token,
+        "personal_token": PersonalTokenResponse::from(created)
+    });
+
+    Ok(json_response(201, response_body))
+}
+
+#[cfg(test)]
+mod tests {
+    use surrealdb::sql::Thing;
+
+    use super::*;
+    use crate::lambda::auth::{list_tokens, revoke_token};
+    use crate::models::token::TokenScope;
+    use crate::models::user::{User, UserRole};
+    use crate::repository::memory::connect;
+    use crate::repository::UserRepository;
+    use crate::testing::{request_as, response_json};
+
+    #[tokio::test]
+    async fn personal_access_tokens_are_scoped_and_revocable() {
+        let repo = connect().await.unwrap();
+        let user = User::new("ada@example.com".to_string(), "Ada".to_string(), UserRole::Educator);
+        let id = repo.create_user(user).await.unwrap().id.unwrap();
+        let session = request_as(&repo, &id, UserRole::Educator).await;
+
+        let mut request = session.clone();
+        request.body = Some(serde_json::json!({"name": "ci", "scopes": ["courses:read"], "expires_in_days": 7}).to_string());
+        let response = handle(&repo, request).await.unwrap();
+        assert_eq!(response.status_code, 201);
+        let created = response_json(&response);
+        let token = created["token"].as_str().unwrap().to_string();
+        assert!(auth::is_personal_token(&token));
+
+        let mut request = session.clone();
+        request.body = Some(serde_json::json!({"name": "ci", "scopes": [], "expires_in_days": 7}).to_string());
+        let response = handle(&repo, request).await.unwrap();
+        assert_eq!(response.status_code, 400);
+
+        // The token authenticates as its owner, but only within its scopes
+        let mut pat_request = ApiGatewayProxyRequest::default();
+        pat_request
+            .headers
+            .insert("Authorization", format!("Bearer {}", token).parse().unwrap());
+        let context = AuthContext::authenticate(&repo, &pat_request).await.unwrap().unwrap();
+        assert_eq!((&context.user, &context.role), (&id, &UserRole::Educator));
+        assert!(context.require_scope(Some(TokenScope::CoursesRead)).is_ok());
+        assert!(context.require_scope(Some(TokenScope::CoursesWrite)).is_err());
+        assert!(context.require_scope(None).is_err());
+
+        // Listing shows the token without its value, along with its last use
+        let response = list_tokens::handle(&repo, session.clone()).await.unwrap();
+        let listed = response_json(&response)["tokens"].clone();
+        assert_eq!(listed.as_array().unwrap().len(), 1);
+        assert_eq!(listed[0]["name"], "ci");
+        assert!(listed[0]["last_used_at"].is_number());
+        assert!(listed[0].get("token_hash").is_none());
+
+        // Other users cannot revoke it
+        let token_id = created["personal_token"]["id"].as_str().unwrap().to_string();
+        let mut request = request_as(&repo, &Thing::from(("user", "grace")), UserRole::Admin).await;
+        request.path_parameters.insert("id".to_string(), token_id.clone());
+        let response = revoke_token::handle(&repo, request).await.unwrap();
+        assert_eq!(response.status_code, 404);
+
+        let mut request = session.clone();
+        request.path_parameters.insert("id".to_string(), token_id);
+        let response = revoke_token::handle(&repo, request).await.unwrap();
+        assert_eq!(response.status_code, 200);
+        assert!(AuthContext::authenticate(&repo, &pat_request).await.is_err());
+        assert!(repo.list_personal_tokens(&id).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,66 @@
+use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
+use lambda_runtime::{Error, LambdaEvent};
+use serde_json::json;
+use tracing::error;
+
+use crate::common::error::{AppError, ErrorCode, FieldError};
+use crate::common::response::json_response;
+use crate::identity::{self, IdentityProvider};
+use crate::models::token::ForgotPasswordRequest;
+use crate::repository;
+
+/// Lambda handler for requesting a password reset email
+pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
+    // Connect to database
+    let repo = match repository::connect().await {
+        Ok(repo) => repo,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    let identity = match identity::connect(repo).await {
+        Ok(identity) => identity,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    handle(identity.as_ref(), event.payload).await
+}
+
+/// Send a password reset link or code with the given identity provider.
+///
+/// The response is the same whether or not the email belongs to an account, so the
+/// endpoint cannot be used to discover registered addresses.
+pub async fn handle(identity: &dyn IdentityProvider, request: ApiGatewayProxyRequest) -> Result<ApiGatewayProxyResponse, Error> {
+    // Parse request body
+    let forgot_request = match request.body {
+        Some(body) => match serde_json::from_str::<ForgotPasswordRequest>(&body) {
+            Ok(req) => req,
+            Err(err) => {
+                error!("Failed to parse forgot password request: {}", err);
+                return Ok(AppError::Validation(ErrorCode::InvalidBody, format!("Invalid request format: {}", err)).into());
+            }
+        },
+        None => {
+            return Ok(AppError::Validation(ErrorCode::MissingBody, "Missing request body".to_string()).into());
+        }
+    };
+
+    if forgot_request.email.is_empty() {
+        return Ok(AppError::InvalidFields(vec![FieldError::required("email", "Email is required")]).into());
+    }
+
+    if let Err(err) = identity.forgot_password(&forgot_request.email).await {
+        error!("Password reset request failed: {}", err);
+        return Ok(err.into());
+    }
+
+    // Create accepted response
+    let response_body = json!({
+        "message": "If an account exists for this email, a password reset link has been sent"
+    });
+
+    Ok(json_response(202, response_body))
+}
@@ -0,0 +1,22 @@
+use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
+use lambda_runtime::{Error, LambdaEvent};
+
+use crate::common::error::AppError;
+use crate::common::keys::KEYS;
+use crate::common::response::json_response;
+
+/// Lambda handler publishing the public keys that verify Kaiju access tokens
+pub async fn handler(_event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
+    let body = match serde_json::to_value(KEYS.jwks()) {
+        Ok(body) => body,
+        Err(err) => {
+            return Ok(AppError::Internal(format!("Failed to serialize JWKS: {}", err)).into());
+        }
+    };
+
+    let mut response = json_response(200, body);
+    // Verifiers may cache keys briefly; rotation keeps the old key published for longer than this
+    response.headers.insert("Cache-Control", "public, max-age=300".parse().unwrap());
+
+    Ok(response)
+}
@@ -0,0 +1,120 @@
+use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
+use chrono::Utc;
+use lambda_runtime::{Error, LambdaEvent};
+use serde_json::json;
+use tracing::error;
+
+use crate::common::extract::AuthContext;
+use crate::common::response::json_response;
+use crate::models::session::SessionResponse;
+use crate::repository::{self, TokenRepository};
+
+/// Lambda handler for listing signed-in sessions
+pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
+    // Connect to database
+    let repo = match repository::connect().await {
+        Ok(repo) => repo,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    handle(&repo, event.payload).await
+}
+
+/// List the caller's active sessions, marking the one making the request
+pub async fn handle(repo: &impl TokenRepository, request: ApiGatewayProxyRequest) -> Result<ApiGatewayProxyResponse, Error> {
+    // Authenticate the caller
+    let auth = match AuthContext::from_request(&request) {
+        Ok(auth) => auth,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    let sessions = match repo.list_sessions(&auth.user, Utc::now()).await {
+        Ok(sessions) => sessions,
+        Err(err) => {
+            error!("Database error while listing sessions: {}", err);
+            return Ok(err.into());
+        }
+    };
+
+    let current = auth.token.session.as_deref();
+    let sessions: Vec<SessionResponse> = sessions
+        .into_iter()
+        .map(|session| SessionResponse::new(session, current))
+        .collect();
+    let response_body = json!({
+        "sessions": sessions
+    });
+
+    Ok(json_response(200, response_body))
+}
+
+#[cfg(test)]
+mod tests {
+    use surrealdb::sql::Thing;
+
+    use super::*;
+    use crate::common::auth;
+    use crate::lambda::auth::{logout, refresh, revoke_session};
+    use crate::models::session::ClientInfo;
+    use crate::models::user::{User, UserRole};
+    use crate::repository::memory::connect;
+    use crate::repository::UserRepository;
+    use crate::testing::{json_request, request_as, request_with, response_json};
+
+    #[tokio::test]
+    async fn sessions_can_be_listed_and_signed_out_remotely() {
+        let repo = connect().await.unwrap();
+        let user = User::new("ada@example.com".to_string(), "Ada".to_string(), UserRole::Student);
+        let id = repo.create_user(user).await.unwrap().id.unwrap();
+
+        let lab_machine = ClientInfo {
+            user_agent: Some("Mozilla/5.0 (X11; Linux x86_64; rv:127.0) Gecko/20100101 Firefox/127.0".to_string()),
+            ip_address: Some("10.0.0.7".to_string()),
+        };
+        let lab = auth::open_session(&repo, &id, "student", &lab_machine).await.unwrap();
+        let phone = auth::open_session(&repo, &id, "student", &ClientInfo::default()).await.unwrap();
+        let phone_request = request_with(&repo, &phone.token).await;
+
+        let response = handle(&repo, phone_request.clone()).await.unwrap();
+        let sessions = response_json(&response)["sessions"].as_array().unwrap().clone();
+        assert_eq!(sessions.len(), 2);
+        let lab_session = sessions.iter().find(|session| session["ip_address"] == "10.0.0.7").unwrap();
+        assert_eq!(lab_session["device"], "Firefox on Linux");
+        assert_eq!(lab_session["current"], false);
+        assert_eq!(sessions.iter().filter(|session| session["current"] == true).count(), 1);
+        let lab_id = lab_session["id"].as_str().unwrap().to_string();
+
+        // Other users cannot sign it out
+        let mut request = request_as(&repo, &Thing::from(("user", "grace")), UserRole::Student).await;
+        request.path_parameters.insert("id".to_string(), lab_id.clone());
+        let response = revoke_session::handle(&repo, request).await.unwrap();
+        assert_eq!(response.status_code, 404);
+
+        // Signing it out from the phone ends both of its tokens at once
+        let mut request = phone_request.clone();
+        request.path_parameters.insert("id".to_string(), lab_id);
+        let response = revoke_session::handle(&repo, request).await.unwrap();
+        assert_eq!(response.status_code, 200);
+
+        assert!(auth::validate_token(&repo, &lab.token).await.is_err());
+        let refresh = json_request(serde_json::json!({"refresh_token": lab.refresh_token}));
+        let response = refresh::handle(&repo, refresh).await.unwrap();
+        assert_eq!(response.status_code, 401);
+
+        // The phone stays signed in, and refreshing keeps its session
+        let refresh = json_request(serde_json::json!({"refresh_token": phone.refresh_token}));
+        let response = refresh::handle(&repo, refresh).await.unwrap();
+        assert_eq!(response.status_code, 200);
+        assert!(auth::validate_token(&repo, response_json(&response)["token"].as_str().unwrap()).await.is_ok());
+        assert_eq!(repo.list_sessions(&id, chrono::Utc::now()).await.unwrap().len(), 1);
+
+        // Logging out ends the current session
+        let response = logout::handle(&repo, phone_request).await.unwrap();
+        assert_eq!(response.status_code, 200);
+        assert!(repo.list_sessions(&id, chrono::Utc::now()).await.unwrap().is_empty());
+    }
+}
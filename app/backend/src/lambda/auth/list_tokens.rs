@@ -0,0 +1,48 @@
+use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
+use lambda_runtime::{Error, LambdaEvent};
+use serde_json::json;
+use tracing::error;
+
+use crate::common::extract::AuthContext;
+use crate::common::response::json_response;
+use crate::models::token::PersonalTokenResponse;
+use crate::repository::{self, TokenRepository};
+
+/// Lambda handler for listing personal access tokens
+pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
+    // Connect to database
+    let repo = match repository::connect().await {
+        Ok(repo) => repo,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    handle(&repo, event.payload).await
+}
+
+/// List the caller's unrevoked personal access tokens, including expired ones
+pub async fn handle(repo: &impl TokenRepository, request: ApiGatewayProxyRequest) -> Result<ApiGatewayProxyResponse, Error> {
+    // Authenticate the caller
+    let auth = match AuthContext::from_request(&request) {
+        Ok(auth) => auth,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    let tokens = match repo.list_personal_tokens(&auth.user).await {
+        Ok(tokens) => tokens,
+        Err(err) => {
+            error!("Database error while listing personal access tokens: {}", err);
+            return Ok(err.into());
+        }
+    };
+
+    let tokens: Vec<PersonalTokenResponse> = tokens.into_iter().map(PersonalTokenResponse::from).collect();
+    let response_body = json!({
+        "tokens": tokens
+    });
+
+    Ok(json_response(200, response_body))
+}
@@ -1,20 +1,51 @@
 use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
-use aws_lambda_events::encodings::Body;
-use http::HeaderMap;
+use chrono::Utc;
 use lambda_runtime::{Error, LambdaEvent};
-use serde_json::json;
-use std::collections::HashMap;
-use tracing::{error, info};
-
-use argon2::password_hash::PasswordVerifier;
-use crate::common::auth;
-use crate::common::db;
-use crate::common::error::AppError;
+use serde_json::{json, Value};
+use tracing::error;
+
+use crate::common::auth::{self, MfaStep};
+use crate::common::config::CONFIG;
+use crate::common::error::{AppError, ErrorCode, FieldError};
+use crate::common::extract;
+use crate::common::mfa;
+use crate::common::response::json_response;
+use crate::common::throttle::{self, LoginAttempt};
+use crate::identity::{self, IdentityProvider};
+use crate::models::session::ClientInfo;
 use crate::models::user::{User, UserLoginRequest, UserResponse};
+use crate::repository::{self, MfaRepository, SecurityRepository, TokenRepository, UserRepository};
 
 /// Lambda handler for user login
 pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
-    let request = event.payload;
+    // Connect to database
+    let repo = match repository::connect().await {
+        Ok(repo) => repo,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    let identity = match identity::connect(repo.clone()).await {
+        Ok(identity) => identity,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    handle(&repo, identity.as_ref(), event.payload).await
+}
+
+/// Log a user in with the given identity provider, starting a session in the repository.
+///
+/// Repeated failures for an account or source IP are throttled; see `common::throttle`.
+/// Users with MFA get an MFA-pending token instead of a session; see `common::mfa`.
+pub async fn handle(
+    repo: &(impl UserRepository + TokenRepository + SecurityRepository + MfaRepository),
+    identity: &dyn IdentityProvider,
+    request: ApiGatewayProxyRequest,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let client = extract::client_info(&request);
 
     // Parse request body
     let login_request = match request.body {
@@ -22,113 +53,219 @@ pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGa
             Ok(req) => req,
             Err(err) => {
                 error!("Failed to parse login request: {}", err);
-                return Ok(AppError::Validation(format!("Invalid request format: {}", err)).into());
+                return Ok(AppError::Validation(ErrorCode::InvalidBody, format!("Invalid request format: {}", err)).into());
             }
         },
         None => {
-            return Ok(AppError::Validation("Missing request body".to_string()).into());
+            return Ok(AppError::Validation(ErrorCode::MissingBody, "Missing request body".to_string()).into());
         }
     };
 
     // Validate email and password
-    if login_request.email.is_empty() || login_request.password.is_empty() {
-        return Ok(AppError::Validation("Email and password are required".to_string()).into());
+    let mut invalid = Vec::new();
+    if login_request.email.is_empty() {
+        invalid.push(FieldError::required("email", "Email is required"));
+    }
+    if login_request.password.is_empty() {
+        invalid.push(FieldError::required("password", "Password is required"));
+    }
+    if !invalid.is_empty() {
+        return Ok(AppError::InvalidFields(invalid).into());
     }
 
-    // Connect to database
-    let db = match db::get_db_client().await {
-        Ok(client) => client,
-        Err(err) => {
-            error!("Failed to connect to database: {}", err);
-            return Ok(
-                AppError::Internal("Failed to connect to the database".to_string()).into(),
-            );
-        }
-    };
-
-    // Find user by email
-    let result: Result<Option<User>, _> = db
-        .query("SELECT * FROM user WHERE email = $email LIMIT 1")
-        .bind(("email", login_request.email.clone()))
-        .await
-        .and_then(|mut res| res.take::<Option<User>>(0));
-
-    let user = match result {
-        Ok(Some(user)) => user,
-        Ok(None) => {
-            return Ok(
-                AppError::Authentication("Invalid email or password".to_string()).into(),
-            );
+    // Refuse the attempt outright while the account or IP is backed off or locked
+    let attempt = LoginAttempt::new(&login_request.email, client.ip_address.as_deref());
+    match throttle::retry_after(repo, &attempt, Utc::now()).await {
+        Ok(Some(retry_after)) => {
+            return Ok(too_many_attempts(retry_after));
         }
+        Ok(None) => {}
         Err(err) => {
-            error!("Database error during login: {}", err);
-            return Ok(AppError::Database(err).into());
-        }
-    };
-
-    // Verify password
-    let stored_password = match user.password {
-        Some(ref pw) => pw,
-        None => {
-            return Ok(
-                AppError::Authentication("Invalid email or password".to_string()).into(),
-            );
+            error!("Failed to check login throttle: {}", err);
+            return Ok(err.into());
         }
-    };
+    }
 
-    // Use the Argon2 crate to verify the password
-    // In Argon2 0.5+, we need to use the password-hash crate's functions
-    let is_valid = match argon2::Argon2::default().verify_password(
-        login_request.password.as_bytes(),
-        &argon2::password_hash::PasswordHash::new(stored_password).unwrap()
-    ) {
-        Ok(_) => true,
+    // Check the credentials with the identity provider
+    let user = match identity.sign_in(&login_request.email, &login_request.password).await {
+        Ok(user) => user,
         Err(err) => {
-            error!("Error verifying password: {}", err);
-            return Ok(
-                AppError::Authentication("Failed to verify password".to_string()).into(),
-            );
+            error!("Sign-in failed for {}: {}", login_request.email, err);
+            if matches!(err, AppError::Authentication(..))
+                && let Err(throttle_err) = throttle::record_failure(repo, &attempt, Utc::now()).await
+            {
+                error!("Failed to record login failure: {}", throttle_err);
+            }
+            return Ok(err.into());
         }
     };
 
-    if !is_valid {
+    if let Err(err) = throttle::record_success(repo, &attempt).await {
+        error!("Failed to clear login failures: {}", err);
+    }
+
+    // Optionally refuse accounts that have not confirmed their email address
+    if CONFIG.require_email_verification && !user.email_verified {
         return Ok(
-            AppError::Authentication("Invalid email or password".to_string()).into(),
+            AppError::Authorization(ErrorCode::EmailNotVerified, "Email address has not been verified".to_string()).into(),
         );
     }
 
-    // Generate JWT token
-    let user_id = user.id.as_ref().map(|id| id.to_string()).unwrap_or_default();
-    let token = match auth::generate_token(&user_id, &user.role.to_string()) {
-        Ok(token) => token,
+    // Users with an authenticator, or whose role requires one, have a second step to complete
+    let response_body = match mfa::pending_step(repo, &user).await {
+        Ok(Some(step)) => match mfa_challenge(&user, step) {
+            Ok(body) => body,
+            Err(err) => {
+                error!("Failed to issue MFA token: {}", err);
+                return Ok(err.into());
+            }
+        },
+        Ok(None) => match start_session(repo, user, &client).await {
+            Ok(body) => body,
+            Err(err) => {
+                error!("Failed to start session: {}", err);
+                return Ok(err.into());
+            }
+        },
         Err(err) => {
-            error!("Failed to generate token: {}", err);
+            error!("Failed to check MFA enrollment: {}", err);
             return Ok(err.into());
         }
     };
 
+    Ok(json_response(200, response_body))
+}
+
+/// Issue tokens for a user who has completed every login step, returning the response body
+pub async fn start_session(
+    repo: &(impl UserRepository + TokenRepository),
+    user: User,
+    client: &ClientInfo,
+) -> Result<Value, AppError> {
+    // Issue an access token and a refresh token for a new session
+    let Some(user_id) = user.id.clone() else {
+        return Err(AppError::Internal("User record has no ID".to_string()));
+    };
+    let tokens = auth::open_session(repo, &user_id, &user.role.to_string(), client).await?;
+
     // Update last login timestamp
-    let _: Result<(), _> = db
-        .query("UPDATE $user SET last_login = time::now()")
-        .bind(("user", user.id.clone()))
-        .await
-        .map(|_| ());
-
-    // Create successful response with token
-    let response_body = json!({
+    if let Err(err) = repo.touch_last_login(&user_id, Utc::now()).await {
+        error!("Failed to update last login: {}", err);
+    }
+
+    Ok(json!({
         "message": "Login successful",
-        "token": token,
+        "token": tokens.token,
+        "refresh_token": tokens.refresh_token,
+        "expires_in": tokens.expires_in,
         "user": UserResponse::from(user)
-    });
-
-    let mut headers = HeaderMap::new();
-    headers.insert("Content-Type", "application/json".parse().unwrap());
-
-    Ok(ApiGatewayProxyResponse {
-        status_code: 200,
-        headers,
-        multi_value_headers: HeaderMap::new(),
-        body: Some(Body::from(response_body.to_string())),
-        is_base64_encoded: false,
-    })
-} 
\ No newline at end of file
+    }))
+}
+
+/// Response body asking the client to finish the login at an MFA endpoint
+pub fn mfa_challenge(user: &User, step: MfaStep) -> Result<Value, AppError> {
+    let Some(user_id) = &user.id else {
+        return Err(AppError::Internal("User record has no ID".to_string()));
+    };
+    let mfa_token = auth::generate_mfa_token(&user_id.to_string(), &user.role.to_string(), step)?;
+
+    let message = match step {
+        MfaStep::Verify => "Enter a code from your authenticator app",
+        MfaStep::Enroll => "Your account requires an authenticator app. Enroll one to continue.",
+    };
+
+    Ok(json!({
+        "message": message,
+        "mfa_required": true,
+        "mfa_step": step,
+        "mfa_token": mfa_token,
+        "expires_in": auth::mfa_token_lifetime().as_secs()
+    }))
+}
+
+/// Rate limit response telling the client when to try again
+pub fn too_many_attempts(retry_after: u64) -> ApiGatewayProxyResponse {
+    let mut response: ApiGatewayProxyResponse = AppError::RateLimit(ErrorCode::TooManyLoginAttempts, format!(
+        "Too many failed login attempts. Try again in {} seconds.",
+        retry_after
+    ))
+    .into();
+    response.headers.insert("Retry-After", retry_after.into());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::security::SecurityEventKind;
+    use crate::models::user::UserRole;
+    use crate::repository::memory::connect;
+    use crate::testing::{json_request, native_identity};
+
+    #[tokio::test]
+    async fn failed_logins_back_off_and_lock_out() {
+        let repo = connect().await.unwrap();
+        let (identity, _) = native_identity(&repo);
+        let mut user = User::new("ada@example.com".to_string(), "Ada".to_string(), UserRole::Student);
+        user.password = Some(auth::hash_password("right password").unwrap());
+        repo.create_user(user).await.unwrap();
+
+        let login = |password: &str| json_request(serde_json::json!({"email": "ada@example.com", "password": password}));
+        for _ in 0..crate::common::config::CONFIG.login_backoff_after {
+            let response = handle(&repo, &identity, login("wrong")).await.unwrap();
+            assert_eq!(response.status_code, 401);
+        }
+
+        // Even the right password waits out the backoff
+        let response = handle(&repo, &identity, login("right password")).await.unwrap();
+        assert_eq!(response.status_code, 429);
+        assert!(response.headers.contains_key("Retry-After"));
+
+        // Enough failures lock the account, and the lock is logged when it is set and when it expires
+        let attempt = throttle::LoginAttempt::new("grace@example.com", Some("203.0.113.7"));
+        let now = chrono::Utc::now();
+        for _ in 0..crate::common::config::CONFIG.login_lockout_after {
+            throttle::record_failure(&repo, &attempt, now).await.unwrap();
+        }
+        let lockout = chrono::Duration::minutes(crate::common::config::CONFIG.login_lockout_minutes as i64);
+        assert!(throttle::retry_after(&repo, &attempt, now).await.unwrap().is_some());
+        assert!(throttle::retry_after(&repo, &attempt, now + lockout).await.unwrap().is_none());
+
+        let events = repo.list_security_events(10, 0).await.unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(events.iter().any(|event| event.kind == SecurityEventKind::LoginLockout && event.subject == "account:grace@example.com"));
+        assert!(events.iter().any(|event| event.kind == SecurityEventKind::LoginUnlock));
+    }
+
+    #[tokio::test]
+    async fn outdated_password_hashes_are_replaced_on_login() {
+        use argon2::password_hash::{PasswordHasher, SaltString};
+
+        let repo = connect().await.unwrap();
+        let (identity, _) = native_identity(&repo);
+
+        let salt = SaltString::generate(&mut argon2::password_hash::rand_core::OsRng);
+        let params = argon2::Params::new(8, 1, 1, None).unwrap();
+        let cheap = argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+            .hash_password(b"right password", &salt)
+            .unwrap()
+            .to_string();
+        let mut user = User::new("ada@example.com".to_string(), "Ada".to_string(), UserRole::Student);
+        user.password = Some(cheap);
+        repo.create_user(user).await.unwrap();
+
+        identity.sign_in("ada@example.com", "right password").await.unwrap();
+
+        let stored = repo.find_user_by_email("ada@example.com").await.unwrap().unwrap().password.unwrap();
+        assert_eq!(auth::verify_password("right password", &stored), auth::PasswordMatch::Valid);
+
+        // A corrupt hash is a failed login, not a panic
+        let mut user = User::new("grace@example.com".to_string(), "Grace".to_string(), UserRole::Student);
+        user.password = Some("not a hash".to_string());
+        repo.create_user(user).await.unwrap();
+        assert!(matches!(
+            identity.sign_in("grace@example.com", "anything").await,
+            Err(AppError::Authentication(..))
+        ));
+    }
+}
@@ -0,0 +1,127 @@
+use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
+use lambda_runtime::{Error, LambdaEvent};
+use serde_json::json;
+use tracing::{error, info};
+
+use crate::common::auth;
+use crate::common::error::{AppError, ErrorCode};
+use crate::common::extract::AuthContext;
+use crate::common::response::json_response;
+use crate::models::token::LogoutRequest;
+use crate::repository::{self, TokenRepository};
+
+/// Lambda handler for logging out
+pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
+    // Connect to database
+    let repo = match repository::connect().await {
+        Ok(repo) => repo,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    handle(&repo, event.payload).await
+}
+
+/// Revoke the caller's access token and refresh tokens using the given repository
+pub async fn handle(repo: &impl TokenRepository, request: ApiGatewayProxyRequest) -> Result<ApiGatewayProxyResponse, Error> {
+    // Authenticate the caller
+    let auth = match AuthContext::from_request(&request) {
+        Ok(auth) => auth,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    // The body is optional: without it the presented access token's session is ended
+    let logout_request = match &request.body {
+        Some(body) if !body.trim().is_empty() => match serde_json::from_str::<LogoutRequest>(body) {
+            Ok(req) => req,
+            Err(err) => {
+                error!("Failed to parse logout request: {}", err);
+                return Ok(AppError::Validation(ErrorCode::InvalidBody, format!("Invalid request format: {}", err)).into());
+            }
+        },
+        _ => LogoutRequest::default(),
+    };
+
+    let now = chrono::Utc::now();
+
+    // Revoke the access token used for this request
+    if let Err(err) = repo.revoke_access_token(&auth.token.id, auth.token.expires_at).await {
+        error!("Failed to revoke access token: {}", err);
+        return Ok(err.into());
+    }
+
+    // Revoke sessions and their refresh tokens so they cannot be renewed
+    if logout_request.all_sessions {
+        let revoked = match repo.revoke_user_sessions(&auth.user, now).await {
+            Ok(()) => repo.revoke_user_refresh_tokens(&auth.user, now).await,
+            Err(err) => Err(err),
+        };
+        if let Err(err) = revoked {
+            error!("Failed to revoke sessions: {}", err);
+            return Ok(err.into());
+        }
+    } else {
+        if let Some(refresh_token) = &logout_request.refresh_token {
+            let token_hash = auth::hash_opaque_token(refresh_token);
+            match repo.find_refresh_token(&token_hash).await {
+                // Only the owner can end a session
+                Ok(Some(stored)) if auth.is_user(&stored.user) => {
+                    if let Err(err) = repo.revoke_session(&stored.user, &auth::session_id(&stored.family), now).await {
+                        error!("Failed to revoke session: {}", err);
+                        return Ok(err.into());
+                    }
+                }
+                Ok(_) => {
+                    return Ok(AppError::Validation(ErrorCode::InvalidToken, "Invalid refresh token".to_string()).into());
+                }
+                Err(err) => {
+                    error!("Database error during logout: {}", err);
+                    return Ok(err.into());
+                }
+            }
+        }
+
+        // End the session the access token belongs to
+        if let Some(family) = &auth.token.session
+            && let Err(err) = repo.revoke_session(&auth.user, &auth::session_id(family), now).await
+        {
+            error!("Failed to revoke session: {}", err);
+            return Ok(err.into());
+        }
+    }
+
+    info!("User {} logged out", auth.user);
+
+    // Create successful response
+    let response_body = json!({
+        "message": "Logout successful"
+    });
+
+    Ok(json_response(200, response_body))
+}
+
+#[cfg(test)]
+mod tests {
+    use surrealdb::sql::Thing;
+
+    use super::*;
+    use crate::models::user::UserRole;
+    use crate::repository::memory::connect;
+    use crate::testing::request_as;
+
+    #[tokio::test]
+    async fn logout_revokes_access_token() {
+        let repo = connect().await.unwrap();
+        let user = Thing::from(("user", "ada"));
+        let request = request_as(&repo, &user, UserRole::Student).await;
+        let jti = AuthContext::from_request(&request).unwrap().token.id;
+
+        let response = handle(&repo, request.clone()).await.unwrap();
+        assert_eq!(response.status_code, 200);
+        assert!(repo.is_access_token_revoked(&jti, &user, 0).await.unwrap());
+        assert!(AuthContext::authenticate(&repo, &request).await.is_err());
+    }
+}
@@ -0,0 +1,126 @@
+use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
+use chrono::{TimeZone, Utc};
+use lambda_runtime::{Error, LambdaEvent};
+use serde_json::json;
+use tracing::error;
+
+use crate::common::error::{AppError, ErrorCode};
+use crate::common::extract;
+use crate::common::response::json_response;
+use crate::common::{mfa, totp};
+use crate::lambda::auth::login;
+use crate::models::mfa::TotpConfirmRequest;
+use crate::repository::{self, MfaRepository, TokenRepository, UserRepository};
+
+/// Lambda handler for confirming TOTP enrollment
+pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
+    // Connect to database
+    let repo = match repository::connect().await {
+        Ok(repo) => repo,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    handle(&repo, event.payload).await
+}
+
+/// Confirm the caller's pending authenticator with a code from it.
+///
+/// The recovery codes are returned here and never again. A caller finishing a login with
+/// an enrollment-pending token also gets their session tokens.
+pub async fn handle(
+    repo: &(impl UserRepository + TokenRepository + MfaRepository),
+    request: ApiGatewayProxyRequest,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    // Parse request body
+    let confirm_request = match &request.body {
+        Some(body) => match serde_json::from_str::<TotpConfirmRequest>(body) {
+            Ok(req) => req,
+            Err(err) => {
+                error!("Failed to parse enrollment confirmation: {}", err);
+                return Ok(AppError::Validation(ErrorCode::InvalidBody, format!("Invalid request format: {}", err)).into());
+            }
+        },
+        None => {
+            return Ok(AppError::Validation(ErrorCode::MissingBody, "Missing request body".to_string()).into());
+        }
+    };
+
+    let caller = match mfa::enrollment_caller(repo, &request, confirm_request.mfa_token.as_deref()).await {
+        Ok(caller) => caller,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    let enrollment = match repo.find_totp(&caller.user).await {
+        Ok(Some(enrollment)) if !enrollment.is_confirmed() => enrollment,
+        Ok(_) => {
+            return Ok(AppError::Validation(
+                ErrorCode::MfaNotPending,
+                "No authenticator is waiting to be confirmed".to_string(),
+            )
+            .into());
+        }
+        Err(err) => {
+            error!("Database error during enrollment confirmation: {}", err);
+            return Ok(err.into());
+        }
+    };
+
+    let now = Utc::now();
+    let Some(step) = totp::verify(&enrollment.secret, &confirm_request.code, now.timestamp() as u64, enrollment.last_used_step) else {
+        return Ok(AppError::Validation(ErrorCode::InvalidMfaCode, "Invalid code".to_string()).into());
+    };
+
+    let recovery_codes = totp::generate_recovery_codes();
+    let hashes = recovery_codes.iter().map(|code| totp::hash_recovery_code(code)).collect();
+
+    match repo.confirm_totp(&caller.user, step, hashes, now).await {
+        Ok(Some(_)) => {}
+        // Confirmed concurrently, or the code was just used
+        Ok(None) => {
+            return Ok(AppError::Validation(ErrorCode::InvalidMfaCode, "Invalid code".to_string()).into());
+        }
+        Err(err) => {
+            error!("Failed to confirm TOTP enrollment: {}", err);
+            return Ok(err.into());
+        }
+    }
+
+    let mut response_body = json!({
+        "message": "Authenticator enrolled. Store these recovery codes somewhere safe; they will not be shown again."
+    });
+
+    // Finishing a login: spend the pending token and start the session
+    if let Some(claims) = caller.pending {
+        let expires_at = Utc.timestamp_opt(claims.exp as i64, 0).single().unwrap_or(now);
+        if let Err(err) = repo.revoke_access_token(&claims.jti, expires_at).await {
+            error!("Failed to revoke MFA token: {}", err);
+            return Ok(err.into());
+        }
+
+        let user = match repo.find_user(&caller.user).await {
+            Ok(Some(user)) => user,
+            Ok(None) => {
+                return Ok(AppError::NotFound(ErrorCode::UserNotFound, "User not found".to_string()).into());
+            }
+            Err(err) => {
+                error!("Database error during enrollment confirmation: {}", err);
+                return Ok(err.into());
+            }
+        };
+
+        response_body = match login::start_session(repo, user, &extract::client_info(&request)).await {
+            Ok(body) => body,
+            Err(err) => {
+                error!("Failed to start session: {}", err);
+                return Ok(err.into());
+            }
+        };
+    }
+    response_body["recovery_codes"] = json!(recovery_codes);
+
+    Ok(json_response(200, response_body))
+}
@@ -0,0 +1,184 @@
+use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
+use chrono::Utc;
+use lambda_runtime::{Error, LambdaEvent};
+use serde_json::json;
+use tracing::{error, info};
+
+use crate::common::error::{AppError, ErrorCode};
+use crate::common::extract::{self, AuthContext};
+use crate::common::mfa;
+use crate::common::response::json_response;
+use crate::common::throttle::{self, LoginAttempt};
+use crate::lambda::auth::login;
+use crate::models::mfa::TotpDisableRequest;
+use crate::repository::{self, MfaRepository, SecurityRepository, UserRepository};
+
+/// Lambda handler for removing the caller's authenticator
+pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
+    // Connect to database
+    let repo = match repository::connect().await {
+        Ok(repo) => repo,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    handle(&repo, event.payload).await
+}
+
+/// Remove the caller's authenticator, proven with a current code or a recovery code.
+///
+/// Roles that require MFA cannot remove theirs. Wrong codes count as failed logins, like in
+/// [`mfa_verify`](super::mfa_verify), so a stolen access token cannot be used to guess them.
+pub async fn handle(
+    repo: &(impl UserRepository + SecurityRepository + MfaRepository),
+    request: ApiGatewayProxyRequest,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    // Authenticate the caller
+    let auth = match AuthContext::from_request(&request) {
+        Ok(auth) => auth,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    if mfa::required_for(&auth.role) {
+        return Ok(AppError::Authorization(
+            ErrorCode::MfaRequiredForRole,
+            "Your role requires multi-factor authentication".to_string(),
+        )
+        .into());
+    }
+
+    // Parse request body
+    let disable_request = match &request.body {
+        Some(body) => match serde_json::from_str::<TotpDisableRequest>(body) {
+            Ok(req) => req,
+            Err(err) => {
+                error!("Failed to parse MFA disable request: {}", err);
+                return Ok(AppError::Validation(ErrorCode::InvalidBody, format!("Invalid request format: {}", err)).into());
+            }
+        },
+        None => {
+            return Ok(AppError::Validation(ErrorCode::MissingBody, "Missing request body".to_string()).into());
+        }
+    };
+
+    let enrollment = match repo.find_totp(&auth.user).await {
+        Ok(Some(enrollment)) => enrollment,
+        Ok(None) => {
+            return Ok(AppError::NotFound(ErrorCode::MfaNotEnrolled, "No authenticator is enrolled".to_string()).into());
+        }
+        Err(err) => {
+            error!("Database error while disabling MFA: {}", err);
+            return Ok(err.into());
+        }
+    };
+
+    // A pending enrollment never protected anything, so it can be dropped without a code
+    if enrollment.is_confirmed() {
+        let user = match repo.find_user(&auth.user).await {
+            Ok(Some(user)) => user,
+            Ok(None) => {
+                return Ok(AppError::NotFound(ErrorCode::UserNotFound, "User not found".to_string()).into());
+            }
+            Err(err) => {
+                error!("Database error while disabling MFA: {}", err);
+                return Ok(err.into());
+            }
+        };
+
+        // Refuse the attempt outright while the account or IP is backed off or locked
+        let client = extract::client_info(&request);
+        let attempt = LoginAttempt::new(&user.email, client.ip_address.as_deref());
+        match throttle::retry_after(repo, &attempt, Utc::now()).await {
+            Ok(Some(retry_after)) => {
+                return Ok(login::too_many_attempts(retry_after));
+            }
+            Ok(None) => {}
+            Err(err) => {
+                error!("Failed to check login throttle: {}", err);
+                return Ok(err.into());
+            }
+        }
+
+        let now = Utc::now();
+        if let Err(err) = mfa::check_second_factor(
+            repo,
+            &enrollment,
+            disable_request.code.as_deref(),
+            disable_request.recovery_code.as_deref(),
+            now,
+        )
+        .await
+        {
+            error!("MFA code rejected while disabling MFA for {}: {}", user.email, err);
+            if matches!(err, AppError::Authentication(..))
+                && let Err(throttle_err) = throttle::record_failure(repo, &attempt, now).await
+            {
+                error!("Failed to record login failure: {}", throttle_err);
+            }
+            return Ok(err.into());
+        }
+
+        if let Err(err) = throttle::record_success(repo, &attempt).await {
+            error!("Failed to clear login failures: {}", err);
+        }
+    }
+
+    if let Err(err) = repo.delete_totp(&auth.user).await {
+        error!("Failed to remove authenticator: {}", err);
+        return Ok(err.into());
+    }
+    info!("Removed authenticator for {}", auth.user);
+
+    let response_body = json!({
+        "message": "Authenticator removed"
+    });
+
+    Ok(json_response(200, response_body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::config::CONFIG;
+    use crate::common::totp;
+    use crate::lambda::auth::{mfa_confirm, mfa_enroll};
+    use crate::models::user::{User, UserRole};
+    use crate::repository::memory::connect;
+    use crate::testing::{request_as, response_json};
+
+    #[tokio::test]
+    async fn wrong_codes_are_throttled() {
+        let repo = connect().await.unwrap();
+        let user = User::new("ada@example.com".to_string(), "Ada".to_string(), UserRole::Student);
+        let id = repo.create_user(user).await.unwrap().id.unwrap();
+
+        let response = mfa_enroll::handle(&repo, request_as(&repo, &id, UserRole::Student).await).await.unwrap();
+        let secret = response_json(&response)["secret"].as_str().unwrap().to_string();
+        let step = totp::step_at(Utc::now().timestamp() as u64);
+        let mut confirm = request_as(&repo, &id, UserRole::Student).await;
+        confirm.body = Some(json!({"code": totp::code_at(&secret, step).unwrap()}).to_string());
+        assert_eq!(mfa_confirm::handle(&repo, confirm).await.unwrap().status_code, 200);
+
+        let disable = |body: serde_json::Value| {
+            let (repo, id) = (&repo, &id);
+            async move {
+                let mut request = request_as(repo, id, UserRole::Student).await;
+                request.body = Some(body.to_string());
+                handle(repo, request).await.unwrap()
+            }
+        };
+        for _ in 0..CONFIG.login_backoff_after {
+            let response = disable(json!({"code": "000000"})).await;
+            assert_eq!(response.status_code, 401);
+        }
+
+        // Even the right code waits out the backoff, and the authenticator stays
+        let response = disable(json!({"code": totp::code_at(&secret, step + 1).unwrap()})).await;
+        assert_eq!(response.status_code, 429);
+        assert!(response.headers.contains_key("Retry-After"));
+        assert!(repo.find_totp(&id).await.unwrap().is_some());
+    }
+}
@@ -0,0 +1,97 @@
+use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
+use lambda_runtime::{Error, LambdaEvent};
+use serde_json::json;
+use tracing::error;
+
+use crate::common::config::CONFIG;
+use crate::common::error::{AppError, ErrorCode};
+use crate::common::response::json_response;
+use crate::common::{mfa, totp};
+use crate::models::mfa::{TotpEnrollRequest, TotpEnrollment};
+use crate::repository::{self, MfaRepository, TokenRepository, UserRepository};
+
+/// Lambda handler for starting TOTP enrollment
+pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
+    // Connect to database
+    let repo = match repository::connect().await {
+        Ok(repo) => repo,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    handle(&repo, event.payload).await
+}
+
+/// Generate a new TOTP secret for the caller, pending confirmation.
+///
+/// The caller is identified by their access token, or by the enrollment-pending token a
+/// login returns when their role requires MFA.
+pub async fn handle(
+    repo: &(impl UserRepository + TokenRepository + MfaRepository),
+    request: ApiGatewayProxyRequest,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    // The body is optional for signed-in callers
+    let enroll_request = match &request.body {
+        Some(body) if !body.trim().is_empty() => match serde_json::from_str::<TotpEnrollRequest>(body) {
+            Ok(req) => req,
+            Err(err) => {
+                error!("Failed to parse enrollment request: {}", err);
+                return Ok(AppError::Validation(ErrorCode::InvalidBody, format!("Invalid request format: {}", err)).into());
+            }
+        },
+        _ => TotpEnrollRequest::default(),
+    };
+
+    let caller = match mfa::enrollment_caller(repo, &request, enroll_request.mfa_token.as_deref()).await {
+        Ok(caller) => caller,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    let user = match repo.find_user(&caller.user).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            return Ok(AppError::NotFound(ErrorCode::UserNotFound, "User not found".to_string()).into());
+        }
+        Err(err) => {
+            error!("Database error during enrollment: {}", err);
+            return Ok(err.into());
+        }
+    };
+
+    // A confirmed authenticator has to be disabled before another can be enrolled
+    match repo.find_totp(&caller.user).await {
+        Ok(Some(existing)) if existing.is_confirmed() => {
+            return Ok(AppError::Validation(
+                ErrorCode::MfaAlreadyEnrolled,
+                "An authenticator is already enrolled. Disable it before enrolling another.".to_string(),
+            )
+            .into());
+        }
+        Ok(_) => {}
+        Err(err) => {
+            error!("Database error during enrollment: {}", err);
+            return Ok(err.into());
+        }
+    }
+
+    let secret = totp::generate_secret();
+    if let Err(err) = repo
+        .replace_pending_totp(TotpEnrollment::new(caller.user.clone(), secret.clone()))
+        .await
+    {
+        error!("Failed to store TOTP enrollment: {}", err);
+        return Ok(err.into());
+    }
+
+    // Clients render the URI as a QR code for the authenticator app to scan
+    let response_body = json!({
+        "message": "Scan the QR code with your authenticator app, then confirm a code",
+        "secret": secret,
+        "otpauth_uri": totp::provisioning_uri(&secret, &user.email, &CONFIG.mfa_issuer)
+    });
+
+    Ok(json_response(200, response_body))
+}
@@ -0,0 +1,212 @@
+use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
+use chrono::{TimeZone, Utc};
+use lambda_runtime::{Error, LambdaEvent};
+use tracing::error;
+
+use crate::common::auth::{self, MfaStep};
+use crate::common::error::{AppError, ErrorCode};
+use crate::common::extract;
+use crate::common::mfa;
+use crate::common::response::json_response;
+use crate::common::throttle::{self, LoginAttempt};
+use crate::lambda::auth::login;
+use crate::models::mfa::MfaVerifyRequest;
+use crate::repository::{self, record_id, MfaRepository, SecurityRepository, TokenRepository, UserRepository};
+
+/// Lambda handler for the second step of an MFA login
+pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
+    // Connect to database
+    let repo = match repository::connect().await {
+        Ok(repo) => repo,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    handle(&repo, event.payload).await
+}
+
+/// Exchange an MFA-pending token and a TOTP or recovery code for a session.
+///
+/// Wrong codes count as failed logins for the account and source IP, so they share the
+/// login backoff and lockout.
+pub async fn handle(
+    repo: &(impl UserRepository + TokenRepository + SecurityRepository + MfaRepository),
+    request: ApiGatewayProxyRequest,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let client = extract::client_info(&request);
+
+    // Parse request body
+    let verify_request = match request.body {
+        Some(body) => match serde_json::from_str::<MfaVerifyRequest>(&body) {
+            Ok(req) => req,
+            Err(err) => {
+                error!("Failed to parse MFA request: {}", err);
+                return Ok(AppError::Validation(ErrorCode::InvalidBody, format!("Invalid request format: {}", err)).into());
+            }
+        },
+        None => {
+            return Ok(AppError::Validation(ErrorCode::MissingBody, "Missing request body".to_string()).into());
+        }
+    };
+
+    let claims = match auth::validate_mfa_token(repo, &verify_request.mfa_token, MfaStep::Verify).await {
+        Ok(claims) => claims,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    let user = match record_id("user", &claims.sub) {
+        Ok(id) => match repo.find_user(&id).await {
+            Ok(Some(user)) => user,
+            Ok(None) => {
+                return Ok(AppError::Authentication(ErrorCode::InvalidMfaToken, "Invalid MFA token".to_string()).into());
+            }
+            Err(err) => {
+                error!("Database error during MFA verification: {}", err);
+                return Ok(err.into());
+            }
+        },
+        Err(_) => {
+            return Ok(AppError::Authentication(ErrorCode::InvalidMfaToken, "Invalid MFA token".to_string()).into());
+        }
+    };
+
+    // Refuse the attempt outright while the account or IP is backed off or locked
+    let attempt = LoginAttempt::new(&user.email, client.ip_address.as_deref());
+    match throttle::retry_after(repo, &attempt, Utc::now()).await {
+        Ok(Some(retry_after)) => {
+            return Ok(login::too_many_attempts(retry_after));
+        }
+        Ok(None) => {}
+        Err(err) => {
+            error!("Failed to check login throttle: {}", err);
+            return Ok(err.into());
+        }
+    }
+
+    // The authenticator may have been removed since the password step
+    let Some(user_id) = user.id.clone() else {
+        return Ok(AppError::Internal("User record has no ID".to_string()).into());
+    };
+    let enrollment = match repo.find_totp(&user_id).await {
+        Ok(Some(enrollment)) if enrollment.is_confirmed() => enrollment,
+        Ok(_) => {
+            return Ok(AppError::Authentication(
+                ErrorCode::InvalidMfaToken,
+                "Invalid MFA token. Log in again.".to_string(),
+            )
+            .into());
+        }
+        Err(err) => {
+            error!("Database error during MFA verification: {}", err);
+            return Ok(err.into());
+        }
+    };
+
+    let now = Utc::now();
+    if let Err(err) = mfa::check_second_factor(
+        repo,
+        &enrollment,
+        verify_request.code.as_deref(),
+        verify_request.recovery_code.as_deref(),
+        now,
+    )
+    .await
+    {
+        error!("MFA verification failed for {}: {}", user.email, err);
+        if matches!(err, AppError::Authentication(..))
+            && let Err(throttle_err) = throttle::record_failure(repo, &attempt, now).await
+        {
+            error!("Failed to record login failure: {}", throttle_err);
+        }
+        return Ok(err.into());
+    }
+
+    if let Err(err) = throttle::record_success(repo, &attempt).await {
+        error!("Failed to clear login failures: {}", err);
+    }
+
+    // Each pending token completes one login
+    let expires_at = Utc.timestamp_opt(claims.exp as i64, 0).single().unwrap_or(now);
+    if let Err(err) = repo.revoke_access_token(&claims.jti, expires_at).await {
+        error!("Failed to revoke MFA token: {}", err);
+        return Ok(err.into());
+    }
+
+    let response_body = match login::start_session(repo, user, &client).await {
+        Ok(body) => body,
+        Err(err) => {
+            error!("Failed to start session: {}", err);
+            return Ok(err.into());
+        }
+    };
+
+    Ok(json_response(200, response_body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lambda::auth::{mfa_confirm, mfa_enroll};
+    use crate::models::user::{User, UserRole};
+    use crate::repository::memory::connect;
+    use crate::testing::{json_request, native_identity, request_as, response_json};
+
+    #[tokio::test]
+    async fn totp_logins_need_a_second_step() {
+        use crate::common::totp;
+
+        let repo = connect().await.unwrap();
+        let (identity, _) = native_identity(&repo);
+        let mut user = User::new("ada@example.com".to_string(), "Ada".to_string(), UserRole::Educator);
+        user.password = Some(auth::hash_password("right password").unwrap());
+        let id = repo.create_user(user).await.unwrap().id.unwrap();
+
+        // Enroll and confirm an authenticator
+        let response = mfa_enroll::handle(&repo, request_as(&repo, &id, UserRole::Educator).await).await.unwrap();
+        assert_eq!(response.status_code, 200);
+        let body = response_json(&response);
+        let secret = body["secret"].as_str().unwrap().to_string();
+        assert!(body["otpauth_uri"].as_str().unwrap().starts_with("otpauth://totp/"));
+
+        let step = totp::step_at(chrono::Utc::now().timestamp() as u64);
+        let mut confirm = request_as(&repo, &id, UserRole::Educator).await;
+        confirm.body = Some(serde_json::json!({"code": totp::code_at(&secret, step).unwrap()}).to_string());
+        let response = mfa_confirm::handle(&repo, confirm).await.unwrap();
+        assert_eq!(response.status_code, 200);
+        let recovery_codes = response_json(&response)["recovery_codes"].clone();
+        assert_eq!(recovery_codes.as_array().unwrap().len(), totp::RECOVERY_CODE_COUNT);
+
+        // The password alone now only earns an MFA token, which is no good as an access token
+        let login = || json_request(serde_json::json!({"email": "ada@example.com", "password": "right password"}));
+        let response = login::handle(&repo, &identity, login()).await.unwrap();
+        let body = response_json(&response);
+        assert_eq!(body["mfa_required"], true);
+        assert!(body.get("token").is_none());
+        let mfa_token = body["mfa_token"].as_str().unwrap().to_string();
+        assert!(auth::validate_token(&repo, &mfa_token).await.is_err());
+
+        // The code used to confirm cannot be replayed, and wrong codes fail
+        let replayed = totp::code_at(&secret, step).unwrap();
+        let response = handle(&repo, json_request(serde_json::json!({"mfa_token": mfa_token, "code": replayed}))).await.unwrap();
+        assert_eq!(response.status_code, 401);
+
+        // A recovery code completes the login, once
+        let recovery_code = recovery_codes[0].as_str().unwrap();
+        let response = handle(&repo, json_request(serde_json::json!({"mfa_token": mfa_token, "recovery_code": recovery_code}))).await.unwrap();
+        assert_eq!(response.status_code, 200);
+        assert!(auth::validate_token(&repo, response_json(&response)["token"].as_str().unwrap()).await.is_ok());
+
+        let response = handle(&repo, json_request(serde_json::json!({"mfa_token": mfa_token, "recovery_code": recovery_code}))).await.unwrap();
+        assert_eq!(response.status_code, 401);
+
+        // A fresh login with the next code from the app works too
+        let response = login::handle(&repo, &identity, login()).await.unwrap();
+        let mfa_token = response_json(&response)["mfa_token"].as_str().unwrap().to_string();
+        let code = totp::code_at(&secret, step + 1).unwrap();
+        let response = handle(&repo, json_request(serde_json::json!({"mfa_token": mfa_token, "code": code}))).await.unwrap();
+        assert_eq!(response.status_code, 200);
+    }
+}
@@ -1,3 +1,20 @@
+pub mod create_token;
+pub mod forgot_password;
+pub mod jwks;
+pub mod list_sessions;
+pub mod list_tokens;
 pub mod login;
+pub mod logout;
+pub mod mfa_confirm;
+pub mod mfa_disable;
+pub mod mfa_enroll;
+pub mod mfa_verify;
+pub mod oidc_authorize;
+pub mod oidc_callback;
+pub mod refresh;
 pub mod register;
+pub mod resend_verification;
+pub mod reset_password;
+pub mod revoke_session;
+pub mod revoke_token;
 pub mod verify; 
\ No newline at end of file
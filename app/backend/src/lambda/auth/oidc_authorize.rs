@@ -0,0 +1,76 @@
+use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
+use lambda_runtime::{Error, LambdaEvent};
+use serde_json::json;
+use tracing::error;
+
+use crate::common::auth;
+use crate::common::config::{OidcProviders, CONFIG};
+use crate::common::response::json_response;
+use crate::common::router;
+use crate::identity::oidc::OidcClient;
+use crate::repository::{self, OidcRepository};
+
+/// Lambda handler for starting an OpenID Connect sign-in
+pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
+    // Connect to database
+    let repo = match repository::connect().await {
+        Ok(repo) => repo,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    handle(&repo, &CONFIG.oidc_providers, event.payload).await
+}
+
+/// Start a sign-in with the provider named in the path, returning the URL to send the user to.
+///
+/// The frontend should keep the returned `state` and check it matches the one the provider
+/// redirects back with before calling the callback endpoint.
+pub async fn handle(
+    repo: &impl OidcRepository,
+    providers: &OidcProviders,
+    request: ApiGatewayProxyRequest,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let client = match router::path_param::<String>(&request, "provider")
+        .and_then(|name| OidcClient::from_config(providers, &name))
+    {
+        Ok(client) => client,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    let metadata = match client.discover().await {
+        Ok(metadata) => metadata,
+        Err(err) => {
+            error!("Discovery failed for {}: {}", client.name(), err);
+            return Ok(err.into());
+        }
+    };
+
+    let authorization = match client.authorize(&metadata) {
+        Ok(authorization) => authorization,
+        Err(err) => {
+            error!("Failed to start sign-in with {}: {}", client.name(), err);
+            return Ok(err.into());
+        }
+    };
+
+    let expires_in = (authorization.pending.expires_at - authorization.pending.created_at).num_seconds();
+    if let Err(err) = repo
+        .create_oidc_state(&auth::hash_opaque_token(&authorization.state), authorization.pending)
+        .await
+    {
+        error!("Failed to store sign-in state: {}", err);
+        return Ok(err.into());
+    }
+
+    let response_body = json!({
+        "authorization_url": authorization.url,
+        "state": authorization.state,
+        "expires_in": expires_in
+    });
+
+    Ok(json_response(200, response_body))
+}
@@ -0,0 +1,209 @@
+use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
+use chrono::Utc;
+use lambda_runtime::{Error, LambdaEvent};
+use tracing::error;
+
+use crate::common::auth;
+use crate::common::config::{OidcProviders, CONFIG};
+use crate::common::error::{AppError, ErrorCode};
+use crate::common::extract;
+use crate::common::response::json_response;
+use crate::common::{mfa, router};
+use crate::identity::oidc::{self, OidcClient};
+use crate::lambda::auth::login;
+use crate::models::oidc::OidcCallbackRequest;
+use crate::repository::{self, MfaRepository, OidcRepository, TokenRepository, UserRepository};
+
+/// Lambda handler for finishing an OpenID Connect sign-in
+pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
+    // Connect to database
+    let repo = match repository::connect().await {
+        Ok(repo) => repo,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    handle(&repo, &CONFIG.oidc_providers, event.payload).await
+}
+
+/// Finish a sign-in with the code and state the provider redirected back with.
+///
+/// Responds like `POST /auth/login`: with a session, or with an MFA-pending token for users
+/// who have a second step to complete.
+pub async fn handle(
+    repo: &(impl UserRepository + TokenRepository + MfaRepository + OidcRepository),
+    providers: &OidcProviders,
+    request: ApiGatewayProxyRequest,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let client = match router::path_param::<String>(&request, "provider")
+        .and_then(|name| OidcClient::from_config(providers, &name))
+    {
+        Ok(client) => client,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    let client_info = extract::client_info(&request);
+
+    // Parse request body
+    let callback_request = match request.body {
+        Some(body) => match serde_json::from_str::<OidcCallbackRequest>(&body) {
+            Ok(req) => req,
+            Err(err) => {
+                error!("Failed to parse sign-in callback: {}", err);
+                return Ok(AppError::Validation(ErrorCode::InvalidBody, format!("Invalid request format: {}", err)).into());
+            }
+        },
+        None => {
+            return Ok(AppError::Validation(ErrorCode::MissingBody, "Missing request body".to_string()).into());
+        }
+    };
+
+    // Each state is good for one callback, with the provider it was issued for
+    let pending = match repo
+        .consume_oidc_state(&auth::hash_opaque_token(&callback_request.state), Utc::now())
+        .await
+    {
+        Ok(Some(pending)) if pending.provider == client.name() => pending,
+        Ok(_) => {
+            return Ok(AppError::Authentication(
+                ErrorCode::SignInExpired,
+                "Sign-in has expired or was already used. Try again.".to_string(),
+            )
+            .into());
+        }
+        Err(err) => {
+            error!("Failed to load sign-in state: {}", err);
+            return Ok(err.into());
+        }
+    };
+
+    let metadata = match client.discover().await {
+        Ok(metadata) => metadata,
+        Err(err) => {
+            error!("Discovery failed for {}: {}", client.name(), err);
+            return Ok(err.into());
+        }
+    };
+
+    let claims = match client.exchange_code(&metadata, &callback_request.code, &pending).await {
+        Ok(claims) => claims,
+        Err(err) => {
+            error!("Sign-in with {} failed: {}", client.name(), err);
+            return Ok(err.into());
+        }
+    };
+
+    let user = match oidc::link_account(repo, client.name(), &claims).await {
+        Ok(user) => user,
+        Err(err) => {
+            error!("Failed to link {} account {}: {}", client.name(), claims.sub, err);
+            return Ok(err.into());
+        }
+    };
+
+    // Users with an authenticator, or whose role requires one, have a second step to complete
+    let response_body = match mfa::pending_step(repo, &user).await {
+        Ok(Some(step)) => match login::mfa_challenge(&user, step) {
+            Ok(body) => body,
+            Err(err) => {
+                error!("Failed to issue MFA token: {}", err);
+                return Ok(err.into());
+            }
+        },
+        Ok(None) => match login::start_session(repo, user, &client_info).await {
+            Ok(body) => body,
+            Err(err) => {
+                error!("Failed to start session: {}", err);
+                return Ok(err.into());
+            }
+        },
+        Err(err) => {
+            error!("Failed to check MFA enrollment: {}", err);
+            return Ok(err.into());
+        }
+    };
+
+    Ok(json_response(200, response_body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lambda::auth::oidc_authorize;
+    use crate::models::user::{User, UserRole};
+    use crate::repository::memory::connect;
+    use crate::testing::response_json;
+
+    #[tokio::test]
+    async fn oidc_sign_in_links_accounts_by_verified_email() {
+        use crate::common::config::OidcProviders;
+        use crate::identity::oidc::mock::MockProvider;
+
+        let repo = connect().await.unwrap();
+        let provider = MockProvider::start().await;
+        let providers = OidcProviders(vec![provider.config("mock")]);
+        let existing = repo
+            .create_user(User::new("ada@example.com".to_string(), "Ada".to_string(), UserRole::Student))
+            .await
+            .unwrap()
+            .id
+            .unwrap();
+
+        let oidc_request = |body: Option<serde_json::Value>| {
+            let mut request = ApiGatewayProxyRequest {
+                body: body.map(|body| body.to_string()),
+                ..Default::default()
+            };
+            request.path_parameters.insert("provider".to_string(), "mock".to_string());
+            request
+        };
+        let sign_in = |claims: serde_json::Value| {
+            let (repo, providers, provider) = (&repo, &providers, &provider);
+            async move {
+                let response = oidc_authorize::handle(repo, providers, oidc_request(None)).await.unwrap();
+                let body = response_json(&response);
+                let code = provider.approve(body["authorization_url"].as_str().unwrap(), claims);
+                let callback = serde_json::json!({"code": code, "state": body["state"]});
+                (handle(repo, providers, oidc_request(Some(callback.clone()))).await.unwrap(), callback)
+            }
+        };
+
+        // An account whose owner never verified the address may have been registered by
+        // someone else, so it is not linked or marked verified
+        let claims = serde_json::json!({"sub": "g-1", "email": "ada@example.com", "email_verified": true});
+        let (response, _) = sign_in(claims.clone()).await;
+        assert_eq!(response.status_code, 403);
+        assert_eq!(response_json(&response)["error"]["code"], "EMAIL_NOT_VERIFIED");
+        assert!(repo.find_user_identity("mock", "g-1").await.unwrap().is_none());
+        assert!(!repo.find_user(&existing).await.unwrap().unwrap().email_verified);
+
+        // Once the owner has verified it, a verified email links the provider account, once
+        repo.mark_email_verified(&existing).await.unwrap();
+        let (response, callback) = sign_in(claims.clone()).await;
+        assert_eq!(response.status_code, 200);
+        assert!(auth::validate_token(&repo, response_json(&response)["token"].as_str().unwrap()).await.is_ok());
+        assert_eq!(repo.find_user_identity("mock", "g-1").await.unwrap().unwrap().user, existing);
+
+        let response = handle(&repo, &providers, oidc_request(Some(callback))).await.unwrap();
+        assert_eq!(response.status_code, 401);
+
+        // Later sign-ins find the link even if the provider's email changes
+        let (response, _) = sign_in(serde_json::json!({"sub": "g-1", "email": "ada@new.example.com", "email_verified": true})).await;
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response_json(&response)["user"]["email"], "ada@example.com");
+
+        // Unverified addresses are never linked or used to create accounts
+        let (response, _) = sign_in(serde_json::json!({"sub": "g-2", "email": "grace@example.com", "email_verified": false})).await;
+        assert_eq!(response.status_code, 401);
+        assert!(repo.find_user_by_email("grace@example.com").await.unwrap().is_none());
+
+        // New verified addresses get a student account
+        let (response, _) = sign_in(serde_json::json!({"sub": "g-3", "email": "grace@example.com", "email_verified": true, "name": "Grace"})).await;
+        assert_eq!(response.status_code, 200);
+        let grace = repo.find_user_by_email("grace@example.com").await.unwrap().unwrap();
+        assert_eq!((grace.name.as_str(), grace.role, grace.email_verified), ("Grace", UserRole::Student, true));
+    }
+}
@@ -0,0 +1,157 @@
+use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
+use lambda_runtime::{Error, LambdaEvent};
+use serde_json::json;
+use tracing::{error, warn};
+
+use crate::common::auth;
+use crate::common::error::{AppError, ErrorCode, FieldError};
+use crate::common::extract;
+use crate::common::response::json_response;
+use crate::models::token::RefreshRequest;
+use crate::repository::{self, TokenRepository, UserRepository};
+
+/// Lambda handler for refreshing an access token
+pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
+    // Connect to database
+    let repo = match repository::connect().await {
+        Ok(repo) => repo,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    handle(&repo, event.payload).await
+}
+
+/// Exchange a refresh token for a new token pair using the given repository
+pub async fn handle(repo: &(impl UserRepository + TokenRepository), request: ApiGatewayProxyRequest) -> Result<ApiGatewayProxyResponse, Error> {
+    // Parse request body
+    let refresh_request = match &request.body {
+        Some(body) => match serde_json::from_str::<RefreshRequest>(body) {
+            Ok(req) => req,
+            Err(err) => {
+                error!("Failed to parse refresh request: {}", err);
+                return Ok(AppError::Validation(ErrorCode::InvalidBody, format!("Invalid request format: {}", err)).into());
+            }
+        },
+        None => {
+            return Ok(AppError::Validation(ErrorCode::MissingBody, "Missing request body".to_string()).into());
+        }
+    };
+
+    if refresh_request.refresh_token.is_empty() {
+        return Ok(AppError::InvalidFields(vec![FieldError::required("refresh_token", "Refresh token is required")]).into());
+    }
+
+    let token_hash = auth::hash_opaque_token(&refresh_request.refresh_token);
+    let now = chrono::Utc::now();
+
+    // Look up the presented token
+    let stored = match repo.find_refresh_token(&token_hash).await {
+        Ok(Some(stored)) => stored,
+        Ok(None) => {
+            return Ok(AppError::Authentication(ErrorCode::InvalidToken, "Invalid refresh token".to_string()).into());
+        }
+        Err(err) => {
+            error!("Database error during token refresh: {}", err);
+            return Ok(err.into());
+        }
+    };
+
+    if stored.revoked_at.is_some() {
+        return Ok(AppError::Authentication(ErrorCode::TokenRevoked, "Refresh token has been revoked".to_string()).into());
+    }
+
+    if stored.expires_at <= now {
+        return Ok(AppError::Authentication(ErrorCode::TokenExpired, "Refresh token has expired".to_string()).into());
+    }
+
+    // Rotate the token. If it was already used, someone is replaying an old token:
+    // revoke the whole family so neither the thief nor the victim can keep refreshing.
+    let consumed = match repo.consume_refresh_token(&token_hash, now).await {
+        Ok(consumed) => consumed,
+        Err(err) => {
+            error!("Failed to rotate refresh token: {}", err);
+            return Ok(err.into());
+        }
+    };
+
+    if consumed.is_none() {
+        warn!("Refresh token reuse detected for {}, revoking session {}", stored.user, stored.family);
+        if let Err(err) = repo.revoke_session(&stored.user, &auth::session_id(&stored.family), now).await {
+            error!("Failed to revoke session: {}", err);
+            return Ok(err.into());
+        }
+        return Ok(AppError::Authentication(
+            ErrorCode::RefreshTokenReused,
+            "Refresh token has already been used".to_string(),
+        )
+        .into());
+    }
+
+    // Reload the user so role changes take effect on refresh
+    let user = match repo.find_user(&stored.user).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            return Ok(AppError::Authentication(ErrorCode::InvalidToken, "Invalid refresh token".to_string()).into());
+        }
+        Err(err) => {
+            error!("Database error during token refresh: {}", err);
+            return Ok(err.into());
+        }
+    };
+
+    let client = extract::client_info(&request);
+    let tokens = match auth::continue_session(repo, &stored.user, &user.role.to_string(), &stored.family, &client).await {
+        Ok(tokens) => tokens,
+        Err(err) => {
+            error!("Failed to issue tokens: {}", err);
+            return Ok(err.into());
+        }
+    };
+
+    // Create successful response with the new token pair
+    let response_body = json!({
+        "message": "Token refreshed",
+        "token": tokens.token,
+        "refresh_token": tokens.refresh_token,
+        "expires_in": tokens.expires_in
+    });
+
+    Ok(json_response(200, response_body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::session::ClientInfo;
+    use crate::models::user::{User, UserRole};
+    use crate::repository::memory::connect;
+
+    #[tokio::test]
+    async fn refresh_rotates_and_detects_reuse() {
+        let repo = connect().await.unwrap();
+        let user = User::new("ada@example.com".to_string(), "Ada".to_string(), UserRole::Student);
+        let id = repo.create_user(user).await.unwrap().id.unwrap();
+
+        let first = auth::open_session(&repo, &id, "student", &ClientInfo::default()).await.unwrap();
+        let refresh = |token: &str| ApiGatewayProxyRequest {
+            body: Some(format!(r#"{{"refresh_token":"{}"}}"#, token)),
+            ..Default::default()
+        };
+
+        let response = handle(&repo, refresh(&first.refresh_token)).await.unwrap();
+        assert_eq!(response.status_code, 200);
+        let body: serde_json::Value = match response.body.unwrap() {
+            aws_lambda_events::encodings::Body::Text(text) => serde_json::from_str(&text).unwrap(),
+            other => panic!("unexpected body {:?}", other),
+        };
+        let second = body["refresh_token"].as_str().unwrap().to_string();
+
+        // Replaying the rotated token revokes the whole family, including the new token
+        let response = handle(&repo, refresh(&first.refresh_token)).await.unwrap();
+        assert_eq!(response.status_code, 401);
+        let response = handle(&repo, refresh(&second)).await.unwrap();
+        assert_eq!(response.status_code, 401);
+    }
+}
@@ -1,19 +1,43 @@
 use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
-use aws_lambda_events::encodings::Body;
-use http::HeaderMap;
 use lambda_runtime::{Error, LambdaEvent};
 use serde_json::json;
-use std::collections::HashMap;
 use tracing::{error, info};
 
 use crate::common::auth;
-use crate::common::db;
-use crate::common::error::AppError;
-use crate::models::user::{User, UserRegistrationRequest, UserResponse};
+use crate::common::error::{AppError, ErrorCode, FieldError};
+use crate::common::extract;
+use crate::common::response::json_response;
+use crate::identity::{self, IdentityProvider};
+use crate::models::user::{UserRegistrationRequest, UserResponse};
+use crate::repository::{self, TokenRepository};
 
 /// Lambda handler for user registration
 pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
-    let request = event.payload;
+    // Connect to database
+    let repo = match repository::connect().await {
+        Ok(repo) => repo,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    let identity = match identity::connect(repo.clone()).await {
+        Ok(identity) => identity,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    handle(&repo, identity.as_ref(), event.payload).await
+}
+
+/// Register a user with the given identity provider, starting a session in the repository
+pub async fn handle(
+    repo: &impl TokenRepository,
+    identity: &dyn IdentityProvider,
+    request: ApiGatewayProxyRequest,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    let client = extract::client_info(&request);
 
     // Parse request body
     let registration_request = match request.body {
@@ -21,127 +45,51 @@ pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGa
             Ok(req) => req,
             Err(err) => {
                 error!("Failed to parse registration request: {}", err);
-                return Ok(AppError::Validation(format!("Invalid request format: {}", err)).into());
+                return Ok(AppError::Validation(ErrorCode::InvalidBody, format!("Invalid request format: {}", err)).into());
             }
         },
         None => {
-            return Ok(AppError::Validation("Missing request body".to_string()).into());
+            return Ok(AppError::Validation(ErrorCode::MissingBody, "Missing request body".to_string()).into());
         }
     };
 
     // Validate request fields
-    if registration_request.email.is_empty() || registration_request.password.is_empty() || registration_request.name.is_empty() {
-        return Ok(AppError::Validation("Email, password, and name are required".to_string()).into());
+    let mut invalid = Vec::new();
+    if registration_request.email.is_empty() {
+        invalid.push(FieldError::required("email", "Email is required"));
+    } else if !registration_request.email.contains('@') {
+        // Simple check; the identity provider has the final say
+        invalid.push(FieldError::new("email", ErrorCode::InvalidFormat, "Invalid email format"));
     }
-
-    // Validate email format (simple check)
-    if !registration_request.email.contains('@') {
-        return Ok(AppError::Validation("Invalid email format".to_string()).into());
+    if registration_request.password.is_empty() {
+        invalid.push(FieldError::required("password", "Password is required"));
     }
-
-    // Validate password complexity
-    if registration_request.password.len() < 8 {
-        return Ok(AppError::Validation("Password must be at least 8 characters".to_string()).into());
+    if registration_request.name.is_empty() {
+        invalid.push(FieldError::required("name", "Name is required"));
     }
-
-    // Connect to database
-    let db = match db::get_db_client().await {
-        Ok(client) => client,
-        Err(err) => {
-            error!("Failed to connect to database: {}", err);
-            return Ok(
-                AppError::Internal("Failed to connect to the database".to_string()).into(),
-            );
-        }
-    };
-
-    // Check if user with this email already exists
-    let existing_user: Result<Option<User>, _> = db
-        .query("SELECT * FROM user WHERE email = $email LIMIT 1")
-        .bind(("email", registration_request.email.clone()))
-        .await
-        .and_then(|mut res| res.take::<Option<User>>(0));
-
-    match existing_user {
-        Ok(Some(_)) => {
-            return Ok(
-                AppError::Validation("User with this email already exists".to_string()).into(),
-            );
-        }
-        Ok(None) => {
-            // Proceed with registration
-        }
-        Err(err) => {
-            error!("Database error during registration: {}", err);
-            return Ok(AppError::Database(err).into());
-        }
+    if !invalid.is_empty() {
+        return Ok(AppError::InvalidFields(invalid).into());
     }
 
-    // Create password hash
-    use argon2::{
-        password_hash::{
-            rand_core::OsRng,
-            PasswordHasher, SaltString
-        },
-        Argon2
-    };
-    
-    let salt = SaltString::generate(&mut OsRng);
-    let password_hash = match Argon2::default().hash_password(
-        registration_request.password.as_bytes(),
-        &salt
-    ) {
-        Ok(hash) => hash.to_string(),
-        Err(err) => {
-            error!("Failed to hash password: {}", err);
-            return Ok(
-                AppError::Internal("Failed to process credentials".to_string()).into(),
-            );
-        }
-    };
-
-    // Create new user
-    let new_user = User {
-        id: None, // Database will assign ID
-        email: registration_request.email,
-        password: Some(password_hash),
-        name: registration_request.name,
-        role: registration_request.role,
-        created_at: Some(chrono::Utc::now()),
-        last_login: None,
-        profile_image: None,
-        bio: None,
-    };
-
-    // Insert user into database
-    let created_user: Result<Option<User>, _> = db
-        .query("CREATE user CONTENT $data RETURN *")
-        .bind(("data", new_user.clone()))
-        .await
-        .and_then(|mut res| res.take::<Option<User>>(0));
-
-    let user = match created_user {
-        Ok(Some(user)) => user,
-        Ok(None) => {
-            error!("User created but not returned");
-            return Ok(AppError::Internal("Failed to retrieve created user".to_string()).into());
-        }
+    // Create the account with the identity provider
+    let user = match identity.sign_up(registration_request).await {
+        Ok(user) => user,
         Err(err) => {
             error!("Failed to create user: {}", err);
-            return Ok(AppError::Database(err).into());
+            return Ok(err.into());
         }
     };
 
-    // Create Cognito user (simulated here, actual implementation would call AWS Cognito)
-    info!("Creating Cognito user for {}", user.email);
-    // TODO: Implement actual Cognito user creation
+    info!("Registered user {}", user.email);
 
-    // Generate JWT token for immediate login
-    let user_id = user.id.as_ref().map(|id| id.to_string()).unwrap_or_default();
-    let token = match auth::generate_token(&user_id, &user.role.to_string()) {
-        Ok(token) => token,
+    // Issue an access token and a refresh token for a new session
+    let Some(user_id) = user.id.clone() else {
+        return Ok(AppError::Internal("User record has no ID".to_string()).into());
+    };
+    let tokens = match auth::open_session(repo, &user_id, &user.role.to_string(), &client).await {
+        Ok(tokens) => tokens,
         Err(err) => {
-            error!("Failed to generate token: {}", err);
+            error!("Failed to issue tokens: {}", err);
             return Ok(err.into());
         }
     };
@@ -149,18 +97,32 @@ pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGa
     // Create successful response
     let response_body = json!({
         "message": "Registration successful",
-        "token": token,
+        "token": tokens.token,
+        "refresh_token": tokens.refresh_token,
+        "expires_in": tokens.expires_in,
         "user": UserResponse::from(user)
     });
 
-    let mut headers = HeaderMap::new();
-    headers.insert("Content-Type", "application/json".parse().unwrap());
-
-    Ok(ApiGatewayProxyResponse {
-        status_code: 201, // Created
-        headers,
-        multi_value_headers: HeaderMap::new(),
-        body: Some(Body::from(response_body.to_string())),
-        is_base64_encoded: false,
-    })
-} 
\ No newline at end of file
+    Ok(json_response(201, response_body))
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::memory::connect;
+    use crate::testing::{json_request, native_identity, response_json};
+
+    #[tokio::test]
+    async fn registering_a_taken_email_is_refused() {
+        let repo = connect().await.unwrap();
+        let (identity, _) = native_identity(&repo);
+        let registration = || json_request(json!({"email": "ada@example.com", "password": "long enough", "name": "Ada", "role": "student"}));
+
+        let response = handle(&repo, &identity, registration()).await.unwrap();
+        assert_eq!(response.status_code, 201);
+
+        let response = handle(&repo, &identity, registration()).await.unwrap();
+        assert_eq!(response.status_code, 400);
+        assert_eq!(response_json(&response)["error"]["code"], "EMAIL_TAKEN");
+    }
+}
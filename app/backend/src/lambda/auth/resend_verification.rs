@@ -0,0 +1,67 @@
+use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
+use lambda_runtime::{Error, LambdaEvent};
+use serde_json::json;
+use tracing::error;
+
+use crate::common::error::{AppError, ErrorCode, FieldError};
+use crate::common::response::json_response;
+use crate::identity::{self, IdentityProvider};
+use crate::models::token::ResendVerificationRequest;
+use crate::repository;
+
+/// Lambda handler for resending an email verification code
+pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
+    // Connect to database
+    let repo = match repository::connect().await {
+        Ok(repo) => repo,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    let identity = match identity::connect(repo).await {
+        Ok(identity) => identity,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    handle(identity.as_ref(), event.payload).await
+}
+
+/// Send a new verification code with the given identity provider.
+///
+/// The native provider sends a code at most once every `email_verification_resend_secs`
+/// per user.
+pub async fn handle(identity: &dyn IdentityProvider, request: ApiGatewayProxyRequest) -> Result<ApiGatewayProxyResponse, Error> {
+    // Parse request body
+    let resend_request = match request.body {
+        Some(body) => match serde_json::from_str::<ResendVerificationRequest>(&body) {
+            Ok(req) => req,
+            Err(err) => {
+                error!("Failed to parse resend verification request: {}", err);
+                return Ok(AppError::Validation(ErrorCode::InvalidBody, format!("Invalid request format: {}", err)).into());
+            }
+        },
+        None => {
+            return Ok(AppError::Validation(ErrorCode::MissingBody, "Missing request body".to_string()).into());
+        }
+    };
+
+    if resend_request.email.is_empty() {
+        return Ok(AppError::InvalidFields(vec![FieldError::required("email", "Email is required")]).into());
+    }
+
+    // Unknown and already verified addresses get the same response as a successful send
+    if let Err(err) = identity.resend_confirmation(&resend_request.email).await {
+        error!("Failed to resend verification code to {}: {}", resend_request.email, err);
+        return Ok(err.into());
+    }
+
+    // Create accepted response
+    let response_body = json!({
+        "message": "If this email needs verification, a new code has been sent"
+    });
+
+    Ok(json_response(202, response_body))
+}
@@ -0,0 +1,162 @@
+use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
+use chrono::{DateTime, Utc};
+use lambda_runtime::{Error, LambdaEvent};
+use serde_json::json;
+use surrealdb::sql::Thing;
+use tracing::{error, info};
+
+use crate::common::error::{AppError, ErrorCode, FieldError};
+use crate::common::response::json_response;
+use crate::identity::{self, IdentityProvider};
+use crate::models::token::ResetPasswordRequest;
+use crate::repository::{self, TokenRepository};
+
+/// Lambda handler for choosing a new password with a reset token
+pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
+    // Connect to database
+    let repo = match repository::connect().await {
+        Ok(repo) => repo,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    let identity = match identity::connect(repo.clone()).await {
+        Ok(identity) => identity,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    handle(&repo, identity.as_ref(), event.payload).await
+}
+
+/// Reset a password with the given identity provider, signing the user out everywhere
+pub async fn handle(
+    repo: &impl TokenRepository,
+    identity: &dyn IdentityProvider,
+    request: ApiGatewayProxyRequest,
+) -> Result<ApiGatewayProxyResponse, Error> {
+    // Parse request body
+    let reset_request = match request.body {
+        Some(body) => match serde_json::from_str::<ResetPasswordRequest>(&body) {
+            Ok(req) => req,
+            Err(err) => {
+                error!("Failed to parse password reset request: {}", err);
+                return Ok(AppError::Validation(ErrorCode::InvalidBody, format!("Invalid request format: {}", err)).into());
+            }
+        },
+        None => {
+            return Ok(AppError::Validation(ErrorCode::MissingBody, "Missing request body".to_string()).into());
+        }
+    };
+
+    // Validate request fields
+    let mut invalid = Vec::new();
+    if reset_request.token.is_empty() {
+        invalid.push(FieldError::required("token", "Reset token is required"));
+    }
+    if reset_request.password.is_empty() {
+        invalid.push(FieldError::required("password", "Password is required"));
+    }
+    if !invalid.is_empty() {
+        return Ok(AppError::InvalidFields(invalid).into());
+    }
+
+    // Set the new password; this fails for unknown, expired and already used tokens alike
+    let now = Utc::now();
+    let user = match identity.reset_password(reset_request).await {
+        Ok(user) => user,
+        Err(err) => {
+            error!("Password reset failed: {}", err);
+            return Ok(err.into());
+        }
+    };
+
+    if let Err(err) = end_sessions(repo, &user, now).await {
+        error!("Failed to revoke sessions after password reset: {}", err);
+        return Ok(err.into());
+    }
+
+    info!("Password reset for {}", user);
+
+    // Create successful response
+    let response_body = json!({
+        "message": "Password has been reset. Please sign in with your new password."
+    });
+
+    Ok(json_response(200, response_body))
+}
+
+/// End every existing session: refresh tokens stop working, older access tokens and every
+/// personal access token are rejected and any other outstanding reset links are spent
+async fn end_sessions(repo: &impl TokenRepository, user: &Thing, at: DateTime<Utc>) -> Result<(), AppError> {
+    repo.revoke_user_sessions(user, at).await?;
+    repo.revoke_user_refresh_tokens(user, at).await?;
+    repo.revoke_user_access_tokens(user, at).await?;
+    repo.revoke_user_personal_tokens(user, at).await?;
+    repo.invalidate_password_resets(user, at).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::auth;
+    use crate::lambda::auth::forgot_password;
+    use crate::models::session::ClientInfo;
+    use crate::models::token::{PersonalToken, TokenScope};
+    use crate::models::user::{User, UserRole};
+    use crate::repository::memory::connect;
+    use crate::repository::UserRepository;
+    use crate::testing::{json_request, native_identity};
+
+    #[tokio::test]
+    async fn password_reset_is_single_use_and_ends_sessions() {
+        let repo = connect().await.unwrap();
+        let (identity, mailer) = native_identity(&repo);
+        let mut user = User::new("ada@example.com".to_string(), "Ada".to_string(), UserRole::Student);
+        user.password = Some(auth::hash_password("old password").unwrap());
+        let id = repo.create_user(user).await.unwrap().id.unwrap();
+        let session = auth::open_session(&repo, &id, "student", &ClientInfo::default()).await.unwrap();
+        let personal_token = auth::generate_personal_token();
+        let expires_at = Utc::now() + chrono::Duration::days(30);
+        let record = PersonalToken::new(
+            auth::hash_opaque_token(&personal_token),
+            id.clone(),
+            "cli".to_string(),
+            vec![TokenScope::CoursesRead],
+            expires_at,
+        );
+        repo.create_personal_token(record).await.unwrap();
+        assert!(auth::validate_personal_token(&repo, &personal_token).await.is_ok());
+
+        // Unknown addresses get the same response and no email
+        let response = forgot_password::handle(&identity, json_request(serde_json::json!({"email": "nobody@example.com"}))).await.unwrap();
+        assert_eq!(response.status_code, 202);
+        assert!(mailer.sent.lock().unwrap().is_empty());
+
+        let response = forgot_password::handle(&identity, json_request(serde_json::json!({"email": "ada@example.com"}))).await.unwrap();
+        assert_eq!(response.status_code, 202);
+        let body = mailer.sent.lock().unwrap()[0].body.clone();
+        let token: String = body[body.find("token=").unwrap() + 6..]
+            .chars()
+            .take_while(char::is_ascii_hexdigit)
+            .collect();
+
+        let reset = || json_request(serde_json::json!({"token": token, "password": "new password"}));
+        let response = handle(&repo, &identity, reset()).await.unwrap();
+        assert_eq!(response.status_code, 200);
+        let response = handle(&repo, &identity, reset()).await.unwrap();
+        assert_eq!(response.status_code, 400);
+
+        // The old session is gone
+        let revoked = repo.find_refresh_token(&auth::hash_opaque_token(&session.refresh_token)).await.unwrap().unwrap();
+        assert!(revoked.revoked_at.is_some());
+        let claims = auth::decode_token(&session.token).unwrap();
+        assert!(repo.is_access_token_revoked(&claims.jti, &id, claims.iat).await.unwrap());
+        assert!(matches!(
+            auth::validate_personal_token(&repo, &personal_token).await,
+            Err(AppError::Authentication(ErrorCode::TokenRevoked, _))
+        ));
+    }
+}
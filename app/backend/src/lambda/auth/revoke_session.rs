@@ -0,0 +1,66 @@
+use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
+use chrono::Utc;
+use lambda_runtime::{Error, LambdaEvent};
+use serde_json::json;
+use tracing::{error, info};
+
+use crate::common::error::{AppError, ErrorCode};
+use crate::common::extract::AuthContext;
+use crate::common::response::json_response;
+use crate::common::router;
+use crate::repository::{self, TokenRepository};
+
+/// Lambda handler for signing out a session
+pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
+    // Connect to database
+    let repo = match repository::connect().await {
+        Ok(repo) => repo,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    handle(&repo, event.payload).await
+}
+
+/// Sign out one of the caller's sessions, such as a device left signed in elsewhere.
+///
+/// Its refresh tokens are revoked and its access tokens stop validating immediately.
+pub async fn handle(repo: &impl TokenRepository, request: ApiGatewayProxyRequest) -> Result<ApiGatewayProxyResponse, Error> {
+    // Extract session ID from path parameters
+    let session_id = match router::path_param::<String>(&request, "id")
+        .and_then(|id| repository::record_id("session", &id))
+    {
+        Ok(id) => id,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    // Authenticate the caller
+    let auth = match AuthContext::from_request(&request) {
+        Ok(auth) => auth,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    // Sessions of other users are reported as missing, not forbidden
+    match repo.revoke_session(&auth.user, &session_id, Utc::now()).await {
+        Ok(true) => info!("Revoked session {} of {}", session_id, auth.user),
+        Ok(false) => {
+            return Ok(AppError::NotFound(ErrorCode::SessionNotFound, format!("Session {} not found", session_id)).into());
+        }
+        Err(err) => {
+            error!("Failed to revoke session: {}", err);
+            return Ok(err.into());
+        }
+    }
+
+    let response_body = json!({
+        "message": "Session signed out",
+        "id": session_id.to_string()
+    });
+
+    Ok(json_response(200, response_body))
+}
@@ -0,0 +1,68 @@
+use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
+use chrono::Utc;
+use lambda_runtime::{Error, LambdaEvent};
+use serde_json::json;
+use tracing::{error, info};
+
+use crate::common::error::{AppError, ErrorCode};
+use crate::common::extract::AuthContext;
+use crate::common::response::json_response;
+use crate::common::router;
+use crate::repository::{self, TokenRepository};
+
+/// Lambda handler for revoking a personal access token
+pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
+    // Connect to database
+    let repo = match repository::connect().await {
+        Ok(repo) => repo,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    handle(&repo, event.payload).await
+}
+
+/// Revoke one of the caller's personal access tokens
+pub async fn handle(repo: &impl TokenRepository, request: ApiGatewayProxyRequest) -> Result<ApiGatewayProxyResponse, Error> {
+    // Extract token ID from path parameters
+    let token_id = match router::path_param::<String>(&request, "id")
+        .and_then(|id| repository::record_id("personal_token", &id))
+    {
+        Ok(id) => id,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    // Authenticate the caller
+    let auth = match AuthContext::from_request(&request) {
+        Ok(auth) => auth,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    // Tokens of other users are reported as missing, not forbidden
+    match repo.revoke_personal_token(&auth.user, &token_id, Utc::now()).await {
+        Ok(true) => info!("Revoked personal access token {}", token_id),
+        Ok(false) => {
+            return Ok(AppError::NotFound(
+                ErrorCode::TokenNotFound,
+                format!("Personal access token {} not found", token_id),
+            )
+            .into());
+        }
+        Err(err) => {
+            error!("Failed to revoke personal access token: {}", err);
+            return Ok(err.into());
+        }
+    }
+
+    let response_body = json!({
+        "message": "Personal access token revoked",
+        "id": token_id.to_string()
+    });
+
+    Ok(json_response(200, response_body))
+}
@@ -1,15 +1,13 @@
 use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
-use aws_lambda_events::encodings::Body;
-use http::HeaderMap;
 use lambda_runtime::{Error, LambdaEvent};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::collections::HashMap;
-use tracing::{error, info};
+use tracing::error;
 
-use crate::common::db;
-use crate::common::error::AppError;
-use crate::models::user::User;
+use crate::common::error::{AppError, ErrorCode, FieldError};
+use crate::common::response::json_response;
+use crate::identity::{self, IdentityProvider};
+use crate::repository;
 
 /// Email verification request model
 #[derive(Debug, Serialize, Deserialize)]
@@ -20,94 +18,121 @@ struct VerificationRequest {
 
 /// Lambda handler for email verification
 pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
-    let request = event.payload;
+    // Connect to database
+    let repo = match repository::connect().await {
+        Ok(repo) => repo,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    let identity = match identity::connect(repo).await {
+        Ok(identity) => identity,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    handle(identity.as_ref(), event.payload).await
+}
 
+/// Verify an email address with the given identity provider
+pub async fn handle(identity: &dyn IdentityProvider, request: ApiGatewayProxyRequest) -> Result<ApiGatewayProxyResponse, Error> {
     // Parse request body
     let verification_request = match request.body {
         Some(body) => match serde_json::from_str::<VerificationRequest>(&body) {
             Ok(req) => req,
             Err(err) => {
                 error!("Failed to parse verification request: {}", err);
-                return Ok(AppError::Validation(format!("Invalid request format: {}", err)).into());
+                return Ok(AppError::Validation(ErrorCode::InvalidBody, format!("Invalid request format: {}", err)).into());
             }
         },
         None => {
-            return Ok(AppError::Validation("Missing request body".to_string()).into());
+            return Ok(AppError::Validation(ErrorCode::MissingBody, "Missing request body".to_string()).into());
         }
     };
 
     // Validate request fields
-    if verification_request.email.is_empty() || verification_request.verification_code.is_empty() {
-        return Ok(AppError::Validation("Email and verification code are required".to_string()).into());
+    let mut invalid = Vec::new();
+    if verification_request.email.is_empty() {
+        invalid.push(FieldError::required("email", "Email is required"));
+    }
+    if verification_request.verification_code.is_empty() {
+        invalid.push(FieldError::required("verification_code", "Verification code is required"));
+    }
+    if !invalid.is_empty() {
+        return Ok(AppError::InvalidFields(invalid).into());
     }
 
-    // In a real implementation, this would validate against AWS Cognito or a verification code stored in the database
-    // For this example, we'll simulate the verification process
-    
-    // Connect to database
-    let db = match db::get_db_client().await {
-        Ok(client) => client,
-        Err(err) => {
-            error!("Failed to connect to database: {}", err);
-            return Ok(
-                AppError::Internal("Failed to connect to the database".to_string()).into(),
-            );
-        }
-    };
-
-    // Find user by email
-    let user_result: Result<Option<User>, _> = db
-        .query("SELECT * FROM user WHERE email = $email LIMIT 1")
-        .bind(("email", verification_request.email.clone()))
+    if let Err(err) = identity
+        .confirm(&verification_request.email, &verification_request.verification_code)
         .await
-        .and_then(|mut res| res.take::<Option<User>>(0));
-
-    let user = match user_result {
-        Ok(Some(user)) => user,
-        Ok(None) => {
-            return Ok(
-                AppError::NotFound("User not found".to_string()).into(),
-            );
-        }
-        Err(err) => {
-            error!("Database error during verification: {}", err);
-            return Ok(AppError::Database(err).into());
-        }
-    };
-
-    // In a real implementation, we would verify the code against what was sent to the user
-    // For this example, we'll accept any 6-digit code that starts with "123"
-    if !verification_request.verification_code.starts_with("123") || verification_request.verification_code.len() != 6 {
-        return Ok(
-            AppError::Validation("Invalid verification code".to_string()).into(),
-        );
+    {
+        error!("Email verification failed for {}: {}", verification_request.email, err);
+        return Ok(err.into());
     }
 
-    // Update user's verification status in database
-    // In a real implementation, we would have a verified field in the user model
-    info!("Verifying email for user: {}", user.email);
-    
-    // Simulate updating the user's verification status
-    let _: Result<(), _> = db
-        .query("UPDATE user SET email_verified = true WHERE id = $id")
-        .bind(("id", user.id))
-        .await
-        .map(|_| ());
+    Ok(verified_response(&verification_request.email))
+}
 
+/// Response for a verified address
+fn verified_response(email: &str) -> ApiGatewayProxyResponse {
     // Create successful response
     let response_body = json!({
         "message": "Email verified successfully",
-        "email": verification_request.email
+        "email": email
     });
 
-    let mut headers = HeaderMap::new();
-    headers.insert("Content-Type", "application/json".parse().unwrap());
-
-    Ok(ApiGatewayProxyResponse {
-        status_code: 200,
-        headers,
-        multi_value_headers: HeaderMap::new(),
-        body: Some(Body::from(response_body.to_string())),
-        is_base64_encoded: false,
-    })
-} 
\ No newline at end of file
+    json_response(200, response_body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lambda::auth::{register, resend_verification};
+    use crate::repository::memory::connect;
+    use crate::repository::UserRepository;
+    use crate::testing::{json_request, native_identity, sent_code};
+
+    #[tokio::test]
+    async fn email_verification_codes_are_checked_and_limited() {
+        let repo = connect().await.unwrap();
+        let (identity, mailer) = native_identity(&repo);
+
+        let registration = serde_json::json!({"email": "ada@example.com", "password": "long enough", "name": "Ada", "role": "student"});
+        let response = register::handle(&repo, &identity, json_request(registration)).await.unwrap();
+        assert_eq!(response.status_code, 201);
+
+        let (code, wrong) = sent_code(&mailer, 0);
+        let verify = |code: &str| json_request(serde_json::json!({"email": "ada@example.com", "verification_code": code}));
+
+        let response = handle(&identity, verify(wrong)).await.unwrap();
+        assert_eq!(response.status_code, 400);
+        let response = handle(&identity, verify(&code)).await.unwrap();
+        assert_eq!(response.status_code, 200);
+        assert!(repo.find_user_by_email("ada@example.com").await.unwrap().unwrap().email_verified);
+
+        // Verified accounts are not sent another code
+        let response = resend_verification::handle(&identity, json_request(serde_json::json!({"email": "ada@example.com"}))).await.unwrap();
+        assert_eq!(response.status_code, 202);
+        assert_eq!(mailer.sent.lock().unwrap().len(), 1);
+
+        // Once the attempts are used up even the right code is refused
+        let registration = serde_json::json!({"email": "grace@example.com", "password": "long enough", "name": "Grace", "role": "student"});
+        let response = register::handle(&repo, &identity, json_request(registration)).await.unwrap();
+        assert_eq!(response.status_code, 201);
+        let (code, wrong) = sent_code(&mailer, 1);
+        let verify = |code: &str| json_request(serde_json::json!({"email": "grace@example.com", "verification_code": code}));
+
+        for _ in 0..crate::common::config::CONFIG.email_verification_max_attempts {
+            let response = handle(&identity, verify(wrong)).await.unwrap();
+            assert_eq!(response.status_code, 400);
+        }
+        let response = handle(&identity, verify(&code)).await.unwrap();
+        assert_eq!(response.status_code, 429);
+
+        // Resending is throttled while the last code is fresh
+        let response = resend_verification::handle(&identity, json_request(serde_json::json!({"email": "grace@example.com"}))).await.unwrap();
+        assert_eq!(response.status_code, 429);
+    }
+}
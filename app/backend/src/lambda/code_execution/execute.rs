@@ -1,26 +1,17 @@
 use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
-use aws_lambda_events::encodings::Body;
-use http::HeaderMap;
 use lambda_runtime::{Error, LambdaEvent};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::collections::HashMap;
 use std::time::{Duration, Instant};
-use tracing::{error, info};
+use tracing::field::Empty;
+use tracing::{error, info, info_span, Instrument};
 
-use crate::common::auth;
-use crate::common::error::AppError;
-
-/// Programming languages supported for code execution
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
-pub enum Language {
-    Python,
-    Rust,
-    JavaScript,
-    Java,
-    Cpp,
-}
+use crate::common::config::CONFIG;
+use crate::common::error::{AppError, ErrorCode, FieldError};
+use crate::common::extract::AuthContext;
+use crate::common::metrics;
+use crate::common::response::json_response;
+use crate::models::execution::{ExecutionStatus, Language};
 
 /// Code execution request
 #[derive(Debug, Serialize, Deserialize)]
@@ -41,76 +32,50 @@ pub struct ExecutionResult {
     pub memory_usage_kb: Option<u64>,
 }
 
-/// Execution status codes
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
-pub enum ExecutionStatus {
-    Success,
-    CompileError,
-    RuntimeError,
-    Timeout,
-    MemoryLimitExceeded,
-}
-
 /// Lambda handler for code execution
 pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
     let request = event.payload;
 
     // Parse request body
-    let execute_request = match request.body {
-        Some(body) => match serde_json::from_str::<ExecuteCodeRequest>(&body) {
+    let execute_request = match &request.body {
+        Some(body) => match serde_json::from_str::<ExecuteCodeRequest>(body) {
             Ok(req) => req,
             Err(err) => {
                 error!("Failed to parse code execution request: {}", err);
-                return Ok(AppError::Validation(format!("Invalid request format: {}", err)).into());
+                return Ok(AppError::Validation(ErrorCode::InvalidBody, format!("Invalid request format: {}", err)).into());
             }
         },
         None => {
-            return Ok(AppError::Validation("Missing request body".to_string()).into());
+            return Ok(AppError::Validation(ErrorCode::MissingBody, "Missing request body".to_string()).into());
         }
     };
 
     // Validate request fields
     if execute_request.code.is_empty() {
-        return Ok(AppError::Validation("Code cannot be empty".to_string()).into());
+        return Ok(AppError::InvalidFields(vec![FieldError::required("code", "Code cannot be empty")]).into());
     }
 
-    // Extract and validate authorization token
-    let token = match request.headers.get("Authorization") {
-        Some(auth_header) => {
-            let auth_str = match auth_header.to_str() {
-                Ok(s) => s,
-                Err(_) => {
-                    return Ok(AppError::Authentication("Invalid authorization header".to_string()).into());
-                }
-            };
-            let parts: Vec<&str> = auth_str.split_whitespace().collect();
-            if parts.len() != 2 || parts[0].to_lowercase() != "bearer" {
-                return Ok(AppError::Authentication("Invalid authorization format".to_string()).into());
-            }
-            parts[1]
-        }
-        None => {
-            return Ok(AppError::Authentication("Missing authorization header".to_string()).into());
-        }
-    };
-
-    // Validate token and extract claims
-    let claims = match auth::validate_token(token) {
-        Ok(claims) => claims,
+    // Authenticate the caller
+    let auth = match AuthContext::from_request(&request) {
+        Ok(auth) => auth,
         Err(err) => {
             return Ok(err.into());
         }
     };
 
-    // Set execution timeout (default: 5 seconds, max: 30 seconds)
-    let timeout = Duration::from_secs(execute_request.timeout_seconds.unwrap_or(5).min(30));
+    // Set execution timeout (defaults and upper bound come from configuration)
+    let timeout = Duration::from_secs(
+        execute_request
+            .timeout_seconds
+            .unwrap_or(CONFIG.code_execution_timeout_secs)
+            .min(CONFIG.code_execution_max_timeout_secs),
+    );
 
     // Log the execution request
     info!(
         "Executing {} code for user {}, code length: {} chars",
         format!("{:?}", execute_request.language).to_lowercase(),
-        claims.sub,
+        auth.user,
         execute_request.code.len()
     );
 
@@ -118,25 +83,46 @@ pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGa
     // For now, simulate execution with a simulated result
     let start_time = Instant::now();
     
-    // Simulate a delay
-    tokio::time::sleep(Duration::from_millis(500)).await;
+    // Simulate a delay, bounded by the execution timeout
+    let sandbox_span = info_span!(
+        "code_execution.sandbox",
+        code.language = ?execute_request.language,
+        timeout_secs = timeout.as_secs(),
+        timed_out = Empty,
+    );
+    let timed_out = tokio::time::timeout(timeout, tokio::time::sleep(Duration::from_millis(500)))
+        .instrument(sandbox_span.clone())
+        .await
+        .is_err();
+    sandbox_span.record("timed_out", timed_out);
     
     // Create a simulated execution result
-    let result = match execute_request.language {
-        Language::Python => simulate_python_execution(&execute_request.code, execute_request.input.as_deref()),
-        Language::Rust => simulate_rust_execution(&execute_request.code, execute_request.input.as_deref()),
-        Language::JavaScript => simulate_javascript_execution(&execute_request.code, execute_request.input.as_deref()),
-        Language::Java => simulate_java_execution(&execute_request.code, execute_request.input.as_deref()),
-        Language::Cpp => simulate_cpp_execution(&execute_request.code, execute_request.input.as_deref()),
-    };
+    let result = info_span!("code_execution.output").in_scope(|| {
+        if timed_out {
+            (
+                String::new(),
+                format!("Execution timed out after {} seconds", timeout.as_secs()),
+                ExecutionStatus::Timeout,
+            )
+        } else {
+            match execute_request.language {
+                Language::Python => simulate_python_execution(&execute_request.code, execute_request.input.as_deref()),
+                Language::Rust => simulate_rust_execution(&execute_request.code, execute_request.input.as_deref()),
+                Language::JavaScript => simulate_javascript_execution(&execute_request.code, execute_request.input.as_deref()),
+                Language::Java => simulate_java_execution(&execute_request.code, execute_request.input.as_deref()),
+                Language::Cpp => simulate_cpp_execution(&execute_request.code, execute_request.input.as_deref()),
+            }
+        }
+    });
     
     // Create response
-    let elapsed = start_time.elapsed().as_millis() as u64;
+    let elapsed = start_time.elapsed();
+    metrics::record_execution(&execute_request.language, &result.2, elapsed);
     
     let execution_result = ExecutionResult {
         stdout: result.0,
         stderr: result.1,
-        execution_time_ms: elapsed,
+        execution_time_ms: elapsed.as_millis() as u64,
         status: result.2,
         memory_usage_kb: Some(1024), // Simulated memory usage
     };
@@ -146,20 +132,11 @@ pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGa
         "result": execution_result
     });
 
-    let mut headers = HeaderMap::new();
-    headers.insert("Content-Type", "application/json".parse().unwrap());
-
-    Ok(ApiGatewayProxyResponse {
-        status_code: 200,
-        headers,
-        multi_value_headers: HeaderMap::new(),
-        body: Some(Body::from(response_body.to_string())),
-        is_base64_encoded: false,
-    })
+    Ok(json_response(200, response_body))
 }
 
 // Simulate Python code execution
-fn simulate_python_execution(code: &str, input: Option<&str>) -> (String, String, ExecutionStatus) {
+fn simulate_python_execution(code: &str, _input: Option<&str>) -> (String, String, ExecutionStatus) {
     if code.contains("syntax error") {
         return (
             String::new(),
@@ -192,7 +169,7 @@ fn simulate_python_execution(code: &str, input: Option<&str>) -> (String, String
 }
 
 // Simulate Rust code execution
-fn simulate_rust_execution(code: &str, input: Option<&str>) -> (String, String, ExecutionStatus) {
+fn simulate_rust_execution(code: &str, _input: Option<&str>) -> (String, String, ExecutionStatus) {
     if code.contains("compile_error") {
         return (
             String::new(),
@@ -225,7 +202,7 @@ fn simulate_rust_execution(code: &str, input: Option<&str>) -> (String, String,
 }
 
 // Simulate JavaScript code execution
-fn simulate_javascript_execution(code: &str, input: Option<&str>) -> (String, String, ExecutionStatus) {
+fn simulate_javascript_execution(code: &str, _input: Option<&str>) -> (String, String, ExecutionStatus) {
     if code.contains("syntax error") {
         return (
             String::new(),
@@ -258,7 +235,7 @@ fn simulate_javascript_execution(code: &str, input: Option<&str>) -> (String, St
 }
 
 // Simulate Java code execution
-fn simulate_java_execution(code: &str, input: Option<&str>) -> (String, String, ExecutionStatus) {
+fn simulate_java_execution(code: &str, _input: Option<&str>) -> (String, String, ExecutionStatus) {
     if code.contains("syntax error") {
         return (
             String::new(),
@@ -291,7 +268,7 @@ fn simulate_java_execution(code: &str, input: Option<&str>) -> (String, String,
 }
 
 // Simulate C++ code execution
-fn simulate_cpp_execution(code: &str, input: Option<&str>) -> (String, String, ExecutionStatus) {
+fn simulate_cpp_execution(code: &str, _input: Option<&str>) -> (String, String, ExecutionStatus) {
     if code.contains("syntax error") {
         return (
             String::new(),
@@ -1,81 +1,73 @@
 use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
-use aws_lambda_events::encodings::Body;
 use lambda_runtime::{Error, LambdaEvent};
 use serde_json::json;
-use tracing::{error, info};
-use http::HeaderMap;
-use surrealdb::sql::Thing;
+use tracing::error;
 
-use crate::common::auth;
-use crate::common::db;
-use crate::common::error::AppError;
+use crate::common::audit;
+use crate::common::error::{AppError, ErrorCode, FieldError};
+use crate::common::extract::AuthContext;
+use crate::common::policy::{self, Action};
+use crate::common::response::json_response;
+use crate::models::audit::AuditAction;
 use crate::models::course::{Course, CourseCreateRequest};
-use crate::models::user::UserRole;
+use crate::repository::{self, AuditRepository, CourseRepository};
 
 /// Lambda handler for course creation
 pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
-    let request = event.payload;
+    // Connect to database
+    let repo = match repository::connect().await {
+        Ok(repo) => repo,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
+
+    handle(&repo, event.payload).await
+}
 
+/// Create a course using the given repository
+pub async fn handle(repo: &(impl CourseRepository + AuditRepository), request: ApiGatewayProxyRequest) -> Result<ApiGatewayProxyResponse, Error> {
     // Parse request body
-    let course_request = match request.body {
-        Some(body) => match serde_json::from_str::<CourseCreateRequest>(&body) {
+    let course_request = match &request.body {
+        Some(body) => match serde_json::from_str::<CourseCreateRequest>(body) {
             Ok(req) => req,
             Err(err) => {
                 error!("Failed to parse course creation request: {}", err);
-                return Ok(AppError::Validation(format!("Invalid request format: {}", err)).into());
+                return Ok(AppError::Validation(ErrorCode::InvalidBody, format!("Invalid request format: {}", err)).into());
             }
         },
         None => {
-            return Ok(AppError::Validation("Missing request body".to_string()).into());
+            return Ok(AppError::Validation(ErrorCode::MissingBody, "Missing request body".to_string()).into());
         }
     };
 
     // Validate request fields
-    if course_request.title.is_empty() || course_request.description.is_empty() {
-        return Ok(AppError::Validation("Title and description are required".to_string()).into());
+    let mut invalid = Vec::new();
+    if course_request.title.is_empty() {
+        invalid.push(FieldError::required("title", "Title is required"));
+    }
+    if course_request.description.is_empty() {
+        invalid.push(FieldError::required("description", "Description is required"));
+    }
+    if !invalid.is_empty() {
+        return Ok(AppError::InvalidFields(invalid).into());
     }
 
-    // Extract and validate authorization token
-    let token = match request.headers.get("Authorization") {
-        Some(auth_header) => {
-            let auth_str = auth_header.to_str().unwrap_or_default();
-            let parts: Vec<&str> = auth_str.split_whitespace().collect();
-            if parts.len() != 2 || parts[0].to_lowercase() != "bearer" {
-                return Ok(AppError::Authentication("Invalid authorization format".to_string()).into());
-            }
-            parts[1]
-        }
-        None => {
-            return Ok(AppError::Authentication("Missing authorization header".to_string()).into());
-        }
-    };
-
-    // Validate token and extract claims
-    let claims = match auth::validate_token(token) {
-        Ok(claims) => claims,
+    // Authenticate the caller
+    let auth = match AuthContext::from_request(&request) {
+        Ok(auth) => auth,
         Err(err) => {
             return Ok(err.into());
         }
     };
 
-    // Check if user has educator or admin role
-    if claims.role != "educator" && claims.role != "admin" {
-        return Ok(AppError::Authorization("Only educators and admins can create courses".to_string()).into());
+    // Check the caller may create courses
+    if let Err(err) = policy::authorize(&auth, Action::CreateCourse, None) {
+        return Ok(err.into());
     }
 
-    // Connect to database
-    let db = match db::get_db_client().await {
-        Ok(client) => client,
-        Err(err) => {
-            error!("Failed to connect to database: {}", err);
-            return Ok(
-                AppError::Internal("Failed to connect to the database".to_string()).into(),
-            );
-        }
-    };
-    
     // Create user reference
-    let educator = Thing::from(("user", &*claims.sub));
+    let educator = auth.user.clone();
 
     // Create course object
     let new_course = Course {
@@ -93,37 +85,55 @@ pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGa
     };
 
     // Insert course into database
-    let created_course: Result<Option<Course>, _> = db
-        .query("CREATE course CONTENT $data RETURN *")
-        .bind(("data", new_course))
-        .await
-        .and_then(|mut res| res.take::<Option<Course>>(0));
-
-    let course = match created_course {
-        Ok(Some(course)) => course,
-        Ok(None) => {
-            return Ok(AppError::Internal("Course created but not returned".to_string()).into());
-        }
+    let course = match repo.create_course(new_course).await {
+        Ok(course) => course,
         Err(err) => {
             error!("Failed to create course: {}", err);
-            return Ok(AppError::Database(err).into());
+            return Ok(err.into());
         }
     };
 
+    if let Some(id) = &course.id {
+        audit::record(repo, &request, &auth, AuditAction::CourseCreate, id, None, Some(&course)).await;
+    }
+
     // Create successful response
     let response_body = json!({
         "message": "Course created successfully",
         "course": course
     });
 
-    let mut headers = HeaderMap::new();
-    headers.insert("Content-Type", "application/json".parse().unwrap());
-
-    Ok(ApiGatewayProxyResponse {
-        status_code: 201, // Created
-        headers,
-        multi_value_headers: HeaderMap::new(),
-        body: Some(Body::from(response_body.to_string())),
-        is_base64_encoded: false,
-    })
-} 
\ No newline at end of file
+    Ok(json_response(201, response_body))
+} 
+
+#[cfg(test)]
+mod tests {
+    use surrealdb::sql::Thing;
+
+    use super::*;
+    use crate::models::user::UserRole;
+    use crate::repository::memory::connect;
+    use crate::repository::CourseFilter;
+    use crate::testing::request_as;
+
+    #[tokio::test]
+    async fn create_course_handler_requires_educator() {
+        let repo = connect().await.unwrap();
+        let body = r#"{"title":"Rust 101","description":"Ownership","difficulty":"beginner","category":"rust","thumbnail_url":"","modules":[],"tags":[]}"#;
+
+        let mut request = request_as(&repo, &Thing::from(("user", "student")), UserRole::Student).await;
+        request.body = Some(body.to_string());
+        let response = handle(&repo, request).await.unwrap();
+        assert_eq!(response.status_code, 403);
+
+        let mut request = request_as(&repo, &Thing::from(("user", "educator")), UserRole::Educator).await;
+        request.body = Some(body.to_string());
+        let response = handle(&repo, request).await.unwrap();
+        assert_eq!(response.status_code, 201);
+
+        let filter = CourseFilter { include_unpublished: true, limit: 20, ..Default::default() };
+        let (courses, _) = repo.list_courses(&filter).await.unwrap();
+        assert_eq!(courses.len(), 1);
+        assert_eq!(courses[0].educator, Thing::from(("user", "educator")));
+    }
+}
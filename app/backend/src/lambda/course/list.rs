@@ -1,16 +1,13 @@
 use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
-use aws_lambda_events::encodings::Body;
-use http::HeaderMap;
 use lambda_runtime::{Error, LambdaEvent};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::collections::HashMap;
-use tracing::{error, info};
+use tracing::error;
 
-use crate::common::auth;
-use crate::common::db;
-use crate::common::error::AppError;
-use crate::models::course::Course;
+use crate::common::extract::AuthContext;
+use crate::common::policy::{self, Action};
+use crate::common::response::json_response;
+use crate::repository::{self, CourseFilter, CourseRepository};
 
 /// Query parameters for course listing
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -25,8 +22,19 @@ struct CourseListParams {
 
 /// Lambda handler for listing courses
 pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
-    let request = event.payload;
+    // Connect to database
+    let repo = match repository::connect().await {
+        Ok(repo) => repo,
+        Err(err) => {
+            return Ok(err.into());
+        }
+    };
 
+    handle(&repo, event.payload).await
+}
+
+/// List courses using the given repository
+pub async fn handle(repo: &impl CourseRepository, request: ApiGatewayProxyRequest) -> Result<ApiGatewayProxyResponse, Error> {
     // Parse query parameters
     let mut params = CourseListParams::default();
     
@@ -47,144 +55,43 @@ pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGa
     let limit = params.limit.unwrap_or(20).min(100); // Max 100 items per page
     let offset = params.offset.unwrap_or(0);
 
-    // Extract and validate authorization token
-    let token = match request.headers.get("Authorization") {
-        Some(auth_header) => {
-            let auth_str = match auth_header.to_str() {
-                Ok(s) => s,
-                Err(_) => {
-                    return Ok(AppError::Authentication("Invalid authorization header".to_string()).into());
-                }
-            };
-            let parts: Vec<&str> = auth_str.split_whitespace().collect();
-            if parts.len() != 2 || parts[0].to_lowercase() != "bearer" {
-                return Ok(AppError::Authentication("Invalid authorization format".to_string()).into());
-            }
-            parts[1]
-        }
-        None => {
-            return Ok(AppError::Authentication("Missing authorization header".to_string()).into());
-        }
-    };
-
-    // Validate token and extract claims
-    let claims = match auth::validate_token(token) {
-        Ok(claims) => claims,
+    // Get the authenticated caller
+    let auth = match AuthContext::from_request(&request) {
+        Ok(auth) => auth,
         Err(err) => {
             return Ok(err.into());
         }
     };
 
-    // Connect to database
-    let db = match db::get_db_client().await {
-        Ok(client) => client,
-        Err(err) => {
-            error!("Failed to connect to database: {}", err);
-            return Ok(
-                AppError::Internal("Failed to connect to the database".to_string()).into(),
-            );
-        }
-    };
-
-    // Build the query
-    let mut query_builder = String::from("SELECT * FROM course");
-    let mut conditions = Vec::new();
-    let mut bindings = HashMap::new();
-    
-    // Add conditions based on params
-    // Only show published courses unless include_unpublished is true and user is admin/educator
-    let include_unpublished = params.include_unpublished.unwrap_or(false) && 
-                              (claims.role == "admin" || claims.role == "educator");
-    
-    if !include_unpublished {
-        conditions.push("is_published = true");
-    }
-    
-    // Add category filter
-    if let Some(category) = &params.category {
-        conditions.push("category = $category");
-        bindings.insert("category", category.to_string());
-    }
-    
-    // Add difficulty filter
-    if let Some(difficulty) = &params.difficulty {
-        conditions.push("difficulty = $difficulty");
-        bindings.insert("difficulty", difficulty.to_string());
-    }
-    
-    // Add text search if query parameter is provided
-    if let Some(query) = &params.query {
-        conditions.push("(title CONTAINS $query OR description CONTAINS $query)");
-        bindings.insert("query", query.to_string());
-    }
-    
-    // Add WHERE clause if conditions exist
-    if !conditions.is_empty() {
-        query_builder.push_str(" WHERE ");
-        query_builder.push_str(&conditions.join(" AND "));
-    }
-    
-    // Add ORDER BY, LIMIT and OFFSET
-    query_builder.push_str(" ORDER BY created_at DESC LIMIT $limit OFFSET $offset");
-    bindings.insert("limit", limit.to_string());
-    bindings.insert("offset", offset.to_string());
-    
-    // Execute the query
-    let mut db_query = db.query(&query_builder);
-    
-    // Apply all bindings
-    for (key, value) in bindings.clone() {
-        db_query = db_query.bind((key, value));
-    }
-    
-    // Execute the query and parse the results
-    let courses: Result<Vec<Course>, _> = db_query
-        .await
-        .and_then(|mut res| res.take::<Vec<Course>>(0));
-    
-    // Also get the total count
-    let mut total_count_query = String::from("SELECT count() FROM course");
-    
-    // Add WHERE clause for count query if conditions exist
-    if !conditions.is_empty() {
-        let where_clause = format!(" WHERE {}", conditions.join(" AND "));
-        total_count_query.push_str(&where_clause);
-    }
-        
-    let mut db_count_query = db.query(&total_count_query);
-    
-    // Apply all bindings except limit and offset
-    for (key, value) in bindings {
-        if key != "limit" && key != "offset" {
-            db_count_query = db_count_query.bind((key, value));
+    // Only show published courses unless include_unpublished is true and the caller may
+    // read drafts: every draft, or just their own
+    let mut include_unpublished = false;
+    let mut drafts_of = None;
+    if params.include_unpublished.unwrap_or(false) {
+        if policy::is_allowed(&auth.role, Action::ReadDraftCourse, false) {
+            include_unpublished = true;
+        } else if policy::is_allowed(&auth.role, Action::ReadDraftCourse, true) {
+            include_unpublished = true;
+            drafts_of = Some(auth.user.clone());
         }
     }
-    
-    // Execute the count query
-    let total_count_result = db_count_query.await;
-    let total_count = match total_count_result {
-        Ok(mut res) => {
-            match res.take::<Option<usize>>(0) {
-                Ok(Some(count)) => count as i64,
-                Ok(None) => 0,
-                Err(err) => {
-                    error!("Error parsing count: {}", err);
-                    0
-                }
-            }
-        },
-        Err(err) => {
-            error!("Error executing count query: {}", err);
-            0
-        }
+
+    let filter = CourseFilter {
+        include_unpublished,
+        drafts_of,
+        category: params.category,
+        difficulty: params.difficulty,
+        query: params.query,
+        limit,
+        offset,
     };
     
-    // Handle database query results
-    let courses = match courses {
-        Ok(courses) => courses,
+    // Execute the query and get the total count
+    let (courses, total_count) = match repo.list_courses(&filter).await {
+        Ok(result) => result,
         Err(err) => {
             error!("Database error when listing courses: {}", err);
-            return Ok(AppError::Database(err).into());
+            return Ok(err.into());
         }
     };
 
@@ -198,14 +105,5 @@ pub async fn handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGa
         }
     });
 
-    let mut headers = HeaderMap::new();
-    headers.insert("Content-Type", "application/json".parse().unwrap());
-
-    Ok(ApiGatewayProxyResponse {
-        status_code: 200,
-        headers,
-        multi_value_headers: HeaderMap::new(),
-        body: Some(Body::from(response_body.to_string())),
-        is_base64_encoded: false,
-    })
+    Ok(json_response(200, response_body))
 } 
\ No newline at end of file
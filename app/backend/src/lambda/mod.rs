@@ -1,6 +1,62 @@
+pub mod admin;
 pub mod auth;
 pub mod course;
 pub mod quiz;
 pub mod user;
 pub mod code_execution;
-pub mod forum; 
\ No newline at end of file
+pub mod forum;
+
+use http::Method;
+
+use crate::common::policy::Action;
+use crate::common::router::{AuthRequirement, Router};
+use crate::models::token::TokenScope;
+
+/// Build the route table for every API endpoint served by this function
+pub fn router() -> Router {
+    Router::new()
+        // Authentication routes
+        .route(Method::POST, "/auth/login", AuthRequirement::Public, auth::login::handler)
+        .route(Method::POST, "/auth/register", AuthRequirement::Public, auth::register::handler)
+        .route(Method::POST, "/auth/verify", AuthRequirement::Public, auth::verify::handler)
+        .route(Method::POST, "/auth/verify/resend", AuthRequirement::Public, auth::resend_verification::handler)
+        .route(Method::POST, "/auth/refresh", AuthRequirement::Public, auth::refresh::handler)
+        .route(Method::POST, "/auth/logout", AuthRequirement::Authenticated, auth::logout::handler)
+        .route(Method::POST, "/auth/mfa/verify", AuthRequirement::Public, auth::mfa_verify::handler)
+        .route(Method::POST, "/auth/mfa/totp/enroll", AuthRequirement::Public, auth::mfa_enroll::handler)
+        .route(Method::POST, "/auth/mfa/totp/confirm", AuthRequirement::Public, auth::mfa_confirm::handler)
+        .route(Method::POST, "/auth/mfa/totp/disable", AuthRequirement::Authenticated, auth::mfa_disable::handler)
+        .route(Method::POST, "/auth/oidc/{provider}/authorize", AuthRequirement::Public, auth::oidc_authorize::handler)
+        .route(Method::POST, "/auth/oidc/{provider}/callback", AuthRequirement::Public, auth::oidc_callback::handler)
+        .route(Method::GET, "/auth/sessions", AuthRequirement::Authenticated, auth::list_sessions::handler)
+        .route(Method::DELETE, "/auth/sessions/{id}", AuthRequirement::Authenticated, auth::revoke_session::handler)
+        .route(Method::POST, "/auth/tokens", AuthRequirement::Authenticated, auth::create_token::handler)
+        .route(Method::GET, "/auth/tokens", AuthRequirement::Authenticated, auth::list_tokens::handler)
+        .route(Method::DELETE, "/auth/tokens/{id}", AuthRequirement::Authenticated, auth::revoke_token::handler)
+        .route(Method::POST, "/auth/password/forgot", AuthRequirement::Public, auth::forgot_password::handler)
+        .route(Method::POST, "/auth/password/reset", AuthRequirement::Public, auth::reset_password::handler)
+        .route(Method::GET, "/.well-known/jwks.json", AuthRequirement::Public, auth::jwks::handler)
+
+        // Admin routes
+        .route(Method::GET, "/admin/audit-events", AuthRequirement::Permitted(Action::ReadAuditEvents), admin::audit_events::handler)
+        .route(Method::GET, "/admin/security-events", AuthRequirement::Permitted(Action::ReadSecurityEvents), admin::security_events::handler)
+        .route(Method::POST, "/admin/lockouts/unlock", AuthRequirement::Permitted(Action::ManageLockouts), admin::unlock::handler)
+        
+        // Course routes
+        .route(Method::POST, "/courses", AuthRequirement::Permitted(Action::CreateCourse), course::create::handler)
+        .scoped(TokenScope::CoursesWrite)
+        .route(Method::GET, "/courses", AuthRequirement::Authenticated, course::list::handler)
+        .scoped(TokenScope::CoursesRead)
+        .route(Method::GET, "/courses/{id}", AuthRequirement::Authenticated, course::get::handler)
+        .scoped(TokenScope::CoursesRead)
+        .route(Method::PUT, "/courses/{id}", AuthRequirement::Authenticated, course::update::handler)
+        .scoped(TokenScope::CoursesWrite)
+        .route(Method::DELETE, "/courses/{id}", AuthRequirement::Authenticated, course::delete::handler)
+        .scoped(TokenScope::CoursesWrite)
+        
+        // Code execution routes
+        .route(Method::POST, "/code/execute", AuthRequirement::Permitted(Action::ExecuteCode), code_execution::execute::handler)
+        .scoped(TokenScope::SubmissionsWrite)
+        .route(Method::POST, "/code/evaluate", AuthRequirement::Permitted(Action::ExecuteCode), code_execution::evaluate::handler)
+        .scoped(TokenScope::SubmissionsWrite)
+}
@@ -0,0 +1,55 @@
+//! Kaiju Academy backend: API handlers shared by the Lambda function and the local server
+
+use lambda_runtime::{Error, LambdaEvent};
+use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
+use once_cell::sync::Lazy;
+use tracing::{info, Instrument};
+
+// Module imports
+pub mod common;
+pub mod identity;
+pub mod models;
+pub mod lambda;
+pub mod migrations;
+pub mod repository;
+#[cfg(test)]
+mod testing;
+
+use common::router::Router;
+
+/// Route table, built once per container
+static ROUTER: Lazy<Router> = Lazy::new(lambda::router);
+
+/// This is the main handler for AWS Lambda. It will be called when the Lambda function is invoked.
+/// It routes the request to the appropriate handler based on the HTTP method and path.
+/// See `lambda::router` for the route table.
+///
+/// Tracing must already be initialized with `common::logger::init_tracing`.
+pub async fn function_handler(event: LambdaEvent<ApiGatewayProxyRequest>) -> Result<ApiGatewayProxyResponse, Error> {
+    let request_id = common::logger::correlation_id(&event.payload, &event.context);
+    let span = common::logger::request_span(&event.payload, &event.context);
+    let request_span = span.clone();
+
+    let result = async move {
+        // Log the request
+        info!(
+            "Received API Gateway request: {} {}",
+            event.payload.http_method.to_string(),
+            event.payload.path.as_deref().unwrap_or_default()
+        );
+
+        // Route the request based on path and method
+        let mut response = ROUTER.dispatch(event).await?;
+        common::logger::record_status(&request_span, response.status_code);
+        common::logger::set_request_id_header(&mut response, &request_id);
+
+        Ok(response)
+    }
+    .instrument(span)
+    .await;
+
+    // The request span has closed; send it before Lambda freezes the container
+    common::telemetry::flush().await;
+
+    result
+}
@@ -0,0 +1,210 @@
+//! Versioned SurrealDB schema migrations.
+//!
+//! Each migration is a SurrealQL script of `DEFINE TABLE`, `DEFINE FIELD` and `DEFINE INDEX`
+//! statements compiled into the binary. Applied versions are recorded in the `migrations`
+//! table, so `up` only runs scripts the database has not seen yet. Run them with the
+//! `kaiju-migrate` binary:
+//!
+//! ```text
+//! cargo run --bin kaiju-migrate -- status
+//! cargo run --bin kaiju-migrate -- up
+//! ```
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use surrealdb::{Connection, Surreal};
+use tracing::info;
+
+use crate::common::error::AppError;
+
+/// A schema change, applied at most once per database
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    pub script: &'static str,
+}
+
+/// Every migration, in the order it must be applied.
+/// Append new scripts here; never edit one that has already shipped.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial_schema",
+        script: include_str!("0001_initial_schema.surql"),
+    },
+    Migration {
+        version: 2,
+        name: "tokens",
+        script: include_str!("0002_tokens.surql"),
+    },
+    Migration {
+        version: 3,
+        name: "password_reset",
+        script: include_str!("0003_password_reset.surql"),
+    },
+    Migration {
+        version: 4,
+        name: "email_verification",
+        script: include_str!("0004_email_verification.surql"),
+    },
+    Migration {
+        version: 5,
+        name: "login_throttle",
+        script: include_str!("0005_login_throttle.surql"),
+    },
+    Migration {
+        version: 6,
+        name: "mfa",
+        script: include_str!("0006_mfa.surql"),
+    },
+    Migration {
+        version: 7,
+        name: "oidc",
+        script: include_str!("0007_oidc.surql"),
+    },
+    Migration {
+        version: 8,
+        name: "personal_tokens",
+        script: include_str!("0008_personal_tokens.surql"),
+    },
+    Migration {
+        version: 9,
+        name: "sessions",
+        script: include_str!("0009_sessions.surql"),
+    },
+    Migration {
+        version: 10,
+        name: "audit_log",
+        script: include_str!("0010_audit_log.surql"),
+    },
+];
+
+/// Bookkeeping record stored in the `migrations` table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedMigration {
+    pub version: u32,
+    pub name: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub applied_at: DateTime<Utc>,
+}
+
+/// A known migration and whether it has been applied
+#[derive(Debug, Clone)]
+pub struct MigrationStatus {
+    pub migration: Migration,
+    pub applied_at: Option<DateTime<Utc>>,
+}
+
+/// The table that tracks applied versions
+const BOOKKEEPING: &str = "
+    DEFINE TABLE IF NOT EXISTS migrations SCHEMAFULL;
+    DEFINE FIELD IF NOT EXISTS version ON migrations TYPE int;
+    DEFINE FIELD IF NOT EXISTS name ON migrations TYPE string;
+    DEFINE FIELD IF NOT EXISTS applied_at ON migrations TYPE int;
+    DEFINE INDEX IF NOT EXISTS migrations_version ON migrations FIELDS version UNIQUE;
+";
+
+/// List every known migration with the time it was applied, if it has been
+pub async fn status<C: Connection>(db: &Surreal<C>) -> Result<Vec<MigrationStatus>, AppError> {
+    let applied = applied(db).await?;
+
+    let statuses = MIGRATIONS
+        .iter()
+        .map(|migration| MigrationStatus {
+            migration: *migration,
+            applied_at: applied
+                .iter()
+                .find(|record| record.version == migration.version)
+                .map(|record| record.applied_at),
+        })
+        .collect();
+
+    Ok(statuses)
+}
+
+/// Apply every pending migration in version order, returning the ones that ran.
+///
+/// Each script runs in its own transaction together with its bookkeeping record, so a
+/// failing migration leaves neither partial schema changes nor a record behind.
+pub async fn up<C: Connection>(db: &Surreal<C>) -> Result<Vec<Migration>, AppError> {
+    let applied = applied(db).await?;
+    let mut ran = Vec::new();
+
+    for migration in MIGRATIONS {
+        if applied.iter().any(|record| record.version == migration.version) {
+            continue;
+        }
+
+        info!("Applying migration {:04} {}", migration.version, migration.name);
+
+        let record = AppliedMigration {
+            version: migration.version,
+            name: migration.name.to_string(),
+            applied_at: Utc::now(),
+        };
+
+        let query = format!(
+            "BEGIN TRANSACTION;\n{}\nCREATE type::thing('migrations', $version) CONTENT $record;\nCOMMIT TRANSACTION;",
+            migration.script
+        );
+
+        db.query(query)
+            .bind(("version", migration.version))
+            .bind(("record", record))
+            .await?
+            .check()?;
+
+        ran.push(*migration);
+    }
+
+    Ok(ran)
+}
+
+/// Read the bookkeeping records, creating the table on first use
+async fn applied<C: Connection>(db: &Surreal<C>) -> Result<Vec<AppliedMigration>, AppError> {
+    db.query(BOOKKEEPING).await?.check()?;
+
+    let applied = db
+        .query("SELECT version, name, applied_at FROM migrations ORDER BY version ASC")
+        .await?
+        .take::<Vec<AppliedMigration>>(0)?;
+
+    Ok(applied)
+}
+
+#[cfg(test)]
+mod tests {
+    use surrealdb::engine::any;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn up_applies_pending_migrations_once() {
+        let db = any::connect("mem://").await.unwrap();
+        db.use_ns("test").use_db("test").await.unwrap();
+
+        let pending = status(&db).await.unwrap();
+        assert!(pending.iter().all(|status| status.applied_at.is_none()));
+
+        let ran = up(&db).await.unwrap();
+        assert_eq!(ran.len(), MIGRATIONS.len());
+
+        let applied = status(&db).await.unwrap();
+        assert!(applied.iter().all(|status| status.applied_at.is_some()));
+
+        // Running again is a no-op
+        assert!(up(&db).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn user_email_is_unique() {
+        let db = any::connect("mem://").await.unwrap();
+        db.use_ns("test").use_db("test").await.unwrap();
+        up(&db).await.unwrap();
+
+        let insert = "CREATE user SET email = 'ada@example.com', name = 'Ada', role = 'student'";
+        db.query(insert).await.unwrap().check().unwrap();
+        assert!(db.query(insert).await.unwrap().check().is_err());
+    }
+}
@@ -0,0 +1,67 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use surrealdb::sql::Thing;
+
+/// Privileged or destructive action recorded in the audit log
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AuditAction {
+    #[serde(rename = "course.create")]
+    CourseCreate,
+    #[serde(rename = "course.update")]
+    CourseUpdate,
+    #[serde(rename = "course.publish")]
+    CoursePublish,
+    #[serde(rename = "course.unpublish")]
+    CourseUnpublish,
+    #[serde(rename = "course.delete")]
+    CourseDelete,
+    #[serde(rename = "user.role_change")]
+    UserRoleChange,
+    #[serde(rename = "submission.grade")]
+    SubmissionGrade,
+}
+
+/// Entry in the append-only audit log.
+///
+/// `before` and `after` hold only the fields of the target that the action changed, so
+/// a creation has no `before` and a deletion has no `after`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEvent {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Thing>,
+    /// User who took the action
+    pub actor: Thing,
+    pub action: AuditAction,
+    /// Record the action was taken on
+    pub target: Thing,
+    #[serde(default)]
+    pub before: Option<Value>,
+    #[serde(default)]
+    pub after: Option<Value>,
+    /// Source IP of the request
+    #[serde(default)]
+    pub ip: Option<String>,
+    /// API Gateway ID of the request, for finding its logs
+    #[serde(default)]
+    pub request_id: Option<String>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl AuditEvent {
+    /// Create a new event happening now, without a diff
+    pub fn new(actor: Thing, action: AuditAction, target: Thing) -> Self {
+        Self {
+            id: None,
+            actor,
+            action,
+            target,
+            before: None,
+            after: None,
+            ip: None,
+            request_id: None,
+            created_at: Utc::now(),
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use surrealdb::sql::Thing;
 
 /// Course difficulty levels
@@ -13,13 +14,14 @@ pub enum CourseDifficulty {
     Advanced,
 }
 
-impl ToString for CourseDifficulty {
-    fn to_string(&self) -> String {
-        match self {
-            CourseDifficulty::Beginner => "beginner".to_string(),
-            CourseDifficulty::Intermediate => "intermediate".to_string(),
-            CourseDifficulty::Advanced => "advanced".to_string(),
-        }
+impl fmt::Display for CourseDifficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CourseDifficulty::Beginner => "beginner",
+            CourseDifficulty::Intermediate => "intermediate",
+            CourseDifficulty::Advanced => "advanced",
+        };
+        f.write_str(name)
     }
 }
 
@@ -96,13 +98,14 @@ pub enum MaterialType {
     Code,
 }
 
-impl ToString for MaterialType {
-    fn to_string(&self) -> String {
-        match self {
-            MaterialType::PDF => "pdf".to_string(),
-            MaterialType::Video => "video".to_string(),
-            MaterialType::Code => "code".to_string(),
-        }
+impl fmt::Display for MaterialType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MaterialType::PDF => "pdf",
+            MaterialType::Video => "video",
+            MaterialType::Code => "code",
+        };
+        f.write_str(name)
     }
 }
 
@@ -199,6 +202,20 @@ pub struct CourseUpdateRequest {
     pub modules: Option<Vec<String>>,
 }
 
+impl CourseUpdateRequest {
+    /// Does the request change any field?
+    pub fn has_changes(&self) -> bool {
+        self.title.is_some()
+            || self.description.is_some()
+            || self.difficulty.is_some()
+            || self.category.is_some()
+            || self.tags.is_some()
+            || self.is_published.is_some()
+            || self.thumbnail_url.is_some()
+            || self.modules.is_some()
+    }
+}
+
 /// Course creation request
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CourseCreateRequest {
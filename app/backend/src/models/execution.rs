@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+
+/// Programming languages supported for code execution
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    Python,
+    Rust,
+    JavaScript,
+    Java,
+    Cpp,
+}
+
+/// Execution status codes
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ExecutionStatus {
+    Success,
+    CompileError,
+    RuntimeError,
+    Timeout,
+    MemoryLimitExceeded,
+}
@@ -0,0 +1,82 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Thing;
+
+/// A user's TOTP authenticator.
+///
+/// An enrollment is pending until the user proves their app works by confirming a code.
+/// Only confirmed enrollments are asked for at login. `last_used_step` is the newest time
+/// step whose code was accepted, so a code cannot be replayed. Recovery codes are stored
+/// as SHA-256 hashes and removed as they are used.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TotpEnrollment {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Thing>,
+    pub user: Thing,
+    /// Base32 shared secret; the server needs it in the clear to compute codes
+    pub secret: String,
+    #[serde(with = "chrono::serde::ts_seconds_option", default)]
+    pub confirmed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub last_used_step: u64,
+    #[serde(default)]
+    pub recovery_codes: Vec<String>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl TotpEnrollment {
+    /// Create a new pending enrollment
+    pub fn new(user: Thing, secret: String) -> Self {
+        Self {
+            id: None,
+            user,
+            secret,
+            confirmed_at: None,
+            last_used_step: 0,
+            recovery_codes: Vec::new(),
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Has the user confirmed a code from their app?
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmed_at.is_some()
+    }
+}
+
+/// Request to start TOTP enrollment
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TotpEnrollRequest {
+    /// MFA-pending token from a login that requires enrollment, for callers without an access token
+    #[serde(default)]
+    pub mfa_token: Option<String>,
+}
+
+/// Request to confirm a pending TOTP enrollment
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TotpConfirmRequest {
+    pub code: String,
+    /// MFA-pending token from a login that requires enrollment; tokens are issued on success
+    #[serde(default)]
+    pub mfa_token: Option<String>,
+}
+
+/// Second step of a login for a user with MFA
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MfaVerifyRequest {
+    pub mfa_token: String,
+    #[serde(default)]
+    pub code: Option<String>,
+    #[serde(default)]
+    pub recovery_code: Option<String>,
+}
+
+/// Request to remove the caller's authenticator
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TotpDisableRequest {
+    #[serde(default)]
+    pub code: Option<String>,
+    #[serde(default)]
+    pub recovery_code: Option<String>,
+}
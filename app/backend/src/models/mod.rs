@@ -2,4 +2,11 @@ pub mod user;
 pub mod course;
 pub mod quiz;
 pub mod forum;
-pub mod submission; 
\ No newline at end of file
+pub mod submission;
+pub mod execution;
+pub mod security;
+pub mod token;
+pub mod mfa;
+pub mod oidc;
+pub mod session;
+pub mod audit;
@@ -0,0 +1,55 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Thing;
+
+/// An OpenID Connect sign-in waiting for the user to come back from the provider.
+///
+/// Stored under the SHA-256 hash of the `state` parameter and removed when the callback
+/// uses it. The nonce and PKCE verifier never leave the server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OidcState {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Thing>,
+    pub provider: String,
+    pub nonce: String,
+    pub code_verifier: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub expires_at: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// A provider account linked to a user, identified by the provider's `sub` claim
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserIdentity {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Thing>,
+    pub user: Thing,
+    pub provider: String,
+    pub subject: String,
+    /// Email the provider reported when the account was linked
+    pub email: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl UserIdentity {
+    /// Link a provider account to a user
+    pub fn new(user: Thing, provider: &str, subject: &str, email: &str) -> Self {
+        Self {
+            id: None,
+            user,
+            provider: provider.to_string(),
+            subject: subject.to_string(),
+            email: email.to_string(),
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// Callback request carrying the parameters the provider redirected back with
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OidcCallbackRequest {
+    pub code: String,
+    pub state: String,
+}
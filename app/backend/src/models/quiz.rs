@@ -1,7 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use surrealdb::sql::Thing;
-use crate::models::user::User;
 
 /// Quiz model for the platform
 #[derive(Debug, Clone, Serialize, Deserialize)]
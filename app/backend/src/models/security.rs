@@ -0,0 +1,68 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Thing;
+
+/// Failed login counter for one throttle key, such as `account:ada@example.com` or
+/// `ip:203.0.113.7`.
+///
+/// Logins for the key are refused until `blocked_until`. `locked` marks a full lockout,
+/// as opposed to the short delays that come before it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginThrottle {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Thing>,
+    pub failures: u32,
+    #[serde(with = "chrono::serde::ts_seconds", default)]
+    pub last_failure_at: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_seconds", default)]
+    pub blocked_until: DateTime<Utc>,
+    #[serde(default)]
+    pub locked: bool,
+}
+
+/// Kind of security event
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SecurityEventKind {
+    LoginLockout,
+    LoginUnlock,
+}
+
+/// Security event shown to admins
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityEvent {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Thing>,
+    pub kind: SecurityEventKind,
+    /// Throttle key the event is about
+    pub subject: String,
+    /// Admin who caused the event, if it was not automatic
+    pub actor: Option<Thing>,
+    /// Source IP of the request that caused the event
+    pub ip: Option<String>,
+    pub detail: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl SecurityEvent {
+    /// Create a new event happening now
+    pub fn new(kind: SecurityEventKind, subject: &str, detail: String) -> Self {
+        Self {
+            id: None,
+            kind,
+            subject: subject.to_string(),
+            actor: None,
+            ip: None,
+            detail,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// Request to lift a login lockout early
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnlockRequest {
+    pub email: Option<String>,
+    pub ip: Option<String>,
+}
@@ -0,0 +1,162 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Thing;
+
+/// A signed-in device.
+///
+/// Every login opens a session, keyed by the rotation family of its refresh tokens, and
+/// every access token names its session. Revoking a session revokes its refresh tokens
+/// and makes its access tokens fail validation, so a user can sign out a device remotely.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Thing>,
+    pub user: Thing,
+    /// Readable description of the device, such as "Firefox on Windows"
+    pub device: String,
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    #[serde(default)]
+    pub ip_address: Option<String>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub last_seen_at: DateTime<Utc>,
+    /// When the session's current refresh token expires
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub expires_at: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_seconds_option", default)]
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl Session {
+    /// Create a new session record for the refresh token family `family`
+    pub fn new(family: &str, user: Thing, client: &ClientInfo, expires_at: DateTime<Utc>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Some(Thing::from(("session", family))),
+            user,
+            device: client.device(),
+            user_agent: client.user_agent.clone(),
+            ip_address: client.ip_address.clone(),
+            created_at: now,
+            last_seen_at: now,
+            expires_at,
+            revoked_at: None,
+        }
+    }
+}
+
+/// Where a request came from, recorded on the session it opens or refreshes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+impl ClientInfo {
+    /// Readable device description derived from the user agent
+    pub fn device(&self) -> String {
+        self.user_agent.as_deref().map(describe_device).unwrap_or_else(|| "Unknown device".to_string())
+    }
+}
+
+/// Describe a user agent as "<browser> on <platform>", falling back to "Unknown" for either part
+pub fn describe_device(user_agent: &str) -> String {
+    // Order matters: Edge and Opera also claim to be Chrome, and Chrome claims to be Safari
+    let browser = [
+        ("Edg/", "Edge"),
+        ("OPR/", "Opera"),
+        ("Firefox/", "Firefox"),
+        ("Chrome/", "Chrome"),
+        ("Safari/", "Safari"),
+        ("curl/", "curl"),
+    ]
+    .iter()
+    .find(|(marker, _)| user_agent.contains(marker))
+    .map_or("Unknown browser", |(_, name)| name);
+
+    // Android and iOS user agents also mention Linux and Mac OS X
+    let platform = [
+        ("Android", "Android"),
+        ("iPhone", "iOS"),
+        ("iPad", "iPadOS"),
+        ("Windows", "Windows"),
+        ("Mac OS X", "macOS"),
+        ("CrOS", "ChromeOS"),
+        ("Linux", "Linux"),
+    ]
+    .iter()
+    .find(|(marker, _)| user_agent.contains(marker))
+    .map_or("unknown platform", |(_, name)| name);
+
+    format!("{} on {}", browser, platform)
+}
+
+/// Session as shown to its owner
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionResponse {
+    pub id: String,
+    pub device: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub last_seen_at: DateTime<Utc>,
+    /// Is this the session making the request?
+    pub current: bool,
+}
+
+impl SessionResponse {
+    /// Describe `session`, marking it as current if it is the caller's
+    pub fn new(session: Session, current: Option<&str>) -> Self {
+        let id = session.id.map(|id| id.to_string()).unwrap_or_default();
+        let is_current = current.is_some_and(|family| id == Thing::from(("session", family)).to_string());
+
+        Self {
+            id,
+            device: session.device,
+            user_agent: session.user_agent,
+            ip_address: session.ip_address,
+            created_at: session.created_at,
+            last_seen_at: session.last_seen_at,
+            current: is_current,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_common_user_agents() {
+        let cases = [
+            (
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36 Edg/126.0.0.0",
+                "Edge on Windows",
+            ),
+            (
+                "Mozilla/5.0 (Macintosh; Intel Mac OS X 14_5) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.5 Safari/605.1.15",
+                "Safari on macOS",
+            ),
+            (
+                "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Mobile Safari/537.36",
+                "Chrome on Android",
+            ),
+            ("Mozilla/5.0 (X11; Ubuntu; Linux x86_64; rv:127.0) Gecko/20100101 Firefox/127.0", "Firefox on Linux"),
+            ("curl/8.5.0", "curl on unknown platform"),
+            ("", "Unknown browser on unknown platform"),
+        ];
+
+        for (user_agent, expected) in cases {
+            assert_eq!(describe_device(user_agent), expected, "{}", user_agent);
+        }
+    }
+
+    #[test]
+    fn clients_without_a_user_agent_are_unknown_devices() {
+        assert_eq!(ClientInfo::default().device(), "Unknown device");
+    }
+}
@@ -35,6 +35,17 @@ pub struct CodeSubmission {
     pub reviewed_at: Option<DateTime<Utc>>,
 }
 
+/// Test case definition for a coding assignment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestCase {
+    pub id: String,
+    pub input: String,
+    pub expected_output: String,
+    pub description: Option<String>,
+    pub is_hidden: bool,
+    pub points: Option<f64>,
+}
+
 /// Request model to create a new code submission
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateSubmissionRequest {
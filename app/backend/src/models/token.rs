@@ -0,0 +1,251 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use surrealdb::sql::Thing;
+
+/// Refresh token model for database operations.
+///
+/// Only the SHA-256 hash of the token is stored. Every refresh rotates the token within
+/// its family; presenting a token that was already used revokes the whole family.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefreshToken {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Thing>,
+    pub token_hash: String,
+    pub user: Thing,
+    pub family: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub expires_at: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_seconds_option", default)]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(with = "chrono::serde::ts_seconds_option", default)]
+    pub used_at: Option<DateTime<Utc>>,
+    #[serde(with = "chrono::serde::ts_seconds_option", default)]
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl RefreshToken {
+    /// Create a new refresh token record
+    pub fn new(token_hash: String, user: Thing, family: String, expires_at: DateTime<Utc>) -> Self {
+        Self {
+            id: None,
+            token_hash,
+            user,
+            family,
+            expires_at,
+            created_at: Some(Utc::now()),
+            used_at: None,
+            revoked_at: None,
+        }
+    }
+}
+
+/// Password reset token model for database operations.
+///
+/// Only the SHA-256 hash of the token is stored. A token can be used once, before it expires.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasswordReset {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Thing>,
+    pub token_hash: String,
+    pub user: Thing,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub expires_at: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_seconds_option", default)]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(with = "chrono::serde::ts_seconds_option", default)]
+    pub used_at: Option<DateTime<Utc>>,
+}
+
+impl PasswordReset {
+    /// Create a new password reset record
+    pub fn new(token_hash: String, user: Thing, expires_at: DateTime<Utc>) -> Self {
+        Self {
+            id: None,
+            token_hash,
+            user,
+            expires_at,
+            created_at: Some(Utc::now()),
+            used_at: None,
+        }
+    }
+}
+
+/// Email verification code model for database operations.
+///
+/// Each user has at most one outstanding code. Only its SHA-256 hash is stored, and every
+/// guess counts against `attempts` so the six digits cannot be brute-forced.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailVerification {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Thing>,
+    pub code_hash: String,
+    pub user: Thing,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub expires_at: DateTime<Utc>,
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl EmailVerification {
+    /// Create a new verification code record
+    pub fn new(code_hash: String, user: Thing, expires_at: DateTime<Utc>) -> Self {
+        Self {
+            id: None,
+            code_hash,
+            user,
+            expires_at,
+            attempts: 0,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// What a personal access token may be used for
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TokenScope {
+    #[serde(rename = "courses:read")]
+    CoursesRead,
+    #[serde(rename = "courses:write")]
+    CoursesWrite,
+    #[serde(rename = "submissions:read")]
+    SubmissionsRead,
+    #[serde(rename = "submissions:write")]
+    SubmissionsWrite,
+}
+
+impl fmt::Display for TokenScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TokenScope::CoursesRead => "courses:read",
+            TokenScope::CoursesWrite => "courses:write",
+            TokenScope::SubmissionsRead => "submissions:read",
+            TokenScope::SubmissionsWrite => "submissions:write",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Personal access token model for database operations.
+///
+/// Long-lived credential for scripts, limited to its scopes. Only the SHA-256 hash of the
+/// token is stored; the token itself is shown once, when it is created.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersonalToken {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Thing>,
+    pub token_hash: String,
+    pub user: Thing,
+    pub name: String,
+    pub scopes: Vec<TokenScope>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub expires_at: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_seconds_option", default)]
+    pub last_used_at: Option<DateTime<Utc>>,
+    #[serde(with = "chrono::serde::ts_seconds_option", default)]
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl PersonalToken {
+    /// Create a new personal access token record
+    pub fn new(token_hash: String, user: Thing, name: String, scopes: Vec<TokenScope>, expires_at: DateTime<Utc>) -> Self {
+        Self {
+            id: None,
+            token_hash,
+            user,
+            name,
+            scopes,
+            expires_at,
+            created_at: Utc::now(),
+            last_used_at: None,
+            revoked_at: None,
+        }
+    }
+}
+
+/// Personal access token as shown to its owner, without the hash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersonalTokenResponse {
+    pub id: String,
+    pub name: String,
+    pub scopes: Vec<TokenScope>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub expires_at: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl From<PersonalToken> for PersonalTokenResponse {
+    fn from(token: PersonalToken) -> Self {
+        Self {
+            id: token.id.map(|id| id.to_string()).unwrap_or_default(),
+            name: token.name,
+            scopes: token.scopes,
+            expires_at: token.expires_at,
+            created_at: token.created_at,
+            last_used_at: token.last_used_at,
+        }
+    }
+}
+
+/// Request to create a personal access token
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatePersonalTokenRequest {
+    pub name: String,
+    pub scopes: Vec<TokenScope>,
+    /// Days until the token expires; defaults to 30
+    #[serde(default)]
+    pub expires_in_days: Option<u64>,
+}
+
+/// Refresh request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+/// Logout request
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LogoutRequest {
+    /// Refresh token of the session being closed; its whole rotation family is revoked
+    pub refresh_token: Option<String>,
+    /// Revoke every refresh token of the caller, signing out all devices
+    #[serde(default)]
+    pub all_sessions: bool,
+}
+
+/// Forgotten password request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+/// Request for a new email verification code
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResendVerificationRequest {
+    pub email: String,
+}
+
+/// Password reset request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    pub password: String,
+    /// Account email, required by identity providers whose reset codes are not globally unique
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+/// Access and refresh token issued together
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenPair {
+    pub token: String,
+    pub refresh_token: String,
+    pub expires_in: u64,
+}
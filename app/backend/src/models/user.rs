@@ -1,5 +1,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 use surrealdb::sql::Thing;
 
 /// User roles in the system
@@ -15,13 +17,28 @@ pub enum UserRole {
     Moderator,
 }
 
-impl ToString for UserRole {
-    fn to_string(&self) -> String {
-        match self {
-            UserRole::Admin => "admin".to_string(),
-            UserRole::Educator => "educator".to_string(),
-            UserRole::Student => "student".to_string(),
-            UserRole::Moderator => "moderator".to_string(),
+impl fmt::Display for UserRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            UserRole::Admin => "admin",
+            UserRole::Educator => "educator",
+            UserRole::Student => "student",
+            UserRole::Moderator => "moderator",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for UserRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "admin" => Ok(UserRole::Admin),
+            "educator" => Ok(UserRole::Educator),
+            "student" => Ok(UserRole::Student),
+            "moderator" => Ok(UserRole::Moderator),
+            _ => Err(format!("Unknown user role: {}", s)),
         }
     }
 }
@@ -36,6 +53,8 @@ pub struct User {
     pub password: Option<String>,
     pub name: String,
     pub role: UserRole,
+    #[serde(default)]
+    pub email_verified: bool,
     #[serde(with = "chrono::serde::ts_seconds_option", default)]
     pub created_at: Option<DateTime<Utc>>,
     #[serde(with = "chrono::serde::ts_seconds_option", default)]
@@ -55,6 +74,7 @@ impl User {
             password: None,
             name,
             role,
+            email_verified: false,
             created_at: Some(Utc::now()),
             last_login: None,
             profile_image: None,
@@ -86,6 +106,7 @@ pub struct UserResponse {
     pub email: String,
     pub name: String,
     pub role: UserRole,
+    pub email_verified: bool,
     #[serde(with = "chrono::serde::ts_seconds_option")]
     pub created_at: Option<DateTime<Utc>>,
     #[serde(with = "chrono::serde::ts_seconds_option")]
@@ -101,6 +122,7 @@ impl From<User> for UserResponse {
             email: user.email,
             name: user.name,
             role: user.role,
+            email_verified: user.email_verified,
             created_at: user.created_at,
             last_login: user.last_login,
             profile_image: user.profile_image,
@@ -0,0 +1,87 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use surrealdb::sql::Thing;
+use surrealdb::Connection;
+
+use crate::common::error::AppError;
+use crate::models::audit::AuditEvent;
+use crate::repository::SurrealRepository;
+
+/// Filters for listing audit events
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter {
+    pub actor: Option<Thing>,
+    pub target: Option<Thing>,
+    /// Only events at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only events before this time
+    pub until: Option<DateTime<Utc>>,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+/// Access to `audit_event` records.
+///
+/// The log is append-only: events can be recorded and listed, never changed or removed.
+#[async_trait]
+pub trait AuditRepository: Send + Sync {
+    /// Append an event to the audit log
+    async fn record_audit_event(&self, event: AuditEvent) -> Result<AuditEvent, AppError>;
+
+    /// List audit events matching the filter, newest first
+    async fn list_audit_events(&self, filter: &AuditFilter) -> Result<Vec<AuditEvent>, AppError>;
+}
+
+#[async_trait]
+impl<C: Connection> AuditRepository for SurrealRepository<C> {
+    async fn record_audit_event(&self, event: AuditEvent) -> Result<AuditEvent, AppError> {
+        let created = self
+            .query("CREATE audit_event CONTENT $data RETURN *")
+            .bind(("data", event))
+            .await?
+            .take::<Option<AuditEvent>>(0)?;
+
+        created.ok_or_else(|| AppError::Internal("Audit event created but not returned".to_string()))
+    }
+
+    async fn list_audit_events(&self, filter: &AuditFilter) -> Result<Vec<AuditEvent>, AppError> {
+        let mut conditions = Vec::new();
+
+        if filter.actor.is_some() {
+            conditions.push("actor = $actor");
+        }
+
+        if filter.target.is_some() {
+            conditions.push("target = $target");
+        }
+
+        if filter.since.is_some() {
+            conditions.push("created_at >= $since");
+        }
+
+        if filter.until.is_some() {
+            conditions.push("created_at < $until");
+        }
+
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", conditions.join(" AND "))
+        };
+
+        let query = format!("SELECT * FROM audit_event{where_clause} ORDER BY created_at DESC LIMIT $limit START $offset");
+
+        let events = self
+            .query(query)
+            .bind(("actor", filter.actor.clone()))
+            .bind(("target", filter.target.clone()))
+            .bind(("since", filter.since.map(|since| since.timestamp())))
+            .bind(("until", filter.until.map(|until| until.timestamp())))
+            .bind(("limit", filter.limit))
+            .bind(("offset", filter.offset))
+            .await?
+            .take::<Vec<AuditEvent>>(0)?;
+
+        Ok(events)
+    }
+}
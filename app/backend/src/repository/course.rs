@@ -0,0 +1,369 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use surrealdb::sql::Thing;
+use surrealdb::Connection;
+
+use crate::common::error::AppError;
+use crate::models::course::{Course, CourseDifficulty, CourseUpdateRequest, Enrollment, Material, Section};
+use crate::repository::SurrealRepository;
+
+/// Filters for listing courses
+#[derive(Debug, Clone, Default)]
+pub struct CourseFilter {
+    pub include_unpublished: bool,
+    /// Only include the drafts of this educator; `None` includes every draft
+    pub drafts_of: Option<Thing>,
+    pub category: Option<String>,
+    pub difficulty: Option<String>,
+    pub query: Option<String>,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+/// Fields merged into a course by an update. The editor is kept out of the JSON map so it
+/// is stored as a record link rather than a plain object.
+#[derive(Serialize)]
+struct CourseChanges {
+    #[serde(flatten)]
+    fields: Map<String, Value>,
+    updated_by: Thing,
+}
+
+/// Access to `course` records
+#[async_trait]
+pub trait CourseRepository: Send + Sync {
+    /// Find a course by record ID
+    async fn find_course(&self, id: &Thing) -> Result<Option<Course>, AppError>;
+
+    /// List courses matching the filter, returning the page and the total match count
+    async fn list_courses(&self, filter: &CourseFilter) -> Result<(Vec<Course>, usize), AppError>;
+
+    /// Insert a new course and return the stored record
+    async fn create_course(&self, course: Course) -> Result<Course, AppError>;
+
+    /// Apply the fields set in `changes`, returning the updated course if it still exists
+    async fn update_course(&self, id: &Thing, changes: &CourseUpdateRequest, updated_by: &Thing) -> Result<Option<Course>, AppError>;
+
+    /// Delete a course
+    async fn delete_course(&self, id: &Thing) -> Result<(), AppError>;
+}
+
+/// Access to `section` records
+#[async_trait]
+pub trait SectionRepository: Send + Sync {
+    /// Find a section by record ID
+    async fn find_section(&self, id: &Thing) -> Result<Option<Section>, AppError>;
+
+    /// List the sections of a course in display order
+    async fn list_sections(&self, course: &Thing) -> Result<Vec<Section>, AppError>;
+
+    /// Insert a new section and return the stored record
+    async fn create_section(&self, section: Section) -> Result<Section, AppError>;
+
+    /// Delete a section
+    async fn delete_section(&self, id: &Thing) -> Result<(), AppError>;
+}
+
+/// Access to `material` records
+#[async_trait]
+pub trait MaterialRepository: Send + Sync {
+    /// Find a material by record ID
+    async fn find_material(&self, id: &Thing) -> Result<Option<Material>, AppError>;
+
+    /// List the materials of a section in display order
+    async fn list_materials(&self, section: &Thing) -> Result<Vec<Material>, AppError>;
+
+    /// Insert a new material and return the stored record
+    async fn create_material(&self, material: Material) -> Result<Material, AppError>;
+
+    /// Delete a material
+    async fn delete_material(&self, id: &Thing) -> Result<(), AppError>;
+}
+
+/// Access to `enrollment` records
+#[async_trait]
+pub trait EnrollmentRepository: Send + Sync {
+    /// Find a student's enrollment in a course
+    async fn find_enrollment(&self, student: &Thing, course: &Thing) -> Result<Option<Enrollment>, AppError>;
+
+    /// List every enrollment of a student
+    async fn list_enrollments(&self, student: &Thing) -> Result<Vec<Enrollment>, AppError>;
+
+    /// Count the enrollments of a course
+    async fn count_enrollments(&self, course: &Thing) -> Result<usize, AppError>;
+
+    /// Insert a new enrollment and return the stored record
+    async fn create_enrollment(&self, enrollment: Enrollment) -> Result<Enrollment, AppError>;
+}
+
+#[async_trait]
+impl<C: Connection> CourseRepository for SurrealRepository<C> {
+    async fn find_course(&self, id: &Thing) -> Result<Option<Course>, AppError> {
+        let course = self
+            .query("SELECT * FROM $id")
+            .bind(("id", id.clone()))
+            .await?
+            .take::<Option<Course>>(0)?;
+
+        Ok(course)
+    }
+
+    async fn list_courses(&self, filter: &CourseFilter) -> Result<(Vec<Course>, usize), AppError> {
+        let mut conditions = Vec::new();
+
+        // Only show published courses unless the caller may see drafts
+        if !filter.include_unpublished {
+            conditions.push("is_published = true");
+        } else if filter.drafts_of.is_some() {
+            conditions.push("(is_published = true OR educator = $drafts_of)");
+        }
+
+        if filter.category.is_some() {
+            conditions.push("category = $category");
+        }
+
+        if filter.difficulty.is_some() {
+            conditions.push("difficulty = $difficulty");
+        }
+
+        if filter.query.is_some() {
+            conditions.push("(title CONTAINS $query OR description CONTAINS $query)");
+        }
+
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", conditions.join(" AND "))
+        };
+
+        let query = format!(
+            "SELECT * FROM course{where_clause} ORDER BY created_at DESC LIMIT $limit START $offset; \
+             SELECT count() AS total FROM course{where_clause} GROUP ALL;"
+        );
+
+        let mut response = self
+            .query(query)
+            .bind(("drafts_of", filter.drafts_of.clone()))
+            .bind(("category", filter.category.clone()))
+            .bind(("difficulty", filter.difficulty.clone()))
+            .bind(("query", filter.query.clone()))
+            .bind(("limit", filter.limit))
+            .bind(("offset", filter.offset))
+            .await?;
+
+        let courses = response.take::<Vec<Course>>(0)?;
+        let total = response.take::<Option<usize>>((1, "total"))?.unwrap_or(0);
+
+        Ok((courses, total))
+    }
+
+    async fn create_course(&self, course: Course) -> Result<Course, AppError> {
+        let created = self
+            .query("CREATE course CONTENT $data RETURN *")
+            .bind(("data", course))
+            .await?
+            .take::<Option<Course>>(0)?;
+
+        created.ok_or_else(|| AppError::Internal("Course created but not returned".to_string()))
+    }
+
+    async fn update_course(&self, id: &Thing, changes: &CourseUpdateRequest, updated_by: &Thing) -> Result<Option<Course>, AppError> {
+        let mut merge = Map::new();
+
+        if let Some(title) = &changes.title {
+            merge.insert("title".into(), Value::from(title.clone()));
+        }
+
+        if let Some(description) = &changes.description {
+            merge.insert("description".into(), Value::from(description.clone()));
+        }
+
+        if let Some(category) = &changes.category {
+            merge.insert("category".into(), Value::from(category.clone()));
+        }
+
+        if let Some(difficulty) = &changes.difficulty {
+            merge.insert("difficulty".into(), difficulty_value(difficulty));
+        }
+
+        if let Some(is_published) = changes.is_published {
+            merge.insert("is_published".into(), Value::from(is_published));
+        }
+
+        if let Some(thumbnail_url) = &changes.thumbnail_url {
+            merge.insert("thumbnail".into(), Value::from(thumbnail_url.clone()));
+        }
+
+        if let Some(modules) = &changes.modules {
+            merge.insert("modules".into(), Value::from(modules.clone()));
+        }
+
+        if let Some(tags) = &changes.tags {
+            merge.insert("tags".into(), Value::from(tags.clone()));
+        }
+
+        // Always update the modification timestamp
+        merge.insert("updated_at".into(), Value::from(Utc::now().timestamp()));
+
+        let changes = CourseChanges {
+            fields: merge,
+            updated_by: updated_by.clone(),
+        };
+
+        let course = self
+            .query("UPDATE $id MERGE $changes RETURN AFTER")
+            .bind(("id", id.clone()))
+            .bind(("changes", changes))
+            .await?
+            .take::<Option<Course>>(0)?;
+
+        Ok(course)
+    }
+
+    async fn delete_course(&self, id: &Thing) -> Result<(), AppError> {
+        self
+            .query("DELETE $id")
+            .bind(("id", id.clone()))
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<C: Connection> SectionRepository for SurrealRepository<C> {
+    async fn find_section(&self, id: &Thing) -> Result<Option<Section>, AppError> {
+        let section = self
+            .query("SELECT * FROM $id")
+            .bind(("id", id.clone()))
+            .await?
+            .take::<Option<Section>>(0)?;
+
+        Ok(section)
+    }
+
+    async fn list_sections(&self, course: &Thing) -> Result<Vec<Section>, AppError> {
+        let sections = self
+            .query("SELECT * FROM section WHERE course = $course ORDER BY order_index ASC")
+            .bind(("course", course.clone()))
+            .await?
+            .take::<Vec<Section>>(0)?;
+
+        Ok(sections)
+    }
+
+    async fn create_section(&self, section: Section) -> Result<Section, AppError> {
+        let created = self
+            .query("CREATE section CONTENT $data RETURN *")
+            .bind(("data", section))
+            .await?
+            .take::<Option<Section>>(0)?;
+
+        created.ok_or_else(|| AppError::Internal("Section created but not returned".to_string()))
+    }
+
+    async fn delete_section(&self, id: &Thing) -> Result<(), AppError> {
+        self
+            .query("DELETE $id")
+            .bind(("id", id.clone()))
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<C: Connection> MaterialRepository for SurrealRepository<C> {
+    async fn find_material(&self, id: &Thing) -> Result<Option<Material>, AppError> {
+        let material = self
+            .query("SELECT * FROM $id")
+            .bind(("id", id.clone()))
+            .await?
+            .take::<Option<Material>>(0)?;
+
+        Ok(material)
+    }
+
+    async fn list_materials(&self, section: &Thing) -> Result<Vec<Material>, AppError> {
+        let materials = self
+            .query("SELECT * FROM material WHERE section = $section ORDER BY order_index ASC")
+            .bind(("section", section.clone()))
+            .await?
+            .take::<Vec<Material>>(0)?;
+
+        Ok(materials)
+    }
+
+    async fn create_material(&self, material: Material) -> Result<Material, AppError> {
+        let created = self
+            .query("CREATE material CONTENT $data RETURN *")
+            .bind(("data", material))
+            .await?
+            .take::<Option<Material>>(0)?;
+
+        created.ok_or_else(|| AppError::Internal("Material created but not returned".to_string()))
+    }
+
+    async fn delete_material(&self, id: &Thing) -> Result<(), AppError> {
+        self
+            .query("DELETE $id")
+            .bind(("id", id.clone()))
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<C: Connection> EnrollmentRepository for SurrealRepository<C> {
+    async fn find_enrollment(&self, student: &Thing, course: &Thing) -> Result<Option<Enrollment>, AppError> {
+        let enrollment = self
+            .query("SELECT * FROM enrollment WHERE student = $student AND course = $course LIMIT 1")
+            .bind(("student", student.clone()))
+            .bind(("course", course.clone()))
+            .await?
+            .take::<Option<Enrollment>>(0)?;
+
+        Ok(enrollment)
+    }
+
+    async fn list_enrollments(&self, student: &Thing) -> Result<Vec<Enrollment>, AppError> {
+        let enrollments = self
+            .query("SELECT * FROM enrollment WHERE student = $student ORDER BY enrolled_at DESC")
+            .bind(("student", student.clone()))
+            .await?
+            .take::<Vec<Enrollment>>(0)?;
+
+        Ok(enrollments)
+    }
+
+    async fn count_enrollments(&self, course: &Thing) -> Result<usize, AppError> {
+        let total = self
+            .query("SELECT count() AS total FROM enrollment WHERE course = $course GROUP ALL")
+            .bind(("course", course.clone()))
+            .await?
+            .take::<Option<usize>>((0, "total"))?;
+
+        Ok(total.unwrap_or(0))
+    }
+
+    async fn create_enrollment(&self, enrollment: Enrollment) -> Result<Enrollment, AppError> {
+        let created = self
+            .query("CREATE enrollment CONTENT $data RETURN *")
+            .bind(("data", enrollment))
+            .await?
+            .take::<Option<Enrollment>>(0)?;
+
+        created.ok_or_else(|| AppError::Internal("Enrollment created but not returned".to_string()))
+    }
+}
+
+/// Serialized form of a difficulty, matching the `Course` serde representation
+fn difficulty_value(difficulty: &CourseDifficulty) -> Value {
+    serde_json::to_value(difficulty).unwrap_or_else(|_| Value::from(difficulty.to_string()))
+}
@@ -0,0 +1,105 @@
+use async_trait::async_trait;
+use surrealdb::sql::Thing;
+use surrealdb::Connection;
+
+use crate::common::error::AppError;
+use crate::models::forum::{ForumCategory, ForumPost, ForumThread};
+use crate::repository::SurrealRepository;
+
+/// Access to `forum_category`, `forum_thread` and `forum_post` records
+#[async_trait]
+pub trait ForumRepository: Send + Sync {
+    /// List the forum categories of a course
+    async fn list_categories(&self, course: &Thing) -> Result<Vec<ForumCategory>, AppError>;
+
+    /// Insert a new category and return the stored record
+    async fn create_category(&self, category: ForumCategory) -> Result<ForumCategory, AppError>;
+
+    /// Find a thread by record ID
+    async fn find_thread(&self, id: &Thing) -> Result<Option<ForumThread>, AppError>;
+
+    /// List the threads of a category, pinned threads first
+    async fn list_threads(&self, category: &Thing) -> Result<Vec<ForumThread>, AppError>;
+
+    /// Insert a new thread and return the stored record
+    async fn create_thread(&self, thread: ForumThread) -> Result<ForumThread, AppError>;
+
+    /// List the posts of a thread, oldest first
+    async fn list_posts(&self, thread: &Thing) -> Result<Vec<ForumPost>, AppError>;
+
+    /// Insert a new post and return the stored record
+    async fn create_post(&self, post: ForumPost) -> Result<ForumPost, AppError>;
+}
+
+#[async_trait]
+impl<C: Connection> ForumRepository for SurrealRepository<C> {
+    async fn list_categories(&self, course: &Thing) -> Result<Vec<ForumCategory>, AppError> {
+        let categories = self
+            .query("SELECT * FROM forum_category WHERE course = $course ORDER BY name ASC")
+            .bind(("course", course.clone()))
+            .await?
+            .take::<Vec<ForumCategory>>(0)?;
+
+        Ok(categories)
+    }
+
+    async fn create_category(&self, category: ForumCategory) -> Result<ForumCategory, AppError> {
+        let created = self
+            .query("CREATE forum_category CONTENT $data RETURN *")
+            .bind(("data", category))
+            .await?
+            .take::<Option<ForumCategory>>(0)?;
+
+        created.ok_or_else(|| AppError::Internal("Forum category created but not returned".to_string()))
+    }
+
+    async fn find_thread(&self, id: &Thing) -> Result<Option<ForumThread>, AppError> {
+        let thread = self
+            .query("SELECT * FROM $id")
+            .bind(("id", id.clone()))
+            .await?
+            .take::<Option<ForumThread>>(0)?;
+
+        Ok(thread)
+    }
+
+    async fn list_threads(&self, category: &Thing) -> Result<Vec<ForumThread>, AppError> {
+        let threads = self
+            .query("SELECT * FROM forum_thread WHERE category = $category ORDER BY is_pinned DESC, created_at DESC")
+            .bind(("category", category.clone()))
+            .await?
+            .take::<Vec<ForumThread>>(0)?;
+
+        Ok(threads)
+    }
+
+    async fn create_thread(&self, thread: ForumThread) -> Result<ForumThread, AppError> {
+        let created = self
+            .query("CREATE forum_thread CONTENT $data RETURN *")
+            .bind(("data", thread))
+            .await?
+            .take::<Option<ForumThread>>(0)?;
+
+        created.ok_or_else(|| AppError::Internal("Forum thread created but not returned".to_string()))
+    }
+
+    async fn list_posts(&self, thread: &Thing) -> Result<Vec<ForumPost>, AppError> {
+        let posts = self
+            .query("SELECT * FROM forum_post WHERE thread = $thread ORDER BY created_at ASC")
+            .bind(("thread", thread.clone()))
+            .await?
+            .take::<Vec<ForumPost>>(0)?;
+
+        Ok(posts)
+    }
+
+    async fn create_post(&self, post: ForumPost) -> Result<ForumPost, AppError> {
+        let created = self
+            .query("CREATE forum_post CONTENT $data RETURN *")
+            .bind(("data", post))
+            .await?
+            .take::<Option<ForumPost>>(0)?;
+
+        created.ok_or_else(|| AppError::Internal("Forum post created but not returned".to_string()))
+    }
+}
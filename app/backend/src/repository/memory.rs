@@ -0,0 +1,125 @@
+//! Embedded in-memory SurrealDB backend.
+//!
+//! Only compiled for tests, since the `kv-mem` engine is only enabled through
+//! `[dev-dependencies]`.
+
+use surrealdb::engine::any::{self, Any};
+
+use crate::common::error::AppError;
+use crate::migrations;
+use crate::repository::SurrealRepository;
+
+/// Repository backed by a private in-memory database
+pub type MemoryRepository = SurrealRepository<Any>;
+
+/// Start a fresh in-memory database with every migration applied and wrap it in a repository
+pub async fn connect() -> Result<MemoryRepository, AppError> {
+    let db = any::connect("mem://").await?;
+    db.use_ns("test").use_db("test").await?;
+    migrations::up(&db).await?;
+
+    Ok(SurrealRepository::new(db))
+}
+
+#[cfg(test)]
+mod tests {
+    use surrealdb::sql::Thing;
+
+    use super::*;
+    use crate::common::error::ErrorCode;
+    use crate::models::course::{CourseDifficulty, CourseUpdateRequest};
+    use crate::models::user::{User, UserRole};
+    use crate::repository::{CourseFilter, CourseRepository, UserRepository};
+    use crate::testing::course;
+
+    #[tokio::test]
+    async fn user_round_trip() {
+        let repo = connect().await.unwrap();
+
+        let user = User::new("ada@example.com".to_string(), "Ada".to_string(), UserRole::Student);
+        let created = repo.create_user(user).await.unwrap();
+        let id = created.id.clone().unwrap();
+
+        let by_email = repo.find_user_by_email("ada@example.com").await.unwrap().unwrap();
+        assert_eq!(by_email.id, Some(id.clone()));
+        assert!(repo.find_user_by_email("nobody@example.com").await.unwrap().is_none());
+
+        repo.touch_last_login(&id, chrono::Utc::now()).await.unwrap();
+        let reloaded = repo.find_user(&id).await.unwrap().unwrap();
+        assert!(reloaded.last_login.is_some());
+    }
+
+    #[tokio::test]
+    async fn create_user_rejects_duplicate_email() {
+        let repo = connect().await.unwrap();
+
+        let user = User::new("ada@example.com".to_string(), "Ada".to_string(), UserRole::Student);
+        repo.create_user(user.clone()).await.unwrap();
+
+        let err = repo.create_user(user).await.unwrap_err();
+        assert!(matches!(err, AppError::Validation(ErrorCode::EmailTaken, _)), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn list_courses_hides_drafts_unless_requested() {
+        let repo = connect().await.unwrap();
+        let educator = Thing::from(("user", "educator"));
+
+        repo.create_course(course("Published", &educator, true)).await.unwrap();
+        repo.create_course(course("Draft", &educator, false)).await.unwrap();
+
+        let mut filter = CourseFilter { limit: 20, ..Default::default() };
+        let (courses, total) = repo.list_courses(&filter).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(courses[0].title, "Published");
+
+        filter.include_unpublished = true;
+        let (courses, total) = repo.list_courses(&filter).await.unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(courses.len(), 2);
+
+        // Drafts can be limited to one educator's
+        filter.drafts_of = Some(Thing::from(("user", "other")));
+        let (courses, total) = repo.list_courses(&filter).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(courses[0].title, "Published");
+    }
+
+    #[tokio::test]
+    async fn update_and_delete_course() {
+        let repo = connect().await.unwrap();
+        let educator = Thing::from(("user", "educator"));
+
+        let created = repo.create_course(course("Original", &educator, false)).await.unwrap();
+        let id = created.id.unwrap();
+
+        let changes = CourseUpdateRequest {
+            title: Some("Renamed".to_string()),
+            description: None,
+            difficulty: Some(CourseDifficulty::Advanced),
+            category: None,
+            tags: None,
+            is_published: Some(true),
+            thumbnail_url: None,
+            modules: None,
+        };
+        let updated = repo.update_course(&id, &changes, &educator).await.unwrap().unwrap();
+        assert_eq!(updated.title, "Renamed");
+        assert_eq!(updated.difficulty, CourseDifficulty::Advanced);
+        assert!(updated.is_published);
+
+        // The editor is stored as a record link
+        let editor: Option<Thing> = repo
+            .db
+            .query("SELECT VALUE updated_by FROM ONLY $id")
+            .bind(("id", id.clone()))
+            .await
+            .unwrap()
+            .take(0)
+            .unwrap();
+        assert_eq!(editor, Some(educator.clone()));
+
+        repo.delete_course(&id).await.unwrap();
+        assert!(repo.find_course(&id).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,124 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use surrealdb::sql::Thing;
+use surrealdb::Connection;
+
+use crate::common::error::AppError;
+use crate::models::mfa::TotpEnrollment;
+use crate::repository::SurrealRepository;
+
+/// Access to `mfa_totp` records
+#[async_trait]
+pub trait MfaRepository: Send + Sync {
+    /// Store a new pending enrollment for a user, replacing any other pending one.
+    ///
+    /// Fails if the user already has a confirmed authenticator.
+    async fn replace_pending_totp(&self, enrollment: TotpEnrollment) -> Result<TotpEnrollment, AppError>;
+
+    /// Find a user's authenticator, confirmed or not
+    async fn find_totp(&self, user: &Thing) -> Result<Option<TotpEnrollment>, AppError>;
+
+    /// Confirm a pending enrollment with the step of its first code and the hashed recovery codes.
+    ///
+    /// Returns `None` if there is no pending enrollment or the step was already used.
+    async fn confirm_totp(
+        &self,
+        user: &Thing,
+        step: u64,
+        recovery_codes: Vec<String>,
+        at: DateTime<Utc>,
+    ) -> Result<Option<TotpEnrollment>, AppError>;
+
+    /// Mark a time step as used.
+    ///
+    /// Returns `false` if that step or a later one was already used, so each code works once.
+    async fn record_totp_step(&self, user: &Thing, step: u64) -> Result<bool, AppError>;
+
+    /// Remove a hashed recovery code, returning `false` if the user did not have it
+    async fn consume_recovery_code(&self, user: &Thing, code_hash: &str) -> Result<bool, AppError>;
+
+    /// Remove a user's authenticator
+    async fn delete_totp(&self, user: &Thing) -> Result<(), AppError>;
+}
+
+#[async_trait]
+impl<C: Connection> MfaRepository for SurrealRepository<C> {
+    async fn replace_pending_totp(&self, enrollment: TotpEnrollment) -> Result<TotpEnrollment, AppError> {
+        let created = self
+            .query("DELETE mfa_totp WHERE user = $user AND confirmed_at = NONE")
+            .query("CREATE mfa_totp CONTENT $data RETURN *")
+            .bind(("user", enrollment.user.clone()))
+            .bind(("data", enrollment))
+            .await?
+            .take::<Option<TotpEnrollment>>(1)?;
+
+        created.ok_or_else(|| AppError::Internal("TOTP enrollment created but not returned".to_string()))
+    }
+
+    async fn find_totp(&self, user: &Thing) -> Result<Option<TotpEnrollment>, AppError> {
+        let enrollment = self
+            .query("SELECT * FROM mfa_totp WHERE user = $user LIMIT 1")
+            .bind(("user", user.clone()))
+            .await?
+            .take::<Option<TotpEnrollment>>(0)?;
+
+        Ok(enrollment)
+    }
+
+    async fn confirm_totp(
+        &self,
+        user: &Thing,
+        step: u64,
+        recovery_codes: Vec<String>,
+        at: DateTime<Utc>,
+    ) -> Result<Option<TotpEnrollment>, AppError> {
+        let enrollment = self
+            .query(
+                "UPDATE mfa_totp SET confirmed_at = $at, last_used_step = $step, recovery_codes = $recovery_codes \
+                 WHERE user = $user AND confirmed_at = NONE AND last_used_step < $step RETURN AFTER",
+            )
+            .bind(("user", user.clone()))
+            .bind(("step", step))
+            .bind(("recovery_codes", recovery_codes))
+            .bind(("at", at.timestamp()))
+            .await?
+            .take::<Option<TotpEnrollment>>(0)?;
+
+        Ok(enrollment)
+    }
+
+    async fn record_totp_step(&self, user: &Thing, step: u64) -> Result<bool, AppError> {
+        let updated = self
+            .query("UPDATE mfa_totp SET last_used_step = $step WHERE user = $user AND last_used_step < $step RETURN AFTER")
+            .bind(("user", user.clone()))
+            .bind(("step", step))
+            .await?
+            .take::<Option<TotpEnrollment>>(0)?;
+
+        Ok(updated.is_some())
+    }
+
+    async fn consume_recovery_code(&self, user: &Thing, code_hash: &str) -> Result<bool, AppError> {
+        let updated = self
+            .query(
+                "UPDATE mfa_totp SET recovery_codes -= $code_hash \
+                 WHERE user = $user AND confirmed_at != NONE AND recovery_codes CONTAINS $code_hash RETURN AFTER",
+            )
+            .bind(("user", user.clone()))
+            .bind(("code_hash", code_hash.to_string()))
+            .await?
+            .take::<Option<TotpEnrollment>>(0)?;
+
+        Ok(updated.is_some())
+    }
+
+    async fn delete_totp(&self, user: &Thing) -> Result<(), AppError> {
+        self
+            .query("DELETE mfa_totp WHERE user = $user")
+            .bind(("user", user.clone()))
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,180 @@
+//! Data access layer.
+//!
+//! Each table family gets a repository trait over the `models::*` types. Handlers depend on
+//! the traits instead of building SurrealQL inline, so their logic can run against the
+//! embedded in-memory engine in tests (see `memory`) as well as the production ws client.
+
+pub mod audit;
+pub mod course;
+pub mod forum;
+#[cfg(test)]
+pub(crate) mod memory;
+pub mod mfa;
+pub mod oidc;
+pub mod quiz;
+pub mod security;
+pub mod submission;
+pub mod token;
+pub mod user;
+pub mod verification;
+
+use std::future::{Future, IntoFuture};
+use std::pin::Pin;
+use std::str::FromStr;
+use std::time::Instant;
+
+use serde::Serialize;
+use surrealdb::engine::remote::ws::Client;
+use surrealdb::method::Query;
+use surrealdb::sql::Thing;
+use surrealdb::{Connection, Response, Surreal};
+use tracing::field::Empty;
+use tracing::{error, Instrument, Span};
+
+use crate::common::db;
+use crate::common::error::{AppError, ErrorCode};
+use crate::common::{metrics, telemetry};
+
+pub use audit::{AuditFilter, AuditRepository};
+pub use course::{CourseFilter, CourseRepository, EnrollmentRepository, MaterialRepository, SectionRepository};
+pub use forum::ForumRepository;
+pub use mfa::MfaRepository;
+pub use oidc::OidcRepository;
+pub use quiz::QuizRepository;
+pub use security::SecurityRepository;
+pub use submission::SubmissionRepository;
+pub use token::TokenRepository;
+pub use user::UserRepository;
+pub use verification::VerificationRepository;
+
+/// SurrealDB implementation of every repository trait.
+///
+/// Generic over the connection so the same queries run against the remote ws client in
+/// production and the embedded in-memory engine in tests.
+#[derive(Debug, Clone)]
+pub struct SurrealRepository<C: Connection> {
+    db: Surreal<C>,
+}
+
+impl<C: Connection> SurrealRepository<C> {
+    /// Wrap an already connected and namespaced client
+    pub fn new(db: Surreal<C>) -> Self {
+        Self { db }
+    }
+
+    /// Start a query that is traced as a `db.query` span and whose run time counts towards
+    /// the request's `DbTime` metric
+    fn query(&self, statement: impl Into<String>) -> TimedQuery<'_, C> {
+        let statement = statement.into();
+        TimedQuery {
+            query: self.db.query(statement.clone()),
+            statement,
+        }
+    }
+}
+
+/// A query that reports how long it took once awaited
+struct TimedQuery<'r, C: Connection> {
+    query: Query<'r, C>,
+    /// The SurrealQL text, without bound parameters
+    statement: String,
+}
+
+impl<'r, C: Connection> TimedQuery<'r, C> {
+    /// Append another statement, as [`Query::query`]
+    fn query(mut self, statement: impl Into<String>) -> Self {
+        let statement = statement.into();
+        self.statement = format!("{};\n{}", self.statement.trim_end().trim_end_matches(';'), statement);
+        self.query = self.query.query(statement);
+        self
+    }
+
+    /// Bind a parameter, as [`Query::bind`]
+    fn bind(mut self, bindings: impl Serialize + 'static) -> Self {
+        self.query = self.query.bind(bindings);
+        self
+    }
+}
+
+impl<'r, C: Connection> IntoFuture for TimedQuery<'r, C> {
+    type Output = surrealdb::Result<Response>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send + 'r>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        let span = tracing::info_span!(
+            "db.query",
+            otel.kind = "client",
+            db.system = "surrealdb",
+            db.statement = %self.statement,
+            otel.status_code = Empty,
+        );
+
+        Box::pin(
+            async move {
+                let started = Instant::now();
+                let response = self.query.await;
+                metrics::record_db_query(started.elapsed());
+                if response.is_err() {
+                    telemetry::record_error(&Span::current());
+                }
+                response
+            }
+            .instrument(span),
+        )
+    }
+}
+
+/// Repository backed by the shared production connection
+pub type DbRepository = SurrealRepository<Client>;
+
+/// Build a repository over the shared SurrealDB connection
+pub async fn connect() -> Result<DbRepository, AppError> {
+    match db::get_db_client().await {
+        Ok(client) => Ok(SurrealRepository::new((*client).clone())),
+        Err(err) => {
+            error!("Failed to connect to database: {}", err);
+            Err(AppError::Internal("Failed to connect to the database".to_string()))
+        }
+    }
+}
+
+/// Parse a record ID for `table`, accepting both `table:abc` and a bare `abc`
+pub fn record_id(table: &str, raw: &str) -> Result<Thing, AppError> {
+    if raw.is_empty() {
+        return Err(AppError::Validation(ErrorCode::InvalidId, format!("Invalid {} ID", table)));
+    }
+
+    match Thing::from_str(raw) {
+        Ok(thing) if thing.tb == table => Ok(thing),
+        Ok(_) => Err(AppError::Validation(ErrorCode::InvalidId, format!("Invalid {} ID: {}", table, raw))),
+        Err(_) => Ok(Thing::from((table, raw))),
+    }
+}
+
+/// Whether a write was rejected by a `UNIQUE` index
+pub(crate) fn is_unique_violation(err: &surrealdb::Error) -> bool {
+    match err {
+        surrealdb::Error::Db(surrealdb::error::Db::IndexExists { .. }) => true,
+        // The remote engine only passes on the server's message, so match the `IndexExists` text
+        surrealdb::Error::Api(surrealdb::error::Api::Query(message)) => {
+            message.starts_with("Database index `") && message.contains("` already contains ")
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remote_index_errors_are_unique_violations() {
+        let remote = surrealdb::error::Api::Query(
+            "Database index `unique_email` already contains 'ada@example.com', with record `user:abc`".to_string(),
+        );
+        assert!(is_unique_violation(&remote.into()));
+
+        let other = surrealdb::error::Api::Query("There was a problem with the database: already contains".to_string());
+        assert!(!is_unique_violation(&other.into()));
+    }
+}
@@ -0,0 +1,85 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use surrealdb::Connection;
+
+use crate::common::error::{AppError, ErrorCode};
+use crate::models::oidc::{OidcState, UserIdentity};
+use crate::repository::{is_unique_violation, SurrealRepository};
+
+/// Access to `oidc_state` and `user_identity` records
+#[async_trait]
+pub trait OidcRepository: Send + Sync {
+    /// Store a sign-in in progress under the hash of its state parameter
+    async fn create_oidc_state(&self, state_hash: &str, state: OidcState) -> Result<OidcState, AppError>;
+
+    /// Remove and return a sign-in in progress, unless it has expired.
+    ///
+    /// Only one caller can take a given state, so each authorization response is used once.
+    async fn consume_oidc_state(&self, state_hash: &str, at: DateTime<Utc>) -> Result<Option<OidcState>, AppError>;
+
+    /// Find the link for a provider account
+    async fn find_user_identity(&self, provider: &str, subject: &str) -> Result<Option<UserIdentity>, AppError>;
+
+    /// Link a provider account to a user
+    async fn create_user_identity(&self, identity: UserIdentity) -> Result<UserIdentity, AppError>;
+}
+
+#[async_trait]
+impl<C: Connection> OidcRepository for SurrealRepository<C> {
+    async fn create_oidc_state(&self, state_hash: &str, state: OidcState) -> Result<OidcState, AppError> {
+        // Abandoned sign-ins are only needed until they expire, so prune old ones here
+        let created = self
+            .query("DELETE oidc_state WHERE expires_at < $now")
+            .query("CREATE type::thing('oidc_state', $key) CONTENT $data RETURN *")
+            .bind(("now", Utc::now().timestamp()))
+            .bind(("key", state_hash.to_string()))
+            .bind(("data", state))
+            .await?
+            .take::<Option<OidcState>>(1)?;
+
+        created.ok_or_else(|| AppError::Internal("OIDC state created but not returned".to_string()))
+    }
+
+    async fn consume_oidc_state(&self, state_hash: &str, at: DateTime<Utc>) -> Result<Option<OidcState>, AppError> {
+        let state = self
+            .query("DELETE type::thing('oidc_state', $key) WHERE expires_at > $at RETURN BEFORE")
+            .bind(("key", state_hash.to_string()))
+            .bind(("at", at.timestamp()))
+            .await?
+            .take::<Option<OidcState>>(0)?;
+
+        Ok(state)
+    }
+
+    async fn find_user_identity(&self, provider: &str, subject: &str) -> Result<Option<UserIdentity>, AppError> {
+        let identity = self
+            .query("SELECT * FROM user_identity WHERE provider = $provider AND subject = $subject LIMIT 1")
+            .bind(("provider", provider.to_string()))
+            .bind(("subject", subject.to_string()))
+            .await?
+            .take::<Option<UserIdentity>>(0)?;
+
+        Ok(identity)
+    }
+
+    async fn create_user_identity(&self, identity: UserIdentity) -> Result<UserIdentity, AppError> {
+        let created = match self
+            .query("CREATE user_identity CONTENT $data RETURN *")
+            .bind(("data", identity))
+            .await
+        {
+            Ok(mut response) => response.take::<Option<UserIdentity>>(0),
+            Err(err) => Err(err),
+        };
+
+        match created {
+            Ok(Some(identity)) => Ok(identity),
+            Ok(None) => Err(AppError::Internal("User identity created but not returned".to_string())),
+            // Two callbacks for the same new provider account raced past the lookup
+            Err(err) if is_unique_violation(&err) => {
+                Err(AppError::Validation(ErrorCode::IdentityAlreadyLinked, "This provider account is already linked".to_string()))
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+}
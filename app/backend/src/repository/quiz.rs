@@ -0,0 +1,106 @@
+use async_trait::async_trait;
+use surrealdb::sql::Thing;
+use surrealdb::Connection;
+
+use crate::common::error::AppError;
+use crate::models::quiz::{Quiz, QuizAttempt, QuizQuestion};
+use crate::repository::SurrealRepository;
+
+/// Access to `quiz`, `quiz_question` and `quiz_attempt` records
+#[async_trait]
+pub trait QuizRepository: Send + Sync {
+    /// Find a quiz by record ID
+    async fn find_quiz(&self, id: &Thing) -> Result<Option<Quiz>, AppError>;
+
+    /// List the quizzes of a section in display order
+    async fn list_quizzes(&self, section: &Thing) -> Result<Vec<Quiz>, AppError>;
+
+    /// Insert a new quiz and return the stored record
+    async fn create_quiz(&self, quiz: Quiz) -> Result<Quiz, AppError>;
+
+    /// List the questions of a quiz in display order
+    async fn list_questions(&self, quiz: &Thing) -> Result<Vec<QuizQuestion>, AppError>;
+
+    /// Insert a new question and return the stored record
+    async fn create_question(&self, question: QuizQuestion) -> Result<QuizQuestion, AppError>;
+
+    /// Store a student's attempt and return the stored record
+    async fn create_attempt(&self, attempt: QuizAttempt) -> Result<QuizAttempt, AppError>;
+
+    /// List a student's attempts at a quiz, newest first
+    async fn list_attempts(&self, student: &Thing, quiz: &Thing) -> Result<Vec<QuizAttempt>, AppError>;
+}
+
+#[async_trait]
+impl<C: Connection> QuizRepository for SurrealRepository<C> {
+    async fn find_quiz(&self, id: &Thing) -> Result<Option<Quiz>, AppError> {
+        let quiz = self
+            .query("SELECT * FROM $id")
+            .bind(("id", id.clone()))
+            .await?
+            .take::<Option<Quiz>>(0)?;
+
+        Ok(quiz)
+    }
+
+    async fn list_quizzes(&self, section: &Thing) -> Result<Vec<Quiz>, AppError> {
+        let quizzes = self
+            .query("SELECT * FROM quiz WHERE section = $section ORDER BY order_index ASC")
+            .bind(("section", section.clone()))
+            .await?
+            .take::<Vec<Quiz>>(0)?;
+
+        Ok(quizzes)
+    }
+
+    async fn create_quiz(&self, quiz: Quiz) -> Result<Quiz, AppError> {
+        let created = self
+            .query("CREATE quiz CONTENT $data RETURN *")
+            .bind(("data", quiz))
+            .await?
+            .take::<Option<Quiz>>(0)?;
+
+        created.ok_or_else(|| AppError::Internal("Quiz created but not returned".to_string()))
+    }
+
+    async fn list_questions(&self, quiz: &Thing) -> Result<Vec<QuizQuestion>, AppError> {
+        let questions = self
+            .query("SELECT * FROM quiz_question WHERE quiz = $quiz ORDER BY order_index ASC")
+            .bind(("quiz", quiz.clone()))
+            .await?
+            .take::<Vec<QuizQuestion>>(0)?;
+
+        Ok(questions)
+    }
+
+    async fn create_question(&self, question: QuizQuestion) -> Result<QuizQuestion, AppError> {
+        let created = self
+            .query("CREATE quiz_question CONTENT $data RETURN *")
+            .bind(("data", question))
+            .await?
+            .take::<Option<QuizQuestion>>(0)?;
+
+        created.ok_or_else(|| AppError::Internal("Question created but not returned".to_string()))
+    }
+
+    async fn create_attempt(&self, attempt: QuizAttempt) -> Result<QuizAttempt, AppError> {
+        let created = self
+            .query("CREATE quiz_attempt CONTENT $data RETURN *")
+            .bind(("data", attempt))
+            .await?
+            .take::<Option<QuizAttempt>>(0)?;
+
+        created.ok_or_else(|| AppError::Internal("Quiz attempt created but not returned".to_string()))
+    }
+
+    async fn list_attempts(&self, student: &Thing, quiz: &Thing) -> Result<Vec<QuizAttempt>, AppError> {
+        let attempts = self
+            .query("SELECT * FROM quiz_attempt WHERE student = $student AND quiz = $quiz ORDER BY started_at DESC")
+            .bind(("student", student.clone()))
+            .bind(("quiz", quiz.clone()))
+            .await?
+            .take::<Vec<QuizAttempt>>(0)?;
+
+        Ok(attempts)
+    }
+}
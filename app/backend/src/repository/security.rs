@@ -0,0 +1,135 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use surrealdb::Connection;
+
+use crate::common::error::AppError;
+use crate::models::security::{LoginThrottle, SecurityEvent};
+use crate::repository::SurrealRepository;
+
+/// Access to `login_throttle` and `security_event` records
+#[async_trait]
+pub trait SecurityRepository: Send + Sync {
+    /// Find the failed login counter for a throttle key
+    async fn find_login_throttle(&self, key: &str) -> Result<Option<LoginThrottle>, AppError>;
+
+    /// Count a failed login against a throttle key, returning the updated counter.
+    ///
+    /// The count starts again from one when the previous failure was before `since`.
+    async fn record_login_failure(&self, key: &str, at: DateTime<Utc>, since: DateTime<Utc>) -> Result<LoginThrottle, AppError>;
+
+    /// Refuse logins for a throttle key until at least `until`
+    async fn delay_login(&self, key: &str, until: DateTime<Utc>) -> Result<(), AppError>;
+
+    /// Lock a throttle key until `until`.
+    ///
+    /// Returns `false` if it was already locked, so concurrent failures report one lockout.
+    async fn lock_login(&self, key: &str, until: DateTime<Utc>) -> Result<bool, AppError>;
+
+    /// Clear the lock on a throttle key once it has expired.
+    ///
+    /// Returns `true` only for the call that cleared it.
+    async fn release_expired_lock(&self, key: &str, at: DateTime<Utc>) -> Result<bool, AppError>;
+
+    /// Forget every failed login for a throttle key, returning the counter that was removed
+    async fn clear_login_failures(&self, key: &str) -> Result<Option<LoginThrottle>, AppError>;
+
+    /// Append an event to the security log
+    async fn record_security_event(&self, event: SecurityEvent) -> Result<SecurityEvent, AppError>;
+
+    /// List security events, newest first
+    async fn list_security_events(&self, limit: usize, offset: usize) -> Result<Vec<SecurityEvent>, AppError>;
+}
+
+#[async_trait]
+impl<C: Connection> SecurityRepository for SurrealRepository<C> {
+    async fn find_login_throttle(&self, key: &str) -> Result<Option<LoginThrottle>, AppError> {
+        let throttle = self
+            .query("SELECT * FROM type::thing('login_throttle', $key)")
+            .bind(("key", key.to_string()))
+            .await?
+            .take::<Option<LoginThrottle>>(0)?;
+
+        Ok(throttle)
+    }
+
+    async fn record_login_failure(&self, key: &str, at: DateTime<Utc>, since: DateTime<Utc>) -> Result<LoginThrottle, AppError> {
+        let throttle = self
+            .query(
+                "UPSERT type::thing('login_throttle', $key) SET \
+                     failures = IF last_failure_at > $since { failures + 1 } ELSE { 1 }, \
+                     last_failure_at = $at \
+                 RETURN AFTER",
+            )
+            .bind(("key", key.to_string()))
+            .bind(("at", at.timestamp()))
+            .bind(("since", since.timestamp()))
+            .await?
+            .take::<Option<LoginThrottle>>(0)?;
+
+        throttle.ok_or_else(|| AppError::Internal("Login failure recorded but not returned".to_string()))
+    }
+
+    async fn delay_login(&self, key: &str, until: DateTime<Utc>) -> Result<(), AppError> {
+        self
+            .query("UPDATE type::thing('login_throttle', $key) SET blocked_until = math::max([blocked_until, $until])")
+            .bind(("key", key.to_string()))
+            .bind(("until", until.timestamp()))
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+
+    async fn lock_login(&self, key: &str, until: DateTime<Utc>) -> Result<bool, AppError> {
+        let locked = self
+            .query("UPDATE type::thing('login_throttle', $key) SET locked = true, blocked_until = $until WHERE locked = false RETURN AFTER")
+            .bind(("key", key.to_string()))
+            .bind(("until", until.timestamp()))
+            .await?
+            .take::<Option<LoginThrottle>>(0)?;
+
+        Ok(locked.is_some())
+    }
+
+    async fn release_expired_lock(&self, key: &str, at: DateTime<Utc>) -> Result<bool, AppError> {
+        let released = self
+            .query("UPDATE type::thing('login_throttle', $key) SET locked = false WHERE locked = true AND blocked_until <= $at RETURN AFTER")
+            .bind(("key", key.to_string()))
+            .bind(("at", at.timestamp()))
+            .await?
+            .take::<Option<LoginThrottle>>(0)?;
+
+        Ok(released.is_some())
+    }
+
+    async fn clear_login_failures(&self, key: &str) -> Result<Option<LoginThrottle>, AppError> {
+        let removed = self
+            .query("DELETE type::thing('login_throttle', $key) RETURN BEFORE")
+            .bind(("key", key.to_string()))
+            .await?
+            .take::<Option<LoginThrottle>>(0)?;
+
+        Ok(removed)
+    }
+
+    async fn record_security_event(&self, event: SecurityEvent) -> Result<SecurityEvent, AppError> {
+        let created = self
+            .query("CREATE security_event CONTENT $data RETURN *")
+            .bind(("data", event))
+            .await?
+            .take::<Option<SecurityEvent>>(0)?;
+
+        created.ok_or_else(|| AppError::Internal("Security event created but not returned".to_string()))
+    }
+
+    async fn list_security_events(&self, limit: usize, offset: usize) -> Result<Vec<SecurityEvent>, AppError> {
+        let events = self
+            .query("SELECT * FROM security_event ORDER BY created_at DESC LIMIT $limit START $offset")
+            .bind(("limit", limit))
+            .bind(("offset", offset))
+            .await?
+            .take::<Vec<SecurityEvent>>(0)?;
+
+        Ok(events)
+    }
+}
@@ -0,0 +1,85 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use surrealdb::sql::Thing;
+use surrealdb::Connection;
+
+use crate::common::error::AppError;
+use crate::models::submission::{CodeSubmission, ReviewSubmissionRequest, TestCase};
+use crate::repository::SurrealRepository;
+
+/// Access to `code_submission` and `test_case` records
+#[async_trait]
+pub trait SubmissionRepository: Send + Sync {
+    /// Find a submission by record ID
+    async fn find_submission(&self, id: &Thing) -> Result<Option<CodeSubmission>, AppError>;
+
+    /// List a student's submissions, newest first
+    async fn list_submissions(&self, student: &Thing) -> Result<Vec<CodeSubmission>, AppError>;
+
+    /// Insert a new submission and return the stored record
+    async fn create_submission(&self, submission: CodeSubmission) -> Result<CodeSubmission, AppError>;
+
+    /// Record a reviewer's verdict, returning the updated submission if it exists
+    async fn review_submission(&self, id: &Thing, review: &ReviewSubmissionRequest, reviewer: &Thing) -> Result<Option<CodeSubmission>, AppError>;
+
+    /// List the test cases of an assignment
+    async fn list_test_cases(&self, assignment_id: &str) -> Result<Vec<TestCase>, AppError>;
+}
+
+#[async_trait]
+impl<C: Connection> SubmissionRepository for SurrealRepository<C> {
+    async fn find_submission(&self, id: &Thing) -> Result<Option<CodeSubmission>, AppError> {
+        let submission = self
+            .query("SELECT * FROM $id")
+            .bind(("id", id.clone()))
+            .await?
+            .take::<Option<CodeSubmission>>(0)?;
+
+        Ok(submission)
+    }
+
+    async fn list_submissions(&self, student: &Thing) -> Result<Vec<CodeSubmission>, AppError> {
+        let submissions = self
+            .query("SELECT * FROM code_submission WHERE student = $student ORDER BY submitted_at DESC")
+            .bind(("student", student.clone()))
+            .await?
+            .take::<Vec<CodeSubmission>>(0)?;
+
+        Ok(submissions)
+    }
+
+    async fn create_submission(&self, submission: CodeSubmission) -> Result<CodeSubmission, AppError> {
+        let created = self
+            .query("CREATE code_submission CONTENT $data RETURN *")
+            .bind(("data", submission))
+            .await?
+            .take::<Option<CodeSubmission>>(0)?;
+
+        created.ok_or_else(|| AppError::Internal("Submission created but not returned".to_string()))
+    }
+
+    async fn review_submission(&self, id: &Thing, review: &ReviewSubmissionRequest, reviewer: &Thing) -> Result<Option<CodeSubmission>, AppError> {
+        let submission = self
+            .query("UPDATE $id SET status = $status, feedback = $feedback, reviewed_by = $reviewer, reviewed_at = $reviewed_at RETURN AFTER")
+            .bind(("id", id.clone()))
+            .bind(("status", review.status.clone()))
+            .bind(("feedback", review.feedback.clone()))
+            .bind(("reviewer", reviewer.clone()))
+            .bind(("reviewed_at", Utc::now().timestamp()))
+            .await?
+            .take::<Option<CodeSubmission>>(0)?;
+
+        Ok(submission)
+    }
+
+    async fn list_test_cases(&self, assignment_id: &str) -> Result<Vec<TestCase>, AppError> {
+        // Test cases expose their bare record key as `id`
+        let test_cases = self
+            .query("SELECT *, record::id(id) AS id FROM test_case WHERE assignment_id = $assignment_id")
+            .bind(("assignment_id", assignment_id.to_string()))
+            .await?
+            .take::<Vec<TestCase>>(0)?;
+
+        Ok(test_cases)
+    }
+}
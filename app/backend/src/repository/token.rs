@@ -0,0 +1,389 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use surrealdb::sql::Thing;
+use surrealdb::Connection;
+
+use crate::common::error::AppError;
+use crate::models::session::{ClientInfo, Session};
+use crate::models::token::{PasswordReset, PersonalToken, RefreshToken};
+use crate::repository::SurrealRepository;
+
+/// Access to `refresh_token`, `revoked_token`, `token_cutoff`, `password_reset`,
+/// `personal_token` and `session` records
+#[async_trait]
+pub trait TokenRepository: Send + Sync {
+    /// Store a newly issued refresh token
+    async fn create_refresh_token(&self, token: RefreshToken) -> Result<RefreshToken, AppError>;
+
+    /// Find a refresh token by the hash of its value
+    async fn find_refresh_token(&self, token_hash: &str) -> Result<Option<RefreshToken>, AppError>;
+
+    /// Mark an unused, unrevoked refresh token as used.
+    ///
+    /// Returns `None` if the token was already used or revoked, so two concurrent
+    /// refreshes with the same token cannot both succeed.
+    async fn consume_refresh_token(&self, token_hash: &str, at: DateTime<Utc>) -> Result<Option<RefreshToken>, AppError>;
+
+    /// Revoke every refresh token in a rotation family
+    async fn revoke_refresh_family(&self, family: &str, at: DateTime<Utc>) -> Result<(), AppError>;
+
+    /// Revoke every refresh token of a user
+    async fn revoke_user_refresh_tokens(&self, user: &Thing, at: DateTime<Utc>) -> Result<(), AppError>;
+
+    /// Add an access token to the revocation list until it expires
+    async fn revoke_access_token(&self, jti: &str, expires_at: DateTime<Utc>) -> Result<(), AppError>;
+
+    /// Reject every access token of a user issued up to `at`
+    async fn revoke_user_access_tokens(&self, user: &Thing, at: DateTime<Utc>) -> Result<(), AppError>;
+
+    /// Is the access token with this `jti`, issued to `user` at `issued_at` (unix seconds), revoked?
+    async fn is_access_token_revoked(&self, jti: &str, user: &Thing, issued_at: u64) -> Result<bool, AppError>;
+
+    /// Store a newly issued password reset token
+    async fn create_password_reset(&self, reset: PasswordReset) -> Result<PasswordReset, AppError>;
+
+    /// Find a password reset token by the hash of its value
+    async fn find_password_reset(&self, token_hash: &str) -> Result<Option<PasswordReset>, AppError>;
+
+    /// Mark an unused, unexpired password reset token as used.
+    ///
+    /// Returns `None` if the token is unknown, expired or was already used.
+    async fn consume_password_reset(&self, token_hash: &str, at: DateTime<Utc>) -> Result<Option<PasswordReset>, AppError>;
+
+    /// Mark every outstanding password reset token of a user as used
+    async fn invalidate_password_resets(&self, user: &Thing, at: DateTime<Utc>) -> Result<(), AppError>;
+
+    /// Store a newly issued personal access token
+    async fn create_personal_token(&self, token: PersonalToken) -> Result<PersonalToken, AppError>;
+
+    /// Find a personal access token by the hash of its value
+    async fn find_personal_token(&self, token_hash: &str) -> Result<Option<PersonalToken>, AppError>;
+
+    /// List a user's unrevoked personal access tokens, newest first
+    async fn list_personal_tokens(&self, user: &Thing) -> Result<Vec<PersonalToken>, AppError>;
+
+    /// Revoke one of a user's personal access tokens, returning `false` if they have no such token
+    async fn revoke_personal_token(&self, user: &Thing, id: &Thing, at: DateTime<Utc>) -> Result<bool, AppError>;
+
+    /// Revoke every personal access token of a user
+    async fn revoke_user_personal_tokens(&self, user: &Thing, at: DateTime<Utc>) -> Result<(), AppError>;
+
+    /// Record that a personal access token was used, at most once a minute to spare writes
+    async fn touch_personal_token(&self, id: &Thing, at: DateTime<Utc>) -> Result<(), AppError>;
+
+    /// Store a newly opened session
+    async fn create_session(&self, session: Session) -> Result<Session, AppError>;
+
+    /// Find a session by ID
+    async fn find_session(&self, id: &Thing) -> Result<Option<Session>, AppError>;
+
+    /// List a user's unrevoked, unexpired sessions, most recently seen first
+    async fn list_sessions(&self, user: &Thing, at: DateTime<Utc>) -> Result<Vec<Session>, AppError>;
+
+    /// Record a refresh of an unrevoked session, from `client`, extending it to `expires_at`
+    async fn refresh_session(&self, id: &Thing, client: &ClientInfo, at: DateTime<Utc>, expires_at: DateTime<Utc>) -> Result<(), AppError>;
+
+    /// Record that a session was seen, at most once a minute to spare writes
+    async fn touch_session(&self, id: &Thing, at: DateTime<Utc>) -> Result<(), AppError>;
+
+    /// Revoke one of a user's sessions and its refresh tokens, returning `false` if they have no such session
+    async fn revoke_session(&self, user: &Thing, id: &Thing, at: DateTime<Utc>) -> Result<bool, AppError>;
+
+    /// Revoke every session of a user; their refresh and access tokens are revoked separately
+    async fn revoke_user_sessions(&self, user: &Thing, at: DateTime<Utc>) -> Result<(), AppError>;
+}
+
+#[async_trait]
+impl<C: Connection> TokenRepository for SurrealRepository<C> {
+    async fn create_refresh_token(&self, token: RefreshToken) -> Result<RefreshToken, AppError> {
+        let created = self
+            .query("CREATE refresh_token CONTENT $data RETURN *")
+            .bind(("data", token))
+            .await?
+            .take::<Option<RefreshToken>>(0)?;
+
+        created.ok_or_else(|| AppError::Internal("Refresh token created but not returned".to_string()))
+    }
+
+    async fn find_refresh_token(&self, token_hash: &str) -> Result<Option<RefreshToken>, AppError> {
+        let token = self
+            .query("SELECT * FROM refresh_token WHERE token_hash = $token_hash LIMIT 1")
+            .bind(("token_hash", token_hash.to_string()))
+            .await?
+            .take::<Option<RefreshToken>>(0)?;
+
+        Ok(token)
+    }
+
+    async fn consume_refresh_token(&self, token_hash: &str, at: DateTime<Utc>) -> Result<Option<RefreshToken>, AppError> {
+        let token = self
+            .query("UPDATE refresh_token SET used_at = $at WHERE token_hash = $token_hash AND used_at = NONE AND revoked_at = NONE RETURN AFTER")
+            .bind(("token_hash", token_hash.to_string()))
+            .bind(("at", at.timestamp()))
+            .await?
+            .take::<Option<RefreshToken>>(0)?;
+
+        Ok(token)
+    }
+
+    async fn revoke_refresh_family(&self, family: &str, at: DateTime<Utc>) -> Result<(), AppError> {
+        self
+            .query("UPDATE refresh_token SET revoked_at = $at WHERE family = $family AND revoked_at = NONE")
+            .bind(("family", family.to_string()))
+            .bind(("at", at.timestamp()))
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+
+    async fn revoke_user_refresh_tokens(&self, user: &Thing, at: DateTime<Utc>) -> Result<(), AppError> {
+        self
+            .query("UPDATE refresh_token SET revoked_at = $at WHERE user = $user AND revoked_at = NONE")
+            .bind(("user", user.clone()))
+            .bind(("at", at.timestamp()))
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+
+    async fn revoke_access_token(&self, jti: &str, expires_at: DateTime<Utc>) -> Result<(), AppError> {
+        // Entries are only needed until the token would have expired anyway, so prune old ones here
+        self
+            .query("DELETE revoked_token WHERE expires_at < $now; UPSERT type::thing('revoked_token', $jti) SET expires_at = $expires_at")
+            .bind(("now", Utc::now().timestamp()))
+            .bind(("jti", jti.to_string()))
+            .bind(("expires_at", expires_at.timestamp()))
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+
+    async fn revoke_user_access_tokens(&self, user: &Thing, at: DateTime<Utc>) -> Result<(), AppError> {
+        self
+            .query("UPSERT type::thing('token_cutoff', record::id($user)) SET revoked_before = $at")
+            .bind(("user", user.clone()))
+            .bind(("at", at.timestamp()))
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+
+    async fn is_access_token_revoked(&self, jti: &str, user: &Thing, issued_at: u64) -> Result<bool, AppError> {
+        let mut response = self
+            .query("SELECT VALUE id FROM type::thing('revoked_token', $jti)")
+            .query("SELECT VALUE revoked_before FROM type::thing('token_cutoff', record::id($user))")
+            .bind(("jti", jti.to_string()))
+            .bind(("user", user.clone()))
+            .await?;
+
+        let revoked = response.take::<Option<Thing>>(0)?;
+        let revoked_before = response.take::<Option<u64>>(1)?;
+
+        // `iat` has one-second resolution, so a token from the same second as the cutoff is rejected too
+        Ok(revoked.is_some() || revoked_before.is_some_and(|cutoff| issued_at <= cutoff))
+    }
+
+    async fn create_password_reset(&self, reset: PasswordReset) -> Result<PasswordReset, AppError> {
+        let created = self
+            .query("CREATE password_reset CONTENT $data RETURN *")
+            .bind(("data", reset))
+            .await?
+            .take::<Option<PasswordReset>>(0)?;
+
+        created.ok_or_else(|| AppError::Internal("Password reset created but not returned".to_string()))
+    }
+
+    async fn find_password_reset(&self, token_hash: &str) -> Result<Option<PasswordReset>, AppError> {
+        let reset = self
+            .query("SELECT * FROM password_reset WHERE token_hash = $token_hash LIMIT 1")
+            .bind(("token_hash", token_hash.to_string()))
+            .await?
+            .take::<Option<PasswordReset>>(0)?;
+
+        Ok(reset)
+    }
+
+    async fn consume_password_reset(&self, token_hash: &str, at: DateTime<Utc>) -> Result<Option<PasswordReset>, AppError> {
+        let reset = self
+            .query("UPDATE password_reset SET used_at = $at WHERE token_hash = $token_hash AND used_at = NONE AND expires_at > $at RETURN AFTER")
+            .bind(("token_hash", token_hash.to_string()))
+            .bind(("at", at.timestamp()))
+            .await?
+            .take::<Option<PasswordReset>>(0)?;
+
+        Ok(reset)
+    }
+
+    async fn invalidate_password_resets(&self, user: &Thing, at: DateTime<Utc>) -> Result<(), AppError> {
+        self
+            .query("UPDATE password_reset SET used_at = $at WHERE user = $user AND used_at = NONE")
+            .bind(("user", user.clone()))
+            .bind(("at", at.timestamp()))
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+
+    async fn create_personal_token(&self, token: PersonalToken) -> Result<PersonalToken, AppError> {
+        let created = self
+            .query("CREATE personal_token CONTENT $data RETURN *")
+            .bind(("data", token))
+            .await?
+            .take::<Option<PersonalToken>>(0)?;
+
+        created.ok_or_else(|| AppError::Internal("Personal access token created but not returned".to_string()))
+    }
+
+    async fn find_personal_token(&self, token_hash: &str) -> Result<Option<PersonalToken>, AppError> {
+        let token = self
+            .query("SELECT * FROM personal_token WHERE token_hash = $token_hash LIMIT 1")
+            .bind(("token_hash", token_hash.to_string()))
+            .await?
+            .take::<Option<PersonalToken>>(0)?;
+
+        Ok(token)
+    }
+
+    async fn list_personal_tokens(&self, user: &Thing) -> Result<Vec<PersonalToken>, AppError> {
+        let tokens = self
+            .query("SELECT * FROM personal_token WHERE user = $user AND revoked_at = NONE ORDER BY created_at DESC")
+            .bind(("user", user.clone()))
+            .await?
+            .take::<Vec<PersonalToken>>(0)?;
+
+        Ok(tokens)
+    }
+
+    async fn revoke_personal_token(&self, user: &Thing, id: &Thing, at: DateTime<Utc>) -> Result<bool, AppError> {
+        let revoked = self
+            .query("UPDATE $id SET revoked_at = $at WHERE user = $user AND revoked_at = NONE RETURN AFTER")
+            .bind(("id", id.clone()))
+            .bind(("user", user.clone()))
+            .bind(("at", at.timestamp()))
+            .await?
+            .take::<Option<PersonalToken>>(0)?;
+
+        Ok(revoked.is_some())
+    }
+
+    async fn revoke_user_personal_tokens(&self, user: &Thing, at: DateTime<Utc>) -> Result<(), AppError> {
+        self
+            .query("UPDATE personal_token SET revoked_at = $at WHERE user = $user AND revoked_at = NONE")
+            .bind(("user", user.clone()))
+            .bind(("at", at.timestamp()))
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+
+    async fn touch_personal_token(&self, id: &Thing, at: DateTime<Utc>) -> Result<(), AppError> {
+        self
+            .query("UPDATE $id SET last_used_at = $at WHERE last_used_at = NONE OR last_used_at < $stale")
+            .bind(("id", id.clone()))
+            .bind(("at", at.timestamp()))
+            .bind(("stale", at.timestamp() - 60))
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+
+    async fn create_session(&self, session: Session) -> Result<Session, AppError> {
+        let id = session
+            .id
+            .clone()
+            .ok_or_else(|| AppError::Internal("Session has no ID".to_string()))?;
+
+        let created = self
+            .query("CREATE $id CONTENT $data RETURN *")
+            .bind(("id", id))
+            .bind(("data", session))
+            .await?
+            .take::<Option<Session>>(0)?;
+
+        created.ok_or_else(|| AppError::Internal("Session created but not returned".to_string()))
+    }
+
+    async fn find_session(&self, id: &Thing) -> Result<Option<Session>, AppError> {
+        let session = self
+            .query("SELECT * FROM $id")
+            .bind(("id", id.clone()))
+            .await?
+            .take::<Option<Session>>(0)?;
+
+        Ok(session)
+    }
+
+    async fn list_sessions(&self, user: &Thing, at: DateTime<Utc>) -> Result<Vec<Session>, AppError> {
+        let sessions = self
+            .query("SELECT * FROM session WHERE user = $user AND revoked_at = NONE AND expires_at > $at ORDER BY last_seen_at DESC")
+            .bind(("user", user.clone()))
+            .bind(("at", at.timestamp()))
+            .await?
+            .take::<Vec<Session>>(0)?;
+
+        Ok(sessions)
+    }
+
+    async fn refresh_session(&self, id: &Thing, client: &ClientInfo, at: DateTime<Utc>, expires_at: DateTime<Utc>) -> Result<(), AppError> {
+        self
+            .query(
+                "UPDATE $id SET last_seen_at = $at, expires_at = $expires_at, device = $device, \
+                 user_agent = $user_agent, ip_address = $ip_address WHERE revoked_at = NONE",
+            )
+            .bind(("id", id.clone()))
+            .bind(("at", at.timestamp()))
+            .bind(("expires_at", expires_at.timestamp()))
+            .bind(("device", client.device()))
+            .bind(("user_agent", client.user_agent.clone()))
+            .bind(("ip_address", client.ip_address.clone()))
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+
+    async fn touch_session(&self, id: &Thing, at: DateTime<Utc>) -> Result<(), AppError> {
+        self
+            .query("UPDATE $id SET last_seen_at = $at WHERE revoked_at = NONE AND last_seen_at < $stale")
+            .bind(("id", id.clone()))
+            .bind(("at", at.timestamp()))
+            .bind(("stale", at.timestamp() - 60))
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+
+    async fn revoke_session(&self, user: &Thing, id: &Thing, at: DateTime<Utc>) -> Result<bool, AppError> {
+        // The session's ID is its refresh token family
+        let revoked = self
+            .query("UPDATE $id SET revoked_at = $at WHERE user = $user AND revoked_at = NONE RETURN AFTER")
+            .query("UPDATE refresh_token SET revoked_at = $at WHERE family = record::id($id) AND user = $user AND revoked_at = NONE")
+            .bind(("id", id.clone()))
+            .bind(("user", user.clone()))
+            .bind(("at", at.timestamp()))
+            .await?
+            .check()?
+            .take::<Option<Session>>(0)?;
+
+        Ok(revoked.is_some())
+    }
+
+    async fn revoke_user_sessions(&self, user: &Thing, at: DateTime<Utc>) -> Result<(), AppError> {
+        self
+            .query("UPDATE session SET revoked_at = $at WHERE user = $user AND revoked_at = NONE")
+            .bind(("user", user.clone()))
+            .bind(("at", at.timestamp()))
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use surrealdb::sql::Thing;
+use surrealdb::Connection;
+
+use crate::common::error::{AppError, ErrorCode};
+use crate::models::user::User;
+use crate::repository::{is_unique_violation, SurrealRepository};
+
+/// Access to `user` records
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    /// Find a user by record ID
+    async fn find_user(&self, id: &Thing) -> Result<Option<User>, AppError>;
+
+    /// Find a user by email address
+    async fn find_user_by_email(&self, email: &str) -> Result<Option<User>, AppError>;
+
+    /// Insert a new user and return the stored record
+    async fn create_user(&self, user: User) -> Result<User, AppError>;
+
+    /// Record a successful login
+    async fn touch_last_login(&self, id: &Thing, at: DateTime<Utc>) -> Result<(), AppError>;
+
+    /// Mark a user's email address as verified
+    async fn mark_email_verified(&self, id: &Thing) -> Result<(), AppError>;
+
+    /// Replace a user's password hash
+    async fn update_password(&self, id: &Thing, password_hash: &str) -> Result<(), AppError>;
+}
+
+#[async_trait]
+impl<C: Connection> UserRepository for SurrealRepository<C> {
+    async fn find_user(&self, id: &Thing) -> Result<Option<User>, AppError> {
+        let user = self
+            .query("SELECT * FROM $id")
+            .bind(("id", id.clone()))
+            .await?
+            .take::<Option<User>>(0)?;
+
+        Ok(user)
+    }
+
+    async fn find_user_by_email(&self, email: &str) -> Result<Option<User>, AppError> {
+        let user = self
+            .query("SELECT * FROM user WHERE email = $email LIMIT 1")
+            .bind(("email", email.to_string()))
+            .await?
+            .take::<Option<User>>(0)?;
+
+        Ok(user)
+    }
+
+    async fn create_user(&self, user: User) -> Result<User, AppError> {
+        let created = match self
+            .query("CREATE user CONTENT $data RETURN *")
+            .bind(("data", user))
+            .await
+        {
+            Ok(mut response) => response.take::<Option<User>>(0),
+            Err(err) => Err(err),
+        };
+
+        match created {
+            Ok(Some(user)) => Ok(user),
+            Ok(None) => Err(AppError::Internal("Failed to retrieve created user".to_string())),
+            // The unique index on `email` catches registrations that raced past the existence check
+            Err(err) if is_unique_violation(&err) => {
+                Err(AppError::Validation(ErrorCode::EmailTaken, "User with this email already exists".to_string()))
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn touch_last_login(&self, id: &Thing, at: DateTime<Utc>) -> Result<(), AppError> {
+        self
+            .query("UPDATE $id SET last_login = $at")
+            .bind(("id", id.clone()))
+            .bind(("at", at.timestamp()))
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+
+    async fn mark_email_verified(&self, id: &Thing) -> Result<(), AppError> {
+        self
+            .query("UPDATE $id SET email_verified = true")
+            .bind(("id", id.clone()))
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+
+    async fn update_password(&self, id: &Thing, password_hash: &str) -> Result<(), AppError> {
+        self
+            .query("UPDATE $id SET password = $password")
+            .bind(("id", id.clone()))
+            .bind(("password", password_hash.to_string()))
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use async_trait::async_trait;
+use surrealdb::sql::Thing;
+use surrealdb::Connection;
+
+use crate::common::error::AppError;
+use crate::models::token::EmailVerification;
+use crate::repository::SurrealRepository;
+
+/// Access to `email_verification` records
+#[async_trait]
+pub trait VerificationRepository: Send + Sync {
+    /// Store a new code for a user, replacing any outstanding one
+    async fn replace_email_verification(&self, verification: EmailVerification) -> Result<EmailVerification, AppError>;
+
+    /// Find a user's outstanding code
+    async fn find_email_verification(&self, user: &Thing) -> Result<Option<EmailVerification>, AppError>;
+
+    /// Count a guess against a user's code.
+    ///
+    /// Returns the updated record, or `None` if there is no code or its attempts are used up.
+    async fn record_verification_attempt(&self, user: &Thing, max_attempts: u32) -> Result<Option<EmailVerification>, AppError>;
+
+    /// Remove a user's code once it has been used
+    async fn delete_email_verification(&self, user: &Thing) -> Result<(), AppError>;
+}
+
+#[async_trait]
+impl<C: Connection> VerificationRepository for SurrealRepository<C> {
+    async fn replace_email_verification(&self, verification: EmailVerification) -> Result<EmailVerification, AppError> {
+        let created = self
+            .query("DELETE email_verification WHERE user = $user")
+            .query("CREATE email_verification CONTENT $data RETURN *")
+            .bind(("user", verification.user.clone()))
+            .bind(("data", verification))
+            .await?
+            .take::<Option<EmailVerification>>(1)?;
+
+        created.ok_or_else(|| AppError::Internal("Email verification created but not returned".to_string()))
+    }
+
+    async fn find_email_verification(&self, user: &Thing) -> Result<Option<EmailVerification>, AppError> {
+        let verification = self
+            .query("SELECT * FROM email_verification WHERE user = $user LIMIT 1")
+            .bind(("user", user.clone()))
+            .await?
+            .take::<Option<EmailVerification>>(0)?;
+
+        Ok(verification)
+    }
+
+    async fn record_verification_attempt(&self, user: &Thing, max_attempts: u32) -> Result<Option<EmailVerification>, AppError> {
+        let verification = self
+            .query("UPDATE email_verification SET attempts += 1 WHERE user = $user AND attempts < $max_attempts RETURN AFTER")
+            .bind(("user", user.clone()))
+            .bind(("max_attempts", max_attempts))
+            .await?
+            .take::<Option<EmailVerification>>(0)?;
+
+        Ok(verification)
+    }
+
+    async fn delete_email_verification(&self, user: &Thing) -> Result<(), AppError> {
+        self
+            .query("DELETE email_verification WHERE user = $user")
+            .bind(("user", user.clone()))
+            .await?
+            .check()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+//! Helpers shared by the handler tests, which run against the in-memory repository.
+
+use std::sync::{Arc, Mutex};
+
+use aws_lambda_events::encodings::Body;
+use aws_lambda_events::event::apigw::{ApiGatewayProxyRequest, ApiGatewayProxyResponse};
+use surrealdb::sql::Thing;
+
+use crate::common::auth;
+use crate::common::error::AppError;
+use crate::common::extract::AuthContext;
+use crate::common::mailer::{Email, Mailer};
+use crate::identity::NativeIdentityProvider;
+use crate::models::course::{Course, CourseDifficulty};
+use crate::models::session::ClientInfo;
+use crate::models::user::UserRole;
+use crate::repository::memory::MemoryRepository;
+
+/// A course by `educator` with placeholder details
+pub fn course(title: &str, educator: &Thing, is_published: bool) -> Course {
+    let mut course = Course::new(
+        title.to_string(),
+        format!("{} description", title),
+        CourseDifficulty::Beginner,
+        vec!["rust".to_string()],
+        educator.clone(),
+        String::new(),
+        1.0,
+    );
+    course.is_published = is_published;
+    course
+}
+
+/// Mailer that keeps sent messages for inspection
+#[derive(Default)]
+pub struct RecordingMailer {
+    pub sent: Mutex<Vec<Email>>,
+}
+
+#[async_trait::async_trait]
+impl Mailer for RecordingMailer {
+    async fn send(&self, email: &Email) -> Result<(), AppError> {
+        self.sent.lock().unwrap().push(email.clone());
+        Ok(())
+    }
+}
+
+/// A native identity provider over `repo` that records outgoing mail
+pub fn native_identity(repo: &MemoryRepository) -> (NativeIdentityProvider<MemoryRepository>, Arc<RecordingMailer>) {
+    let mailer = Arc::new(RecordingMailer::default());
+    (NativeIdentityProvider::new(repo.clone(), mailer.clone()), mailer)
+}
+
+pub fn json_request(body: serde_json::Value) -> ApiGatewayProxyRequest {
+    ApiGatewayProxyRequest {
+        body: Some(body.to_string()),
+        ..Default::default()
+    }
+}
+
+/// The six-digit code in the `index`th sent email, plus a code that differs from it
+pub fn sent_code(mailer: &RecordingMailer, index: usize) -> (String, &'static str) {
+    let body = mailer.sent.lock().unwrap()[index].body.clone();
+    let code = body
+        .split_whitespace()
+        .find(|word| word.len() == 6 && word.chars().all(|c| c.is_ascii_digit()))
+        .unwrap()
+        .to_string();
+    let wrong = if code == "000000" { "111111" } else { "000000" };
+    (code, wrong)
+}
+
+pub fn response_json(response: &ApiGatewayProxyResponse) -> serde_json::Value {
+    match &response.body {
+        Some(Body::Text(body)) => serde_json::from_str(body).unwrap(),
+        other => panic!("unexpected response body: {:?}", other),
+    }
+}
+
+/// Build a request the router has already authenticated, in a new session
+pub async fn request_as(repo: &MemoryRepository, user: &Thing, role: UserRole) -> ApiGatewayProxyRequest {
+    let tokens = auth::open_session(repo, user, &role.to_string(), &ClientInfo::default()).await.unwrap();
+    request_with(repo, &tokens.token).await
+}
+
+/// Build a request the router has already authenticated with `token`
+pub async fn request_with(repo: &MemoryRepository, token: &str) -> ApiGatewayProxyRequest {
+    let mut request = ApiGatewayProxyRequest::default();
+    request
+        .headers
+        .insert("Authorization", format!("Bearer {}", token).parse().unwrap());
+    let context = AuthContext::authenticate(repo, &request).await.unwrap().unwrap();
+    context.attach(&mut request).unwrap();
+    request
+}
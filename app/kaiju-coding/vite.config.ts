@@ -15,8 +15,8 @@ export default defineConfig({
     proxy: {
       // Proxy API requests to the backend during development
       '/api': {
-        // For local development, use localhost:3000
-        target: 'https://11l6evus32.execute-api.ap-southeast-1.amazonaws.com/Stage',
+        // Set KAIJU_API_TARGET=http://localhost:3000 to use the local Rust backend (`cargo run --bin kaiju-local`)
+        target: process.env.KAIJU_API_TARGET || 'https://11l6evus32.execute-api.ap-southeast-1.amazonaws.com/Stage',
         changeOrigin: true,
         rewrite: (path) => path.replace(/^\/api/, ''),
       },